@@ -0,0 +1,13 @@
+//! The UTF-16 text scan used for files that cannot be parsed.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use notebookx_fuzz::text_scan::Utf16Runs;
+
+fuzz_target!(|data: &[u8]| {
+    for run in Utf16Runs::new(data) {
+        if run.is_err() {
+            break;
+        }
+    }
+});
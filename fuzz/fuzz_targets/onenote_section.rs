@@ -0,0 +1,13 @@
+//! Section and table of contents files: the revision store reader and the
+//! mapping of its objects onto pages.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use notebookx_fuzz::conversion_report::ConversionReport;
+use notebookx_fuzz::onenote_converter::OneNoteConverter;
+
+fuzz_target!(|data: &[u8]| {
+    let converter = OneNoteConverter::new();
+    let _ = converter.parse_section(data, None, &mut ConversionReport::default());
+    let _ = converter.parse_toc_entries(data);
+});
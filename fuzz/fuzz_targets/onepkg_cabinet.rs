@@ -0,0 +1,14 @@
+//! Cabinet archives as found in .onepkg notebook packages.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use notebookx_fuzz::cab::Cabinet;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(cabinet) = Cabinet::parse(data) {
+        for entry in &cabinet.entries {
+            let _ = cabinet.compression_name(entry);
+        }
+        let _ = cabinet.extract_all();
+    }
+});
@@ -0,0 +1,19 @@
+//! The OneNote parsing modules of NotebookX, built on their own so the fuzz
+//! targets can call them. NotebookX is a binary crate, so the modules are
+//! compiled from its sources by path.
+#![allow(dead_code)]
+
+#[path = "../../src/cab.rs"]
+pub mod cab;
+#[path = "../../src/conversion_report.rs"]
+pub mod conversion_report;
+#[path = "../../src/drawing.rs"]
+pub mod drawing;
+#[path = "../../src/notebook.rs"]
+pub mod notebook;
+#[path = "../../src/onenote_converter.rs"]
+pub mod onenote_converter;
+#[path = "../../src/onestore.rs"]
+pub mod onestore;
+#[path = "../../src/text_scan.rs"]
+pub mod text_scan;
@@ -0,0 +1,132 @@
+use crate::file_io::NotebookFileHandler;
+use crate::onenote_converter::OneNoteConverter;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Outcome of converting every section file under a directory.
+#[derive(Debug, Default)]
+pub struct BatchSummary {
+    /// Section files that were converted, relative to the input directory
+    pub converted: Vec<PathBuf>,
+    /// Section files that failed, relative to the input directory, with the reason
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl BatchSummary {
+    pub fn total(&self) -> usize {
+        self.converted.len() + self.failed.len()
+    }
+}
+
+/// Convert every .one file below `input_dir` into a NotebookX file at the
+/// same relative path below `output_dir`. A file that fails is recorded in
+/// the summary and the rest are still converted.
+pub fn convert_directory(input_dir: &Path, output_dir: &Path) -> io::Result<BatchSummary> {
+    if !input_dir.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Input directory {} does not exist", input_dir.display()),
+        ));
+    }
+
+    let mut sections = Vec::new();
+    find_sections(input_dir, &mut sections)?;
+    sections.sort();
+
+    let converter = OneNoteConverter::new();
+    // Re-running a migration over its output should not leave backups behind
+    let file_handler = NotebookFileHandler {
+        backups: 0,
+        ..NotebookFileHandler::new()
+    };
+    let mut summary = BatchSummary::default();
+    for section in sections {
+        let relative = section.strip_prefix(input_dir).unwrap_or(&section).to_path_buf();
+        let output_path = output_dir.join(&relative).with_extension("txt");
+
+        let result = converter.convert_to_notebookx(section.clone()).and_then(|mut notebook| {
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            file_handler.save_notebook(&mut notebook, output_path.clone())
+        });
+        match result {
+            Ok(()) => summary.converted.push(relative),
+            Err(e) => summary.failed.push((relative, e.to_string())),
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Collect .one files below `dir`. Symbolic links are not followed.
+fn find_sections(dir: &Path, sections: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+        if file_type.is_dir() {
+            find_sections(&path, sections)?;
+        } else if file_type.is_file()
+            && path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("one"))
+        {
+            sections.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_directory_keeps_layout_and_reports_failures() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/onenote/two_pages.one");
+        let input = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        fs::create_dir_all(input.path().join("Work/Archive")).unwrap();
+        fs::copy(&fixture, input.path().join("Notes.one")).unwrap();
+        fs::copy(&fixture, input.path().join("Work/Archive/Old.ONE")).unwrap();
+        fs::write(input.path().join("Work/Broken.one"), b"not a section").unwrap();
+        fs::write(input.path().join("Work/readme.txt"), b"ignored").unwrap();
+
+        let summary = convert_directory(input.path(), output.path()).unwrap();
+        assert_eq!(summary.total(), 3);
+        assert_eq!(
+            summary.converted,
+            vec![PathBuf::from("Notes.one"), PathBuf::from("Work/Archive/Old.ONE")]
+        );
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].0, PathBuf::from("Work/Broken.one"));
+
+        let (notebook, _) = NotebookFileHandler::new()
+            .load_notebook(output.path().join("Work/Archive/Old.txt"))
+            .unwrap();
+        assert_eq!(notebook.pages.len(), 2);
+        assert_eq!(notebook.pages[0].title, "Meeting Notes");
+        assert!(output.path().join("Notes.txt").is_file());
+        assert!(!output.path().join("Work/Broken.txt").exists());
+        assert!(!output.path().join("Work/readme.txt").exists());
+
+        // Converting again replaces the output without leaving backups
+        convert_directory(input.path(), output.path()).unwrap();
+        let mut files: Vec<_> = fs::read_dir(output.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["Notes.txt", "Work"]);
+    }
+
+    #[test]
+    fn test_convert_directory_requires_input_directory() {
+        let output = tempfile::tempdir().unwrap();
+        let error = convert_directory(&output.path().join("missing"), output.path()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+}
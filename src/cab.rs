@@ -0,0 +1,392 @@
+//! Reader for Microsoft Cabinet (.cab) archives.
+//!
+//! OneNote's "export notebook" writes `.onepkg` files, which are cabinets
+//! holding the notebook's `.onetoc2` and `.one` files. Stored and MSZIP
+//! compressed folders are unpacked in-process; Quantum and LZX folders and
+//! cabinets spanning several files are reported as unsupported.
+
+use miniz_oxide::inflate::core::{decompress, inflate_flags, DecompressorOxide};
+use miniz_oxide::inflate::TINFLStatus;
+use std::io;
+
+const SIGNATURE: &[u8; 4] = b"MSCF";
+
+const FLAG_PREV_CABINET: u16 = 0x0001;
+const FLAG_NEXT_CABINET: u16 = 0x0002;
+const FLAG_RESERVE_PRESENT: u16 = 0x0004;
+
+const ATTRIBUTE_NAME_IS_UTF8: u16 = 0x0080;
+
+const COMPRESSION_NONE: u16 = 0;
+const COMPRESSION_MSZIP: u16 = 1;
+
+/// MSZIP blocks never expand to more than 32 KiB, which is also the size of
+/// the history window carried from one block to the next.
+const MSZIP_BLOCK_SIZE: usize = 32 * 1024;
+
+/// Refuse cabinets whose files, plus the folder data they are cut from,
+/// add up to more than this, to bound memory use. Files may overlap, so a
+/// small directory can claim far more data than the cabinet holds.
+const MAX_UNPACKED_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+
+#[derive(Clone, Debug)]
+pub struct CabEntry {
+    /// Path inside the cabinet, using `/` as separator
+    pub name: String,
+    pub size: u32,
+    folder: u16,
+    offset: u32,
+}
+
+#[derive(Clone, Debug)]
+struct CabFolder {
+    data_offset: u32,
+    block_count: u16,
+    compression: u16,
+}
+
+pub struct Cabinet<'a> {
+    data: &'a [u8],
+    folders: Vec<CabFolder>,
+    data_reserve: usize,
+    pub entries: Vec<CabEntry>,
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn read_u8(data: &[u8], pos: usize) -> io::Result<u8> {
+    data.get(pos).copied().ok_or_else(|| invalid("Cabinet is truncated"))
+}
+
+fn read_u16(data: &[u8], pos: usize) -> io::Result<u16> {
+    Ok(u16::from_le_bytes([read_u8(data, pos)?, read_u8(data, pos + 1)?]))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> io::Result<u32> {
+    Ok(u32::from_le_bytes([
+        read_u8(data, pos)?,
+        read_u8(data, pos + 1)?,
+        read_u8(data, pos + 2)?,
+        read_u8(data, pos + 3)?,
+    ]))
+}
+
+/// Returns the null-terminated string at `pos` and the offset just past it.
+fn read_cstr(data: &[u8], pos: usize) -> io::Result<(&[u8], usize)> {
+    let rest = data.get(pos..).ok_or_else(|| invalid("Cabinet is truncated"))?;
+    let len = rest
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or_else(|| invalid("Unterminated name in cabinet"))?;
+    Ok((&rest[..len], pos + len + 1))
+}
+
+impl<'a> Cabinet<'a> {
+    pub fn parse(data: &'a [u8]) -> io::Result<Self> {
+        if data.len() < 36 || &data[..4] != SIGNATURE {
+            return Err(invalid("Not a Microsoft Cabinet file"));
+        }
+        let files_offset = read_u32(data, 16)? as usize;
+        let folder_count = read_u16(data, 26)? as usize;
+        let file_count = read_u16(data, 28)? as usize;
+        let flags = read_u16(data, 30)?;
+        if flags & (FLAG_PREV_CABINET | FLAG_NEXT_CABINET) != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Cabinets spanning several files are not supported",
+            ));
+        }
+
+        let mut pos = 36;
+        let mut folder_reserve = 0;
+        let mut data_reserve = 0;
+        if flags & FLAG_RESERVE_PRESENT != 0 {
+            let header_reserve = read_u16(data, pos)? as usize;
+            folder_reserve = read_u8(data, pos + 2)? as usize;
+            data_reserve = read_u8(data, pos + 3)? as usize;
+            pos += 4 + header_reserve;
+        }
+
+        let mut folders = Vec::with_capacity(folder_count.min(data.len() / 8));
+        for _ in 0..folder_count {
+            folders.push(CabFolder {
+                data_offset: read_u32(data, pos)?,
+                block_count: read_u16(data, pos + 4)?,
+                compression: read_u16(data, pos + 6)?,
+            });
+            pos += 8 + folder_reserve;
+        }
+
+        let mut entries = Vec::with_capacity(file_count.min(data.len() / 16));
+        pos = files_offset;
+        for _ in 0..file_count {
+            let size = read_u32(data, pos)?;
+            let offset = read_u32(data, pos + 4)?;
+            let folder = read_u16(data, pos + 8)?;
+            let attributes = read_u16(data, pos + 14)?;
+            let (raw_name, next) = read_cstr(data, pos + 16)?;
+            let name = if attributes & ATTRIBUTE_NAME_IS_UTF8 != 0 {
+                String::from_utf8_lossy(raw_name).into_owned()
+            } else {
+                raw_name.iter().map(|&b| b as char).collect()
+            };
+            if folder as usize >= folders.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("{} continues into another cabinet, which is not supported", name),
+                ));
+            }
+            entries.push(CabEntry {
+                name: name.replace('\\', "/"),
+                size,
+                folder,
+                offset,
+            });
+            pos = next;
+        }
+
+        Ok(Self {
+            data,
+            folders,
+            data_reserve,
+            entries,
+        })
+    }
+
+    /// Human-readable compression method of the folder holding `entry`.
+    pub fn compression_name(&self, entry: &CabEntry) -> &'static str {
+        match self.folders[entry.folder as usize].compression & 0x000F {
+            COMPRESSION_NONE => "stored",
+            COMPRESSION_MSZIP => "MSZIP",
+            2 => "Quantum",
+            3 => "LZX",
+            _ => "unknown",
+        }
+    }
+
+    /// Unpack every file in the cabinet, in directory order.
+    pub fn extract_all(&self) -> io::Result<Vec<(String, Vec<u8>)>> {
+        // Folders are only unpacked as far as the files in them reach
+        let mut needed = vec![0u64; self.folders.len()];
+        let mut copied = 0u64;
+        for entry in &self.entries {
+            let end = u64::from(entry.offset) + u64::from(entry.size);
+            let folder_needed = &mut needed[entry.folder as usize];
+            *folder_needed = (*folder_needed).max(end);
+            copied += u64::from(entry.size);
+        }
+        if copied + needed.iter().sum::<u64>() > MAX_UNPACKED_SIZE {
+            return Err(invalid("Cabinet is too large to unpack"));
+        }
+
+        let mut unpacked: Vec<Option<Vec<u8>>> = vec![None; self.folders.len()];
+        let mut files = Vec::with_capacity(self.entries.len());
+
+        for entry in &self.entries {
+            let index = entry.folder as usize;
+            if unpacked[index].is_none() {
+                unpacked[index] = Some(self.unpack_folder(index, needed[index] as usize)?);
+            }
+            let folder = unpacked[index].as_deref().unwrap_or_default();
+            let start = entry.offset as usize;
+            let contents = start
+                .checked_add(entry.size as usize)
+                .and_then(|end| folder.get(start..end))
+                .ok_or_else(|| invalid(format!("{} extends past the end of its folder", entry.name)))?;
+            files.push((entry.name.clone(), contents.to_vec()));
+        }
+
+        Ok(files)
+    }
+
+    /// Unpack the blocks of a folder until at least `needed` bytes are out.
+    fn unpack_folder(&self, index: usize, needed: usize) -> io::Result<Vec<u8>> {
+        let folder = &self.folders[index];
+        let compression = folder.compression & 0x000F;
+        if compression != COMPRESSION_NONE && compression != COMPRESSION_MSZIP {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "Cabinet folder uses {} compression, which is not supported",
+                    if compression == 2 { "Quantum" } else { "LZX" }
+                ),
+            ));
+        }
+
+        let mut output = Vec::new();
+        let mut pos = folder.data_offset as usize;
+        for _ in 0..folder.block_count {
+            if output.len() >= needed {
+                break;
+            }
+            let packed_size = read_u16(self.data, pos + 4)? as usize;
+            let unpacked_size = read_u16(self.data, pos + 6)? as usize;
+            let start = pos + 8 + self.data_reserve;
+            let block = self
+                .data
+                .get(start..start + packed_size)
+                .ok_or_else(|| invalid("Cabinet data block is truncated"))?;
+            pos = start + packed_size;
+
+            if compression == COMPRESSION_NONE {
+                output.extend_from_slice(block);
+            } else {
+                inflate_mszip_block(block, unpacked_size, &mut output)?;
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+/// Inflate one MSZIP block onto `output`. Each block is a complete deflate
+/// stream prefixed with "CK" that may refer back into the previous 32 KiB
+/// of output.
+fn inflate_mszip_block(block: &[u8], unpacked_size: usize, output: &mut Vec<u8>) -> io::Result<()> {
+    let compressed = block
+        .strip_prefix(b"CK")
+        .ok_or_else(|| invalid("MSZIP block is missing its CK signature"))?;
+    if unpacked_size > MSZIP_BLOCK_SIZE {
+        return Err(invalid("MSZIP block is larger than 32 KiB"));
+    }
+
+    let history_len = output.len().min(MSZIP_BLOCK_SIZE);
+    let mut window = Vec::with_capacity(history_len + MSZIP_BLOCK_SIZE);
+    window.extend_from_slice(&output[output.len() - history_len..]);
+    window.resize(history_len + MSZIP_BLOCK_SIZE, 0);
+
+    let mut decompressor = DecompressorOxide::new();
+    let (status, _, written) = decompress(
+        &mut decompressor,
+        compressed,
+        &mut window,
+        history_len,
+        inflate_flags::TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF,
+    );
+    if status != TINFLStatus::Done || written != unpacked_size {
+        return Err(invalid(format!("MSZIP block failed to inflate ({:?})", status)));
+    }
+
+    output.extend_from_slice(&window[history_len..history_len + written]);
+    Ok(())
+}
+
+/// Writes small cabinets for tests.
+#[cfg(test)]
+pub(crate) mod test_builder {
+    use miniz_oxide::deflate::compress_to_vec;
+
+    /// Builds a single-folder cabinet holding `files`, split into blocks of
+    /// `block_size` bytes and optionally MSZIP compressed.
+    pub fn build_cabinet(files: &[(&str, &[u8])], mszip: bool, block_size: usize) -> Vec<u8> {
+        let stream: Vec<u8> = files.iter().flat_map(|(_, data)| data.iter().copied()).collect();
+        let mut blocks = Vec::new();
+        for chunk in stream.chunks(block_size) {
+            let payload = if mszip {
+                let mut payload = b"CK".to_vec();
+                payload.extend_from_slice(&compress_to_vec(chunk, 6));
+                payload
+            } else {
+                chunk.to_vec()
+            };
+            let mut block = 0u32.to_le_bytes().to_vec();
+            block.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+            block.extend_from_slice(&(chunk.len() as u16).to_le_bytes());
+            block.extend_from_slice(&payload);
+            blocks.push(block);
+        }
+
+        let mut directory = Vec::new();
+        let mut offset = 0u32;
+        for (name, data) in files {
+            directory.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            directory.extend_from_slice(&offset.to_le_bytes());
+            directory.extend_from_slice(&0u16.to_le_bytes());
+            directory.extend_from_slice(&[0; 4]);
+            directory.extend_from_slice(&0x0080u16.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+            directory.push(0);
+            offset += data.len() as u32;
+        }
+
+        let files_offset = 36 + 8;
+        let data_offset = files_offset + directory.len();
+        let mut cab = b"MSCF".to_vec();
+        cab.extend_from_slice(&[0; 4]);
+        cab.extend_from_slice(&0u32.to_le_bytes());
+        cab.extend_from_slice(&[0; 4]);
+        cab.extend_from_slice(&(files_offset as u32).to_le_bytes());
+        cab.extend_from_slice(&[0; 4]);
+        cab.extend_from_slice(&[3, 1]);
+        cab.extend_from_slice(&1u16.to_le_bytes());
+        cab.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        cab.extend_from_slice(&[0; 6]);
+        cab.extend_from_slice(&(data_offset as u32).to_le_bytes());
+        cab.extend_from_slice(&(blocks.len() as u16).to_le_bytes());
+        cab.extend_from_slice(&(mszip as u16).to_le_bytes());
+        cab.extend_from_slice(&directory);
+        for block in blocks {
+            cab.extend_from_slice(&block);
+        }
+        let len = cab.len() as u32;
+        cab[8..12].copy_from_slice(&len.to_le_bytes());
+        cab
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_builder::build_cabinet;
+    use super::*;
+
+    #[test]
+    fn test_extract_stored_and_mszip_folders() {
+        let long: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let files: [(&str, &[u8]); 2] = [("Notebook/Open Notebook.onetoc2", b"toc"), ("Notebook/Long.one", &long)];
+
+        for mszip in [false, true] {
+            let cab = build_cabinet(&files, mszip, MSZIP_BLOCK_SIZE);
+            let cabinet = Cabinet::parse(&cab).unwrap();
+            assert_eq!(cabinet.entries.len(), 2);
+
+            let extracted = cabinet.extract_all().unwrap();
+            assert_eq!(extracted[0], ("Notebook/Open Notebook.onetoc2".to_string(), b"toc".to_vec()));
+            assert_eq!(extracted[1].0, "Notebook/Long.one");
+            assert_eq!(extracted[1].1, long);
+        }
+    }
+
+    #[test]
+    fn test_rejects_corrupt_cabinets() {
+        assert!(Cabinet::parse(b"MSCF").is_err());
+        assert!(Cabinet::parse(b"Fake OneNote package content......").is_err());
+
+        let mut cab = build_cabinet(&[("a.one", b"hello world")], true, MSZIP_BLOCK_SIZE);
+        let signature = cab.windows(2).rposition(|pair| pair == b"CK").unwrap();
+        cab[signature] = b'X';
+        let cabinet = Cabinet::parse(&cab).unwrap();
+        assert!(cabinet.extract_all().is_err());
+    }
+
+    #[test]
+    fn test_rejects_oversized_entries() {
+        let mut cab = build_cabinet(&[("a.one", b"hello"), ("b.one", b"world")], true, MSZIP_BLOCK_SIZE);
+        // Size of the first file; it now overlaps the second and claims 4 GiB
+        cab[44..48].copy_from_slice(&u32::MAX.to_le_bytes());
+        let cabinet = Cabinet::parse(&cab).unwrap();
+        let error = cabinet.extract_all().unwrap_err();
+        assert!(error.to_string().contains("too large"), "{}", error);
+    }
+
+    #[test]
+    fn test_rejects_lzx_folders() {
+        let mut cab = build_cabinet(&[("a.one", b"hello")], false, MSZIP_BLOCK_SIZE);
+        // Compression type of the only folder
+        cab[42..44].copy_from_slice(&3u16.to_le_bytes());
+        let cabinet = Cabinet::parse(&cab).unwrap();
+        assert_eq!(cabinet.compression_name(&cabinet.entries[0]), "LZX");
+        assert_eq!(cabinet.extract_all().unwrap_err().kind(), io::ErrorKind::Unsupported);
+    }
+}
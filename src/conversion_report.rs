@@ -0,0 +1,259 @@
+use crate::notebook::Page;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// What a OneNote conversion or Evernote import found, skipped and warned
+/// about. Shown as a
+/// NotebookX page in the GUI and serializable to JSON for batch migrations.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConversionReport {
+    pub source: PathBuf,
+    pub file_size: u64,
+    /// Why the file could not be converted, if it could not
+    pub error: Option<String>,
+    pub pages: Vec<PageReport>,
+    pub skipped_objects: Vec<SkippedObject>,
+    /// Property IDs the converter does not interpret, sorted by ID
+    pub unknown_properties: Vec<UnknownProperty>,
+    /// Problems not tied to a single page, such as unreadable sections
+    pub warnings: Vec<String>,
+    /// Cabinet entries of a .onepkg package
+    pub packaged_files: Vec<PackagedFile>,
+    /// Text found by a plain UTF-16 scan when the file could not be parsed
+    pub text_preview: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PageReport {
+    pub title: String,
+    pub section: Option<String>,
+    pub paragraphs: usize,
+    pub attachments: Vec<AttachmentReport>,
+    #[serde(default)]
+    pub drawings: usize,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AttachmentReport {
+    pub name: String,
+    pub mime_type: String,
+    pub size: usize,
+}
+
+/// An object the converter did not turn into page content.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SkippedObject {
+    /// Title of the page the object belongs to
+    pub page: Option<String>,
+    /// The object's JCID, if the object could be read at all
+    pub jcid: Option<u32>,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UnknownProperty {
+    pub id: u32,
+    pub occurrences: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PackagedFile {
+    pub name: String,
+    pub size: u32,
+    pub compression: String,
+}
+
+impl ConversionReport {
+    pub fn new(source: PathBuf, file_size: u64) -> Self {
+        Self {
+            source,
+            file_size,
+            ..Self::default()
+        }
+    }
+
+    /// Record the page a converted NotebookX page came from.
+    pub fn add_page(&mut self, page: &Page, paragraphs: usize, warnings: Vec<String>) {
+        self.pages.push(PageReport {
+            title: page.title.clone(),
+            section: page.section.clone(),
+            paragraphs,
+            attachments: page
+                .attachments
+                .iter()
+                .map(|attachment| AttachmentReport {
+                    name: attachment.name.clone(),
+                    mime_type: attachment.mime_type.clone(),
+                    size: attachment.data.len(),
+                })
+                .collect(),
+            drawings: page.drawings.len(),
+            warnings,
+        });
+    }
+
+    pub fn add_unknown_property(&mut self, id: u32) {
+        match self.unknown_properties.binary_search_by_key(&id, |property| property.id) {
+            Ok(index) => self.unknown_properties[index].occurrences += 1,
+            Err(index) => self
+                .unknown_properties
+                .insert(index, UnknownProperty { id, occurrences: 1 }),
+        }
+    }
+
+    /// The program the source file came from, by its extension.
+    fn application(&self) -> &str {
+        match self.source.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("enex") => "Evernote",
+            _ => "OneNote",
+        }
+    }
+
+    /// Render the report as a NotebookX page.
+    pub fn to_page(&self) -> Page {
+        let heading = format!("{} Conversion Report", self.application());
+        let mut content = format!(
+            "{}\n\
+             {}\n\
+             \n\
+             Source File: {}\n\
+             File Size: {} bytes\n\
+             Pages Found: {}\n\
+             Skipped Objects: {}\n\
+             Unknown Properties: {}\n\
+             Warnings: {}\n",
+            heading,
+            "=".repeat(heading.len()),
+            self.source.display(),
+            self.file_size,
+            self.pages.len(),
+            self.skipped_objects.len(),
+            self.unknown_properties.len(),
+            self.warnings.len() + self.pages.iter().map(|page| page.warnings.len()).sum::<usize>()
+        );
+        if let Some(error) = &self.error {
+            content.push_str(&format!("\nConversion Failed: {}\n", error));
+        }
+
+        if !self.pages.is_empty() {
+            content.push_str("\nPages:\n------\n");
+            for (index, page) in self.pages.iter().enumerate() {
+                let section = page
+                    .section
+                    .as_ref()
+                    .map(|section| format!(" [{}]", section))
+                    .unwrap_or_default();
+                let drawings = if page.drawings > 0 {
+                    format!(", {} drawings", page.drawings)
+                } else {
+                    String::new()
+                };
+                content.push_str(&format!(
+                    "{}. {}{} ({} paragraphs, {} attachments{})\n",
+                    index + 1,
+                    page.title,
+                    section,
+                    page.paragraphs,
+                    page.attachments.len(),
+                    drawings
+                ));
+                for attachment in &page.attachments {
+                    content.push_str(&format!(
+                        "   Attachment: {} ({}, {} bytes)\n",
+                        attachment.name, attachment.mime_type, attachment.size
+                    ));
+                }
+                for warning in &page.warnings {
+                    content.push_str(&format!("   Warning: {}\n", warning));
+                }
+            }
+        }
+
+        if !self.skipped_objects.is_empty() {
+            content.push_str("\nSkipped Objects:\n----------------\n");
+            for skipped in &self.skipped_objects {
+                let jcid = skipped
+                    .jcid
+                    .map(|jcid| format!("0x{:08X}", jcid))
+                    .unwrap_or_else(|| "unknown type".to_string());
+                match &skipped.page {
+                    Some(page) => content.push_str(&format!("{}: {} ({})\n", page, jcid, skipped.reason)),
+                    None => content.push_str(&format!("{} ({})\n", jcid, skipped.reason)),
+                }
+            }
+        }
+
+        if !self.unknown_properties.is_empty() {
+            content.push_str("\nUnknown Properties:\n-------------------\n");
+            for property in &self.unknown_properties {
+                content.push_str(&format!("0x{:08X} ({} occurrences)\n", property.id, property.occurrences));
+            }
+        }
+
+        if !self.warnings.is_empty() {
+            content.push_str("\nWarnings:\n---------\n");
+            for warning in &self.warnings {
+                content.push_str(&format!("{}\n", warning));
+            }
+        }
+
+        if !self.packaged_files.is_empty() {
+            content.push_str("\nPackage Contents:\n-----------------\n");
+            for file in &self.packaged_files {
+                content.push_str(&format!("{} ({} bytes, {})\n", file.name, file.size, file.compression));
+            }
+        }
+
+        if !self.text_preview.is_empty() {
+            content.push_str("\nExtracted Content Preview:\n--------------------------\n");
+            content.push_str(&self.text_preview.join("\n"));
+        }
+
+        Page::new("Conversion Report".to_string(), content.trim_end().to_string(), Some(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_properties_are_counted_in_order() {
+        let mut report = ConversionReport::default();
+        for id in [0x14001C10, 0x08001C01, 0x14001C10] {
+            report.add_unknown_property(id);
+        }
+        assert_eq!(
+            report.unknown_properties,
+            vec![
+                UnknownProperty { id: 0x08001C01, occurrences: 1 },
+                UnknownProperty { id: 0x14001C10, occurrences: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report_round_trips_through_json() {
+        let mut report = ConversionReport::new(PathBuf::from("Notes.one"), 1024);
+        let mut page = Page::new("Plans".to_string(), String::new(), None);
+        page.section = Some("Work".to_string());
+        report.add_page(&page, 2, vec!["diagram.png: stored outside the section file".to_string()]);
+        report.skipped_objects.push(SkippedObject {
+            page: Some("Plans".to_string()),
+            jcid: Some(0x0006001A),
+            reason: "unsupported object type".to_string(),
+        });
+        report.add_unknown_property(0x14001C10);
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(serde_json::from_str::<ConversionReport>(&json).unwrap(), report);
+
+        let page = report.to_page();
+        assert_eq!(page.title, "Conversion Report");
+        assert!(page.content.contains("Pages Found: 1"));
+        assert!(page.content.contains("1. Plans [Work] (2 paragraphs, 0 attachments)"));
+        assert!(page.content.contains("Plans: 0x0006001A (unsupported object type)"));
+        assert!(page.content.contains("0x14001C10 (1 occurrences)"));
+    }
+}
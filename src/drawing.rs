@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+
+/// A handwritten drawing, such as OneNote ink, in pixels relative to the
+/// drawing's top-left corner.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Drawing {
+    pub width: f32,
+    pub height: f32,
+    pub strokes: Vec<Stroke>,
+}
+
+/// One pen stroke: a polyline drawn with a round pen.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Stroke {
+    pub points: Vec<[f32; 2]>,
+    /// Pen width in pixels
+    pub width: f32,
+    /// Red, green, blue and alpha
+    pub color: [u8; 4],
+}
+
+impl Drawing {
+    /// Build a drawing from strokes in any pixel coordinates. The strokes are
+    /// moved so the drawing starts at the origin, and the drawing is sized to
+    /// fit every stroke including its pen width.
+    pub fn from_strokes(mut strokes: Vec<Stroke>) -> Self {
+        strokes.retain(|stroke| !stroke.points.is_empty());
+        let mut min = [f32::INFINITY; 2];
+        let mut max = [f32::NEG_INFINITY; 2];
+        for stroke in &strokes {
+            let margin = stroke.width / 2.0;
+            for point in &stroke.points {
+                for axis in 0..2 {
+                    min[axis] = min[axis].min(point[axis] - margin);
+                    max[axis] = max[axis].max(point[axis] + margin);
+                }
+            }
+        }
+        if strokes.is_empty() {
+            return Self::default();
+        }
+
+        for stroke in &mut strokes {
+            for point in &mut stroke.points {
+                point[0] -= min[0];
+                point[1] -= min[1];
+            }
+        }
+        Self {
+            width: max[0] - min[0],
+            height: max[1] - min[1],
+            strokes,
+        }
+    }
+
+    /// Render the drawing as a standalone SVG document.
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+            w = format_number(self.width),
+            h = format_number(self.height)
+        );
+        for stroke in &self.strokes {
+            let Some((first, rest)) = stroke.points.split_first() else {
+                continue;
+            };
+            // A single point is drawn as a dot by the round line cap
+            let mut path = format!("M{} {}", format_number(first[0]), format_number(first[1]));
+            for point in if rest.is_empty() { std::slice::from_ref(first) } else { rest } {
+                path.push_str(&format!(" L{} {}", format_number(point[0]), format_number(point[1])));
+            }
+            let [r, g, b, a] = stroke.color;
+            svg.push_str(&format!(
+                "  <path d=\"{}\" fill=\"none\" stroke=\"#{:02x}{:02x}{:02x}\" stroke-opacity=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>\n",
+                path,
+                r,
+                g,
+                b,
+                format_number(a as f32 / 255.0),
+                format_number(stroke.width)
+            ));
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+impl Stroke {
+    /// Text form used in NotebookX files: `#RRGGBBAA <width> <x>,<y> ...`
+    pub fn to_line(&self) -> String {
+        let [r, g, b, a] = self.color;
+        let mut line = format!("#{:02X}{:02X}{:02X}{:02X} {}", r, g, b, a, format_number(self.width));
+        for point in &self.points {
+            line.push_str(&format!(" {},{}", format_number(point[0]), format_number(point[1])));
+        }
+        line
+    }
+
+    /// Parse the text form written by [`Stroke::to_line`].
+    pub fn parse_line(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        let color = u32::from_str_radix(parts.next()?.strip_prefix('#')?, 16).ok()?;
+        let width = parse_number(parts.next()?)?;
+        let points = parts
+            .map(|point| {
+                let (x, y) = point.split_once(',')?;
+                Some([parse_number(x)?, parse_number(y)?])
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            points,
+            width,
+            color: color.to_be_bytes(),
+        })
+    }
+}
+
+/// Format a coordinate with at most two decimals and no trailing zeros.
+fn format_number(value: f32) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" { "0".to_string() } else { text.to_string() }
+}
+
+fn parse_number(text: &str) -> Option<f32> {
+    text.parse::<f32>().ok().filter(|value| value.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stroke(points: &[[f32; 2]]) -> Stroke {
+        Stroke {
+            points: points.to_vec(),
+            width: 2.0,
+            color: [0x12, 0x34, 0x56, 0xFF],
+        }
+    }
+
+    #[test]
+    fn test_from_strokes_moves_drawing_to_origin() {
+        let drawing = Drawing::from_strokes(vec![stroke(&[[10.0, 20.0], [30.0, 25.0]]), stroke(&[])]);
+        assert_eq!(drawing.strokes.len(), 1);
+        assert_eq!(drawing.strokes[0].points, vec![[1.0, 1.0], [21.0, 6.0]]);
+        assert_eq!((drawing.width, drawing.height), (22.0, 7.0));
+    }
+
+    #[test]
+    fn test_svg_export() {
+        let drawing = Drawing::from_strokes(vec![stroke(&[[0.0, 0.0], [10.5, 4.0]]), stroke(&[[3.0, 3.0]])]);
+        assert_eq!(
+            drawing.to_svg(),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"12.5\" height=\"6\" viewBox=\"0 0 12.5 6\">\n\
+             \x20 <path d=\"M1 1 L11.5 5\" fill=\"none\" stroke=\"#123456\" stroke-opacity=\"1\" stroke-width=\"2\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>\n\
+             \x20 <path d=\"M4 4 L4 4\" fill=\"none\" stroke=\"#123456\" stroke-opacity=\"1\" stroke-width=\"2\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>\n\
+             </svg>\n"
+        );
+    }
+
+    #[test]
+    fn test_stroke_line_round_trip() {
+        let original = stroke(&[[0.0, 1.25], [-3.5, 7.0]]);
+        let line = original.to_line();
+        assert_eq!(line, "#123456FF 2 0,1.25 -3.5,7");
+        assert_eq!(Stroke::parse_line(&line), Some(original));
+        assert_eq!(Stroke::parse_line("#123456FF 2 0,1 x,2"), None);
+        assert_eq!(Stroke::parse_line("123456FF 2"), None);
+    }
+}
@@ -0,0 +1,754 @@
+use crate::conversion_report::ConversionReport;
+use crate::notebook::{Attachment, Notebook, Page};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Elements that never have content, even when ENML writes them unclosed.
+const EMPTY_ELEMENTS: [&str; 9] = ["br", "hr", "img", "en-media", "en-todo", "input", "area", "col", "meta"];
+
+/// Elements whose text is not part of the note.
+const IGNORED_ELEMENTS: [&str; 4] = ["head", "title", "script", "style"];
+
+/// Elements that cannot be shown as text. They are left out and counted in
+/// the report.
+const UNSUPPORTED_ELEMENTS: [&str; 15] = [
+    "en-crypt", "object", "embed", "iframe", "applet", "form", "input", "button", "select", "textarea",
+    "map", "area", "video", "audio", "canvas",
+];
+
+/// Limit on how deeply elements may nest; deeper elements are flattened
+/// into the last one allowed.
+const MAX_ELEMENT_DEPTH: usize = 256;
+
+/// Elements that start a line of their own.
+const BLOCK_ELEMENTS: [&str; 17] = [
+    "en-note", "div", "p", "h1", "h2", "h3", "h4", "h5", "h6", "ul", "ol", "blockquote", "table",
+    "center", "dl", "dt", "dd",
+];
+
+/// Read an Evernote export (`.enex`) as a notebook named after the file.
+/// Each note becomes a page with its title, created and updated times, and
+/// its ENML content as text. Resources become attachments, and `en-media`
+/// elements become `[Attachment: name]` placeholders where they appeared.
+/// The report lists the pages and anything that could not be imported.
+pub fn import_enex(path: &Path) -> io::Result<(Notebook, ConversionReport)> {
+    let bytes = fs::read(path)?;
+    let (document, flattened) = parse_xml(&String::from_utf8_lossy(&bytes));
+    let Some(export) = document.child("en-export") else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not an Evernote export", path.display()),
+        ));
+    };
+
+    let title = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Evernote".to_string());
+    let mut notebook = Notebook::new(title);
+    let mut report = ConversionReport::new(path.to_path_buf(), bytes.len() as u64);
+    if flattened {
+        report.warnings.push(nesting_warning("The export"));
+    }
+    for note in export.children("note") {
+        let (page, warnings) = import_note(note);
+        let paragraphs = page.content.lines().filter(|line| !line.trim().is_empty()).count();
+        report.add_page(&page, paragraphs, warnings);
+        notebook.pages.push(page);
+    }
+    if notebook.pages.is_empty() {
+        report.warnings.push("The export contains no notes".to_string());
+    }
+
+    // Number pages in order without touching their timestamps
+    for (index, page) in notebook.pages.iter_mut().enumerate() {
+        page.number = Some(index as u32 + 1);
+    }
+    if let Some(created) = notebook.pages.iter().map(|page| page.created).min() {
+        notebook.created = created;
+    }
+    if let Some(modified) = notebook.pages.iter().map(|page| page.modified).max() {
+        notebook.modified = modified;
+    }
+    Ok((notebook, report))
+}
+
+/// A page from one `<note>`, with warnings about what was left out.
+fn import_note(note: &Element) -> (Page, Vec<String>) {
+    let mut warnings = Vec::new();
+    let title = note.child_text("title").map(|title| title.trim().to_string()).unwrap_or_default();
+    let title = if title.is_empty() { "Untitled Note".to_string() } else { title };
+
+    // Resources by the MD5 hash `en-media` refers to them with
+    let mut attachments = Vec::new();
+    let mut hashes = HashMap::new();
+    for (index, resource) in note.children("resource").enumerate() {
+        let mime_type = resource
+            .child_text("mime")
+            .map(|mime| mime.trim().to_string())
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        let name = resource
+            .child("resource-attributes")
+            .and_then(|attributes| attributes.child_text("file-name"))
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| format!("attachment-{}.{}", index + 1, extension_for(&mime_type)));
+        let encoded: String = resource
+            .child_text("data")
+            .unwrap_or_default()
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        match BASE64.decode(encoded) {
+            Ok(data) => {
+                hashes.insert(md5_hex(&data), name.clone());
+                attachments.push(Attachment { name, mime_type, data });
+            }
+            Err(e) => warnings.push(format!("{}: could not be decoded ({})", name, e)),
+        }
+    }
+
+    let content = match note.child_text("content") {
+        Some(enml) => {
+            let (document, flattened) = parse_xml(&enml);
+            if flattened {
+                warnings.push(nesting_warning("The note"));
+            }
+            let mut renderer = Renderer::new(&hashes);
+            renderer.render(&document);
+            warnings.append(&mut renderer.warnings);
+            for (element, count) in &renderer.skipped {
+                warnings.push(format!(
+                    "Skipped {} <{}> element{}",
+                    count,
+                    element,
+                    if *count == 1 { "" } else { "s" }
+                ));
+            }
+            renderer.text()
+        }
+        None => String::new(),
+    };
+
+    let mut page = Page::new(title, content, None);
+    page.attachments = attachments;
+    let mut timestamp = |name: &str| {
+        let value = note.child_text(name)?;
+        let parsed = parse_timestamp(value.trim());
+        if parsed.is_none() {
+            warnings.push(format!("Unreadable {} time \"{}\"", name, value.trim()));
+        }
+        parsed
+    };
+    let created = timestamp("created");
+    let updated = timestamp("updated");
+    if let Some(created) = created {
+        page.created = created;
+    }
+    if let Some(modified) = updated.or(created) {
+        page.modified = modified;
+    }
+
+    let tags: Vec<String> = note.children("tag").map(|tag| tag.text().trim().to_string()).collect();
+    if !tags.is_empty() {
+        warnings.push(format!("Tags not imported: {}", tags.join(", ")));
+    }
+    (page, warnings)
+}
+
+fn nesting_warning(what: &str) -> String {
+    format!("{} nests elements deeper than {} levels; deeper ones were flattened", what, MAX_ELEMENT_DEPTH)
+}
+
+/// Evernote's `20240501T103000Z` timestamps.
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ")
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+fn extension_for(mime_type: &str) -> &str {
+    match mime_type {
+        "image/jpeg" => "jpg",
+        "image/svg+xml" => "svg",
+        "text/plain" => "txt",
+        "audio/mpeg" => "mp3",
+        _ => match mime_type.split_once('/') {
+            Some((_, subtype)) if !subtype.is_empty() && subtype.chars().all(|c| c.is_ascii_alphanumeric()) => subtype,
+            _ => "bin",
+        },
+    }
+}
+
+/// Turns ENML into NotebookX text.
+struct Renderer<'a> {
+    /// Attachment names by the MD5 hash of their data
+    resources: &'a HashMap<String, String>,
+    text: String,
+    /// Whitespace was seen but not yet written
+    space: bool,
+    preformatted: usize,
+    /// Each open list, with the next number for ordered lists
+    lists: Vec<Option<usize>>,
+    /// Where the current table row starts in `text`
+    row_start: Option<usize>,
+    skipped: BTreeMap<String, usize>,
+    warnings: Vec<String>,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(resources: &'a HashMap<String, String>) -> Self {
+        Self {
+            resources,
+            text: String::new(),
+            space: false,
+            preformatted: 0,
+            lists: Vec::new(),
+            row_start: None,
+            skipped: BTreeMap::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn render(&mut self, element: &Element) {
+        for node in &element.nodes {
+            match node {
+                Node::Text(text) => self.push_text(text),
+                Node::Element(child) => self.render_element(child),
+            }
+        }
+    }
+
+    fn render_element(&mut self, element: &Element) {
+        let name = element.name.as_str();
+        if IGNORED_ELEMENTS.contains(&name) {
+            return;
+        }
+        if UNSUPPORTED_ELEMENTS.contains(&name) {
+            *self.skipped.entry(name.to_string()).or_default() += 1;
+            if name == "en-crypt" {
+                self.push_text("[Encrypted text]");
+            }
+            return;
+        }
+        match name {
+            "br" => self.new_line(),
+            "hr" => {
+                self.start_line();
+                self.push_text("----------");
+                self.new_line();
+            }
+            "en-todo" => {
+                let done = element.attribute("checked") == Some("true");
+                self.push_text(if done { "[x] " } else { "[ ] " });
+            }
+            "en-media" => {
+                let hash = element.attribute("hash").unwrap_or_default().to_ascii_lowercase();
+                match self.resources.get(&hash) {
+                    Some(name) => {
+                        let placeholder = format!("[Attachment: {}]", name);
+                        self.push_text(&placeholder);
+                    }
+                    None => {
+                        self.push_text("[Missing attachment]");
+                        self.warnings.push(format!("Attachment {} is not in the note", hash));
+                    }
+                }
+            }
+            "img" => {
+                let source = element.attribute("src").unwrap_or_default();
+                self.push_text(&format!("[Image: {}]", source));
+                self.warnings.push(format!("Image {} was not downloaded", source));
+            }
+            "a" => {
+                let start = self.text.len();
+                self.render(element);
+                let href = element.attribute("href").unwrap_or_default();
+                if !href.is_empty() && self.text.get(start..).unwrap_or_default().trim() != href {
+                    self.push_text(&format!(" ({})", href));
+                }
+            }
+            "pre" => {
+                self.start_line();
+                self.preformatted += 1;
+                self.render(element);
+                self.preformatted -= 1;
+                self.start_line();
+            }
+            "ul" | "ol" => {
+                self.start_line();
+                self.lists.push(if name == "ol" { Some(1) } else { None });
+                self.render(element);
+                self.lists.pop();
+                self.start_line();
+            }
+            "li" => {
+                self.start_line();
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.text.push_str(&indent);
+                self.text.push_str(&marker);
+                self.render(element);
+                self.start_line();
+            }
+            "tr" => {
+                self.start_line();
+                let outer = self.row_start.replace(self.text.len());
+                self.render(element);
+                self.row_start = outer;
+                self.start_line();
+            }
+            "td" | "th" => {
+                if self.row_start.is_some_and(|start| self.text.len() > start) {
+                    self.text.push_str(" | ");
+                    self.space = false;
+                }
+                self.render(element);
+            }
+            _ if BLOCK_ELEMENTS.contains(&name) => {
+                self.start_line();
+                self.render(element);
+                self.start_line();
+            }
+            _ => self.render(element),
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if self.preformatted > 0 {
+            self.text.push_str(&text.replace("\r\n", "\n"));
+            return;
+        }
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.space = true;
+                continue;
+            }
+            if self.space && !self.text.is_empty() && !self.text.ends_with([' ', '\n']) {
+                self.text.push(' ');
+            }
+            self.space = false;
+            self.text.push(c);
+        }
+    }
+
+    fn new_line(&mut self) {
+        // Lines inside a table cell are joined so the row stays on one line
+        if self.row_start.is_some() {
+            self.space = true;
+            return;
+        }
+        self.text.truncate(self.text.trim_end_matches(' ').len());
+        self.text.push('\n');
+        self.space = false;
+    }
+
+    /// Move to the start of a line unless already there.
+    fn start_line(&mut self) {
+        if !self.text.is_empty() && !self.text.ends_with('\n') {
+            self.new_line();
+        }
+    }
+
+    fn text(&self) -> String {
+        let lines: Vec<&str> = self.text.lines().map(str::trim_end).collect();
+        lines.join("\n").trim_matches('\n').to_string()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    nodes: Vec<Node>,
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.nodes.iter().filter_map(move |node| match node {
+            Node::Element(element) if element.name == name => Some(element),
+            _ => None,
+        })
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.nodes.iter().find_map(|node| match node {
+            Node::Element(element) if element.name == name => Some(element),
+            _ => None,
+        })
+    }
+
+    fn child_text(&self, name: &str) -> Option<String> {
+        self.child(name).map(Element::text)
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// All text inside the element.
+    fn text(&self) -> String {
+        let mut text = String::new();
+        for node in &self.nodes {
+            match node {
+                Node::Text(part) => text.push_str(part),
+                Node::Element(element) => text.push_str(&element.text()),
+            }
+        }
+        text
+    }
+}
+
+/// Parse XML, or XHTML such as ENML, into a tree under an unnamed root,
+/// and whether elements past [`MAX_ELEMENT_DEPTH`] had to be flattened.
+/// Parsing never fails: declarations and comments are skipped, element
+/// names are lowercased, and unmatched end tags are ignored.
+fn parse_xml(text: &str) -> (Element, bool) {
+    let mut stack = vec![Element::default()];
+    // Start tags too deep to keep, whose end tags are skipped
+    let mut flattened = 0;
+    let mut any_flattened = false;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let Some(open) = rest.find('<') else {
+            push_node(&mut stack, Node::Text(decode_entities(rest)));
+            break;
+        };
+        if open > 0 {
+            push_node(&mut stack, Node::Text(decode_entities(&rest[..open])));
+        }
+        rest = &rest[open..];
+
+        if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata.find("]]>").unwrap_or(cdata.len());
+            push_node(&mut stack, Node::Text(cdata[..end].to_string()));
+            rest = cdata.get(end + 3..).unwrap_or_default();
+        } else if rest.starts_with("<!--") {
+            rest = rest.find("-->").map(|end| &rest[end + 3..]).unwrap_or_default();
+        } else if rest.starts_with("<?") {
+            rest = rest.find("?>").map(|end| &rest[end + 2..]).unwrap_or_default();
+        } else if rest.starts_with("<!") {
+            // A DOCTYPE, possibly with an internal subset in brackets
+            let end = match (rest.find('['), rest.find('>')) {
+                (Some(bracket), Some(close)) if bracket < close => rest.find("]>").map(|end| end + 1),
+                (_, close) => close,
+            };
+            rest = end.map(|end| &rest[end + 1..]).unwrap_or_default();
+        } else if let Some(tag) = rest.strip_prefix("</") {
+            let end = tag.find('>').unwrap_or(tag.len());
+            let name = tag[..end].trim().to_ascii_lowercase();
+            if flattened > 0 {
+                flattened -= 1;
+            } else if stack[1..].iter().any(|element| element.name == name) {
+                while let Some(element) = stack.pop() {
+                    let done = element.name == name;
+                    push_node(&mut stack, Node::Element(element));
+                    if done {
+                        break;
+                    }
+                }
+            }
+            rest = tag.get(end + 1..).unwrap_or_default();
+        } else {
+            let (element, closed, remaining) = parse_start_tag(&rest[1..]);
+            rest = remaining;
+            if closed || EMPTY_ELEMENTS.contains(&element.name.as_str()) {
+                push_node(&mut stack, Node::Element(element));
+            } else if stack.len() > MAX_ELEMENT_DEPTH {
+                // The children go into the deepest element kept
+                flattened += 1;
+                any_flattened = true;
+            } else {
+                stack.push(element);
+            }
+        }
+    }
+    while stack.len() > 1 {
+        let element = stack.pop().unwrap();
+        push_node(&mut stack, Node::Element(element));
+    }
+    (stack.pop().unwrap(), any_flattened)
+}
+
+fn push_node(stack: &mut [Element], node: Node) {
+    stack.last_mut().unwrap().nodes.push(node);
+}
+
+/// Parse a start tag after its `<`, returning the element, whether it
+/// closed itself with `/>`, and the text after the tag.
+fn parse_start_tag(tag: &str) -> (Element, bool, &str) {
+    let name_end = tag
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(tag.len());
+    let mut element = Element {
+        name: tag[..name_end].to_ascii_lowercase(),
+        ..Element::default()
+    };
+    let mut rest = &tag[name_end..];
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix("/>") {
+            return (element, true, after);
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            return (element, false, after);
+        }
+        if rest.is_empty() {
+            return (element, false, rest);
+        }
+        let key_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+            .unwrap_or(rest.len())
+            .max(1);
+        let key = rest[..key_end].to_ascii_lowercase();
+        rest = rest[key_end..].trim_start();
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (raw, remaining) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let end = after[1..].find(quote).map(|end| end + 1).unwrap_or(after.len());
+                    (&after[1..end], after.get(end + 1..).unwrap_or_default())
+                }
+                _ => {
+                    let end = after.find(|c: char| c.is_whitespace() || c == '>').unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            value = decode_entities(raw);
+            rest = remaining;
+        }
+        element.attributes.push((key, value));
+    }
+}
+
+/// Replace character references and the common named entities. Unknown
+/// entities are kept as written.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        // Only as far as the longest entity, so bare `&`s cost no more
+        let entity = rest.as_bytes()[1..]
+            .iter()
+            .take(11)
+            .position(|&byte| byte == b';')
+            .map(|end| &rest[1..end + 1]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let number = entity.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// The MD5 digest of `data` in lowercase hex, which is how ENML refers to
+/// resources.
+fn md5_hex(data: &[u8]) -> String {
+    const SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+    let constants: Vec<u32> = (0..64)
+        .map(|i| ((i as f64 + 1.0).sin().abs() * 4_294_967_296.0) as u32)
+        .collect();
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    let mut state: [u32; 4] = [0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476];
+    for chunk in message.chunks(64) {
+        let words: Vec<u32> = chunk
+            .chunks(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(constants[i])
+                .wrapping_add(words[g])
+                .rotate_left(SHIFTS[(i / 16) * 4 + i % 4]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+        for (value, add) in state.iter_mut().zip([a, b, c, d]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+    state.iter().flat_map(|word| word.to_le_bytes()).map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enml_text(enml: &str) -> (String, Vec<String>) {
+        let resources = HashMap::from([("49f68a5c8493ec2c0bf489821c21fc3b".to_string(), "hi.txt".to_string())]);
+        let mut renderer = Renderer::new(&resources);
+        renderer.render(&parse_xml(enml).0);
+        let text = renderer.text();
+        let skipped = renderer.skipped.keys().cloned().collect();
+        (text, skipped)
+    }
+
+    #[test]
+    fn test_md5_matches_known_digests() {
+        assert_eq!(md5_hex(b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(md5_hex(b"abc"), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(md5_hex(&[b'a'; 100]), "36a92cc94a9e0fa21f625f8bfb007adf");
+    }
+
+    #[test]
+    fn test_enml_becomes_text() {
+        let (text, skipped) = enml_text(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <!DOCTYPE en-note SYSTEM \"http://xml.evernote.com/pub/enml2.dtd\">\
+             <en-note><div>First   <b>line</b> &amp; more</div><div><br/></div>\
+             <div><en-todo checked=\"true\"/>Done<br/><en-todo/>Open</div>\
+             <ul><li>One</li><li>Two<ol><li>Nested</li></ol></li></ul>\
+             <table><tr><td>a</td><td><div>b</div></td></tr><tr><td>c</td><td>d</td></tr></table>\
+             <div>See <a href=\"https://example.com\">the site</a> and \
+             <en-media hash=\"49F68A5C8493EC2C0BF489821C21FC3B\" type=\"text/plain\"/></div>\
+             <en-crypt cipher=\"AES\">c2VjcmV0</en-crypt><iframe src=\"x\">frame</iframe></en-note>",
+        );
+        assert_eq!(
+            text,
+            "First line & more\n\
+             \n\
+             [x] Done\n\
+             [ ] Open\n\
+             - One\n\
+             - Two\n  1. Nested\n\
+             a | b\n\
+             c | d\n\
+             See the site (https://example.com) and [Attachment: hi.txt]\n\
+             [Encrypted text]"
+        );
+        assert_eq!(skipped, vec!["en-crypt".to_string(), "iframe".to_string()]);
+    }
+
+    #[test]
+    fn test_line_break_inside_link_after_trailing_spaces() {
+        let (text, _) = enml_text("<en-note><pre>ab   <a href=\"x\"><br/></a></pre></en-note>");
+        assert_eq!(text, "ab\n (x)");
+    }
+
+    #[test]
+    fn test_entities_are_decoded() {
+        assert_eq!(decode_entities("a &amp; b &lt;&#65;&#x42;&gt; &nbsp;&bogus; &"), "a & b <AB>  &bogus; &");
+        // Many bare ampersands and a semicolon far away
+        let text = format!("{};", "& ".repeat(200_000));
+        assert_eq!(decode_entities(&text), text);
+    }
+
+    #[test]
+    fn test_deep_nesting_is_flattened() {
+        let depth = 200_000;
+        let enml = format!("<en-note>{}deep{}<div>after</div></en-note>", "<div>".repeat(depth), "</div>".repeat(depth));
+        assert!(parse_xml(&enml).1);
+        let (text, _) = enml_text(&enml);
+        assert_eq!(text, "deep\nafter");
+
+        assert!(!parse_xml("<en-note><div><div>shallow</div></div></en-note>").1);
+    }
+
+    #[test]
+    fn test_import_maps_notes_to_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Travel.enex");
+        fs::write(
+            &path,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <!DOCTYPE en-export SYSTEM \"http://xml.evernote.com/pub/evernote-export3.dtd\">\n\
+             <en-export export-date=\"20240601T120000Z\" application=\"Evernote\">\n\
+             <note><title>Packing &amp; plans</title>\
+             <content><![CDATA[<?xml version=\"1.0\"?><en-note><div>Bring <en-media hash=\"49f68a5c8493ec2c0bf489821c21fc3b\"/></div></en-note>]]></content>\
+             <created>20240501T103000Z</created><updated>20240502T080000Z</updated><tag>trip</tag>\
+             <resource><data encoding=\"base64\">\n aGk=\n</data><mime>text/plain</mime>\
+             <resource-attributes><file-name>hi.txt</file-name></resource-attributes></resource>\
+             <resource><data encoding=\"base64\">!!</data><mime>image/png</mime></resource></note>\n\
+             <note><title></title><content><![CDATA[<en-note/>]]></content><created>20240503T000000Z</created></note>\n\
+             </en-export>\n",
+        )
+        .unwrap();
+
+        let (notebook, report) = import_enex(&path).unwrap();
+        assert_eq!(notebook.title, "Travel");
+        assert_eq!(notebook.pages.len(), 2);
+        let page = &notebook.pages[0];
+        assert_eq!(page.title, "Packing & plans");
+        assert_eq!(page.content, "Bring [Attachment: hi.txt]");
+        assert_eq!(page.number, Some(1));
+        assert_eq!(page.created, parse_timestamp("20240501T103000Z").unwrap());
+        assert_eq!(page.modified, parse_timestamp("20240502T080000Z").unwrap());
+        assert_eq!(page.attachments.len(), 1);
+        assert_eq!(page.attachments[0].data, b"hi");
+        assert_eq!(notebook.pages[1].title, "Untitled Note");
+        assert_eq!(notebook.pages[1].modified, notebook.pages[1].created);
+        assert_eq!(notebook.modified, notebook.pages[1].modified);
+
+        assert_eq!(report.pages.len(), 2);
+        assert_eq!(report.pages[0].warnings.len(), 2);
+        assert!(report.pages[0].warnings[0].starts_with("attachment-2.png: could not be decoded"));
+        assert_eq!(report.pages[0].warnings[1], "Tags not imported: trip");
+        assert!(report.to_page().content.starts_with("Evernote Conversion Report\n"));
+
+        fs::write(&path, "<html></html>").unwrap();
+        assert_eq!(import_enex(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -1,177 +1,1256 @@
-use crate::notebook::{Notebook, Page};
-use chrono::{DateTime, Utc};
+use crate::drawing::{Drawing, Stroke};
+use crate::lock::NotebookLock;
+use crate::notebook::{Attachment, Notebook, Page, PageSource};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::io;
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Version of the file format written by this build. Version 1 files have no
+/// FORMAT_VERSION line and store page content unescaped.
+pub const FORMAT_VERSION: u32 = 3;
+
+/// Backup generations kept next to a notebook file unless configured otherwise.
+pub const DEFAULT_BACKUPS: usize = 3;
+
+/// Minutes between backups unless configured otherwise.
+pub const DEFAULT_BACKUP_MINUTES: u32 = 10;
+
+/// The UTC time in backup file names, such as `20261016T143005.123456Z`.
+const BACKUP_TIME_FORMAT: &str = "%Y%m%dT%H%M%S%.6fZ";
+
+/// Upgrades the text of a file by one format version; entry `n` turns a
+/// version `n + 1` file into a version `n + 2` file.
+const MIGRATIONS: [fn(&str) -> String; FORMAT_VERSION as usize - 1] = [migrate_v1_to_v2, migrate_v2_to_v3];
+
+const NOTEBOOK_HEADER_START: &str = "--- NOTEBOOKX NOTEBOOK ---";
+const NOTEBOOK_HEADER_END: &str = "--- END NOTEBOOK HEADER ---";
+const METADATA_START: &str = "--- NOTEBOOKX METADATA ---";
+const METADATA_END: &str = "--- END METADATA ---";
+const PAGE_BREAK: &str = "--- PAGE BREAK ---";
+
+const FILE_CHECKSUM_MISMATCH: &str =
+    "Notebook checksum does not match; pages may be missing or damaged, or the file was edited outside NotebookX";
+
+/// CRC-32 lookup table for the IEEE polynomial, as used by zip and PNG.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+};
+
+/// Byte range and first line of a page's section in a notebook file.
+type SectionPosition = (u64, u64, usize);
+
+/// Metadata fields that can be large. Indexes leave them on disk.
+const BULKY_FIELDS: [&str; 3] = ["ATTACHMENT", "DRAWING", "STROKE"];
+
+/// Lines with a meaning of their own in the file format. They only count
+/// when they make up a whole line.
+const DELIMITERS: [&str; 5] = [
+    NOTEBOOK_HEADER_START,
+    NOTEBOOK_HEADER_END,
+    METADATA_START,
+    METADATA_END,
+    PAGE_BREAK,
+];
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NotebookFileHandler {
-    // Configuration and state for file operations
+    /// Refuse files with any problem instead of loading what can be recovered
+    pub strict: bool,
+    /// How many timestamped `.bak` copies of earlier saves to keep next to
+    /// the file. With 0 no backups are made and existing ones are left alone.
+    pub backups: usize,
+    /// Minutes that must pass after the newest backup before saving makes
+    /// another, so that autosaving on every keystroke does not push out the
+    /// older ones. With 0 every save is backed up.
+    #[serde(default = "default_backup_minutes")]
+    pub backup_minutes: u32,
+}
+
+fn default_backup_minutes() -> u32 {
+    DEFAULT_BACKUP_MINUTES
 }
 
 impl NotebookFileHandler {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            strict: false,
+            backups: DEFAULT_BACKUPS,
+            backup_minutes: DEFAULT_BACKUP_MINUTES,
+        }
     }
     
-    pub fn save_notebook(&self, notebook: &Notebook, file_path: PathBuf) -> io::Result<()> {
-        let content = self.serialize_notebook(notebook)?;
-        fs::write(file_path, content)?;
+    /// Save a notebook so that the file always holds either the old or the
+    /// new notebook, even if the program or the machine stops mid-save.
+    /// Pages that are not loaded yet are copied from where they are stored
+    /// and afterwards refer to their place in the new file.
+    pub fn save_notebook(&self, notebook: &mut Notebook, file_path: PathBuf) -> io::Result<()> {
+        let (content, sections) = self.serialize_notebook(notebook)?;
+        back_up(&file_path, self.backups, self.backup_minutes)?;
+        write_atomically(&file_path, content.as_bytes())?;
+        
+        for (page, (start, end, line)) in notebook.pages.iter_mut().zip(sections) {
+            if page.source.is_some() {
+                page.source = Some(PageSource::Section {
+                    file: file_path.clone(),
+                    start,
+                    end,
+                    line,
+                });
+            }
+        }
         Ok(())
     }
     
-    pub fn load_notebook(&self, file_path: PathBuf) -> io::Result<Notebook> {
+    /// Load a notebook together with the problems that were recovered from
+    /// while reading it. In strict mode the first problem is an error instead.
+    pub fn load_notebook(&self, file_path: PathBuf) -> io::Result<(Notebook, Vec<Diagnostic>)> {
         let content = fs::read_to_string(file_path)?;
         self.deserialize_notebook(&content)
     }
     
-    fn serialize_notebook(&self, notebook: &Notebook) -> io::Result<String> {
+    /// The text of a notebook file, with where each page's section is in it.
+    fn serialize_notebook(&self, notebook: &Notebook) -> io::Result<(String, Vec<SectionPosition>)> {
         let mut content = String::new();
         
         // Write notebook header
         content.push_str(&format!("--- NOTEBOOKX NOTEBOOK ---\n"));
+        content.push_str(&format!("FORMAT_VERSION: {}\n", FORMAT_VERSION));
         content.push_str(&format!("NOTEBOOK_ID: {}\n", notebook.id));
-        content.push_str(&format!("NOTEBOOK_TITLE: {}\n", notebook.title));
+        content.push_str(&format!("NOTEBOOK_TITLE: {}\n", single_line(&notebook.title)));
         content.push_str(&format!("CREATED: {}\n", notebook.created.to_rfc3339()));
         content.push_str(&format!("MODIFIED: {}\n", notebook.modified.to_rfc3339()));
-        content.push_str(&format!("--- END NOTEBOOK HEADER ---\n\n"));
         
         // Write each page
+        let mut pages = String::from("\n");
+        let mut sections = Vec::new();
+        let mut line = 0;
+        let mut counted = 0;
         for (index, page) in notebook.pages.iter().enumerate() {
             if index > 0 {
-                content.push_str("--- PAGE BREAK ---\n\n");
+                pages.push_str("--- PAGE BREAK ---\n\n");
             }
             
-            content.push_str("--- NOTEBOOKX METADATA ---\n");
-            content.push_str(&format!("PAGE_ID: {}\n", page.id));
-            content.push_str(&format!("TITLE: {}\n", page.title));
-            if let Some(number) = page.number {
-                content.push_str(&format!("NUMBER: {}\n", number));
-            }
-            content.push_str(&format!("CREATED: {}\n", page.created.to_rfc3339()));
-            content.push_str(&format!("MODIFIED: {}\n", page.modified.to_rfc3339()));
-            content.push_str("--- END METADATA ---\n\n");
-            
-            content.push_str(&page.content);
-            content.push_str("\n\n");
+            // A section starts with the blank line after the header or page break
+            let start = pages.len() - 1;
+            line += pages[counted..start].matches('\n').count();
+            counted = start;
+            pages.push_str(&self.serialize_page(page)?);
+            sections.push((start, pages.len(), line));
         }
         
-        Ok(content)
+        // The header ends with a checksum of everything after it
+        content.push_str(&format!("CHECKSUM: {:08x}\n", text_checksum(&[&pages])));
+        content.push_str(&format!("--- END NOTEBOOK HEADER ---\n"));
+        let header_lines = content.matches('\n').count();
+        let sections = sections
+            .into_iter()
+            .map(|(start, end, line)| ((content.len() + start) as u64, (content.len() + end) as u64, header_lines + 1 + line))
+            .collect();
+        content.push_str(&pages);
+        
+        Ok((content, sections))
+    }
+    
+    /// The metadata block and body of one page, as written in a notebook file
+    /// and in the page files of folder notebooks.
+    pub(crate) fn serialize_page(&self, page: &Page) -> io::Result<String> {
+        if let Some(source) = &page.source {
+            return self.serialize_stored_page(page, &read_source(source)?);
+        }
+        // The body is framed by exactly one blank line on either side
+        let body = format!("\n{}\n\n", escape_content(&page.content));
+        Ok(finish_page(self.page_metadata(page, None), &body, None))
+    }
+    
+    /// The text of a page that is not loaded yet, given `stored`, the text
+    /// it is stored as. The metadata is written afresh; attachments, drawings
+    /// and the body are copied from `stored` without being parsed.
+    pub(crate) fn serialize_stored_page(&self, page: &Page, stored: &str) -> io::Result<String> {
+        let metadata = find_line(stored, METADATA_START).and_then(|(start, _)| {
+            let (end, body_start) = find_line(&stored[start..], METADATA_END)?;
+            Some((&stored[start..], &stored[start..start + end], &stored[start + body_start..]))
+        });
+        match metadata {
+            Some((page_text, metadata, body)) => {
+                // A damaged page keeps failing its checksum when copied
+                let damaged = page_checksum(page_text).filter(|&(_, expected, actual)| expected != Some(actual));
+                let kept = damaged.map(|(value, _, _)| value);
+                Ok(finish_page(self.page_metadata(page, Some(metadata)), body, kept))
+            }
+            None => {
+                // Pages without a metadata block are read like any other text
+                let mut loaded = page.clone();
+                self.fill_page(&mut loaded, stored, None, 1)?;
+                self.serialize_page(&loaded)
+            }
+        }
+    }
+    
+    /// The metadata lines of a page, before its checksum and the end of the
+    /// metadata block. Attachments and drawings come from the `stored`
+    /// metadata block of a page that is not loaded yet.
+    fn page_metadata(&self, page: &Page, stored: Option<&str>) -> String {
+        let mut content = String::new();
+        content.push_str("--- NOTEBOOKX METADATA ---\n");
+        content.push_str(&format!("PAGE_ID: {}\n", page.id));
+        if let Some(parent_id) = &page.parent_id {
+            content.push_str(&format!("PARENT_ID: {}\n", parent_id));
+        }
+        content.push_str(&format!("TITLE: {}\n", single_line(&page.title)));
+        if let Some(number) = page.number {
+            content.push_str(&format!("NUMBER: {}\n", number));
+        }
+        if let Some(section) = &page.section {
+            content.push_str(&format!("SECTION: {}\n", single_line(section)));
+        }
+        match stored {
+            Some(stored) => {
+                for (_, line) in lines_with_offsets(0, stored) {
+                    if is_bulky(line) {
+                        content.push_str(line);
+                        content.push('\n');
+                    }
+                }
+            }
+            None => {
+                for attachment in &page.attachments {
+                    content.push_str(&format!(
+                        "ATTACHMENT: {} {} {}\n",
+                        attachment.mime_type,
+                        BASE64.encode(&attachment.data),
+                        single_line(&attachment.name)
+                    ));
+                }
+                for drawing in &page.drawings {
+                    content.push_str(&format!("DRAWING: {} {}\n", drawing.width, drawing.height));
+                    for stroke in &drawing.strokes {
+                        content.push_str(&format!("STROKE: {}\n", stroke.to_line()));
+                    }
+                }
+            }
+        }
+        content.push_str(&format!("CREATED: {}\n", page.created.to_rfc3339()));
+        content.push_str(&format!("MODIFIED: {}\n", page.modified.to_rfc3339()));
+        content
     }
     
-    fn deserialize_notebook(&self, content: &str) -> io::Result<Notebook> {
-        let sections: Vec<&str> = content.split("--- PAGE BREAK ---").collect();
+    /// Take the advisory lock on a notebook that is opened for editing. It is
+    /// released when the returned lock is dropped.
+    pub fn lock_notebook(&self, file_path: &Path) -> io::Result<NotebookLock> {
+        NotebookLock::acquire(file_path)
+    }
+    
+    /// Load only the notebook header and the metadata of each page, leaving
+    /// page bodies, attachments and drawings on disk until [`Self::load_page`].
+    /// Files that need migrating, and any file in strict mode, are loaded in
+    /// full; otherwise problems in a page are reported when it is loaded.
+    pub fn load_notebook_index(&self, file_path: PathBuf) -> io::Result<(Notebook, Vec<Diagnostic>)> {
+        if self.strict {
+            return self.load_notebook(file_path);
+        }
+        let mut reader = BufReader::new(File::open(&file_path)?);
+        let mut buffer = String::new();
         
-        if sections.is_empty() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Invalid NotebookX file format"
-            ));
+        // Offsets only match the file when the header opens it
+        let mut header = String::new();
+        loop {
+            buffer.clear();
+            if reader.read_line(&mut buffer)? == 0 || (header.is_empty() && !is_line(&buffer, NOTEBOOK_HEADER_START)) {
+                return self.load_notebook(file_path);
+            }
+            header.push_str(&buffer);
+            if is_line(&buffer, NOTEBOOK_HEADER_END) {
+                break;
+            }
+        }
+        if format_version(&header)? != FORMAT_VERSION {
+            return self.load_notebook(file_path);
         }
+        let mut diagnostics = Diagnostics::new(&header, None, false);
+        let header_fields = &header[..header.len() - buffer.len()];
+        let (mut notebook, checksum) = self.parse_notebook_header(0, header_fields, &mut diagnostics)?;
+        let checksum = checksum.and_then(|(offset, expected)| Some((expected?, diagnostics.locate(offset, FILE_CHECKSUM_MISMATCH))));
+        let mut found = diagnostics.found;
+        let mut actual_checksum = 0;
         
-        let first_section = sections[0];
-        let (notebook_header, first_page_content) = self.extract_notebook_header(first_section)?;
+        let mut page_origins = Vec::new();
+        let mut offset = header.len() as u64;
+        let mut line = header.matches('\n').count() + 1;
+        let mut section = SectionIndex::new(offset, line);
+        loop {
+            buffer.clear();
+            let read = reader.read_line(&mut buffer)?;
+            actual_checksum = update_checksum(actual_checksum, &buffer);
+            if read == 0 || is_line(&buffer, PAGE_BREAK) {
+                let source = PageSource::Section {
+                    file: file_path.clone(),
+                    start: section.start,
+                    end: offset,
+                    line: section.line,
+                };
+                match section.finish(self, source)? {
+                    Some((origin_line, page)) => {
+                        notebook.pages.push(page);
+                        page_origins.push(Diagnostic {
+                            file: None,
+                            line: origin_line,
+                            column: 1,
+                            message: String::new(),
+                        });
+                    }
+                    // The first page may be empty when the notebook has none
+                    None if section.start != header.len() as u64 => found.push(Diagnostic {
+                        file: None,
+                        line: section.line,
+                        column: 1,
+                        message: "Empty page skipped".to_string(),
+                    }),
+                    None => {}
+                }
+                if read == 0 {
+                    break;
+                }
+                section = SectionIndex::new(offset + read as u64, line + 1);
+            } else {
+                section.add_line(&buffer, line);
+            }
+            offset += read as u64;
+            line += 1;
+        }
         
-        let mut notebook = self.parse_notebook_header(&notebook_header)?;
+        if let Some((expected, diagnostic)) = checksum {
+            if expected != actual_checksum {
+                found.insert(0, diagnostic);
+            }
+        }
         
-        // Parse first page if it exists
-        if !first_page_content.trim().is_empty() {
-            if let Ok(page) = self.parse_page_section(&first_page_content) {
+        let mut diagnostics = Diagnostics::new("", None, false);
+        diagnostics.found = found;
+        repair_pages(&mut notebook, page_origins, &mut diagnostics)?;
+        Ok((notebook, diagnostics.found))
+    }
+    
+    /// Read the content, attachments and drawings of a page listed from an
+    /// index. Pages that are already loaded are left as they are.
+    pub fn load_page(&self, page: &mut Page) -> io::Result<Vec<Diagnostic>> {
+        let Some(source) = page.source.clone() else {
+            return Ok(Vec::new());
+        };
+        let stored = read_source(&source)?;
+        match &source {
+            PageSource::Section { line, .. } => self.fill_page(page, &stored, None, *line),
+            PageSource::File(path) => self.fill_page(page, &stored, Some(page_file_label(path)), 1),
+        }
+    }
+    
+    /// Load every page that is not loaded yet.
+    pub fn load_all_pages(&self, notebook: &mut Notebook) -> io::Result<Vec<Diagnostic>> {
+        let mut found = Vec::new();
+        for page in &mut notebook.pages {
+            found.extend(self.load_page(page)?);
+        }
+        Ok(found)
+    }
+    
+    /// Fill in a page's content, attachments and drawings from its stored
+    /// text, which starts at line `first_line` of `file`.
+    fn fill_page(&self, page: &mut Page, stored: &str, file: Option<&Path>, first_line: usize) -> io::Result<Vec<Diagnostic>> {
+        let mut diagnostics = Diagnostics::new(stored, file, self.strict);
+        diagnostics.first_line = first_line;
+        if let Some((_, parsed)) = self.parse_page_section(0, stored, &mut diagnostics)? {
+            page.content = parsed.content;
+            page.attachments = parsed.attachments;
+            page.drawings = parsed.drawings;
+        }
+        page.source = None;
+        Ok(diagnostics.found)
+    }
+    
+    /// A page with just the metadata in `metadata`, a metadata block whose
+    /// attachment and drawing lines may have been left out. Its problems are
+    /// reported when the whole page is loaded.
+    fn index_page(&self, metadata: &str) -> io::Result<Page> {
+        let mut diagnostics = Diagnostics::new(metadata, None, false);
+        let page = self.parse_page_section(0, metadata, &mut diagnostics)?.map(|(_, page)| page);
+        Ok(page.unwrap_or_else(|| Page::new("Untitled".to_string(), String::new(), None)))
+    }
+    
+    fn deserialize_notebook(&self, content: &str) -> io::Result<(Notebook, Vec<Diagnostic>)> {
+        let content = migrate(content)?;
+        let mut diagnostics = Diagnostics::new(&content, None, self.strict);
+        let sections = split_at_line(&content, PAGE_BREAK);
+        
+        let (first_offset, first_section) = sections[0];
+        let (header, first_page) = self.extract_notebook_header(first_offset, first_section, &mut diagnostics)?;
+        
+        let mut notebook = match header {
+            Some((offset, header)) => {
+                let (notebook, checksum) = self.parse_notebook_header(offset, header, &mut diagnostics)?;
+                if let Some((checksum_offset, Some(expected))) = checksum {
+                    if expected != text_checksum(&[&content[first_page.0..]]) {
+                        diagnostics.report(checksum_offset, FILE_CHECKSUM_MISMATCH)?;
+                    }
+                }
+                notebook
+            }
+            None => Notebook::new("Untitled Notebook".to_string()),
+        };
+        let mut page_origins = Vec::new();
+        
+        // The first page follows the header in the first section
+        let (offset, text) = first_page;
+        if !text.trim().is_empty() {
+            if let Some((page_offset, page)) = self.parse_page_section(offset, text, &mut diagnostics)? {
                 notebook.pages.push(page);
+                page_origins.push(diagnostics.locate(page_offset, ""));
             }
         }
         
-        // Parse remaining pages
-        for section in sections.iter().skip(1) {
-            if let Ok(page) = self.parse_page_section(section) {
+        for &(offset, section) in sections.iter().skip(1) {
+            if let Some((page_offset, page)) = self.parse_page_section(offset, section, &mut diagnostics)? {
                 notebook.pages.push(page);
+                page_origins.push(diagnostics.locate(page_offset, ""));
             }
         }
         
-        Ok(notebook)
+        repair_pages(&mut notebook, page_origins, &mut diagnostics)?;
+        Ok((notebook, diagnostics.found))
     }
     
-    fn extract_notebook_header(&self, content: &str) -> io::Result<(String, String)> {
-        if let Some(header_start) = content.find("--- NOTEBOOKX NOTEBOOK ---") {
-            if let Some(header_end) = content.find("--- END NOTEBOOK HEADER ---") {
-                let header = content[header_start..header_end + "--- END NOTEBOOK HEADER ---".len()].to_string();
-                let remaining = content[header_end + "--- END NOTEBOOK HEADER ---".len()..].to_string();
-                return Ok((header, remaining));
+    /// Split the first section of a file into its notebook header and the
+    /// first page, each with its byte offset.
+    #[allow(clippy::type_complexity)]
+    fn extract_notebook_header<'a>(
+        &self,
+        offset: usize,
+        content: &'a str,
+        diagnostics: &mut Diagnostics,
+    ) -> io::Result<(Option<(usize, &'a str)>, (usize, &'a str))> {
+        let Some((header_start, _)) = find_line(content, NOTEBOOK_HEADER_START) else {
+            // Fallback: treat entire content as page content with default notebook
+            diagnostics.report(offset, "File has no NotebookX header; loaded as an untitled notebook")?;
+            return Ok((None, (offset, content)));
+        };
+        match find_line(&content[header_start..], NOTEBOOK_HEADER_END) {
+            Some((header_end, remaining_start)) => Ok((
+                Some((offset + header_start, &content[header_start..header_start + header_end])),
+                (offset + header_start + remaining_start, &content[header_start + remaining_start..]),
+            )),
+            None => {
+                diagnostics.report(
+                    offset + header_start,
+                    format!("Notebook header is not closed by a `{}` line", NOTEBOOK_HEADER_END),
+                )?;
+                Ok((Some((offset + header_start, &content[header_start..])), (offset + content.len(), "")))
             }
         }
-        
-        // Fallback: treat entire content as page content with default notebook
-        Ok((String::new(), content.to_string()))
     }
     
-    fn parse_notebook_header(&self, header: &str) -> io::Result<Notebook> {
+    /// Parse the notebook header, returning the notebook with the offset and
+    /// value of its checksum, if it has one. Unreadable checksums are `None`.
+    #[allow(clippy::type_complexity)]
+    fn parse_notebook_header(
+        &self,
+        offset: usize,
+        header: &str,
+        diagnostics: &mut Diagnostics,
+    ) -> io::Result<(Notebook, Option<(usize, Option<u32>)>)> {
         let mut notebook = Notebook::new("Untitled Notebook".to_string());
+        let mut checksum = None;
+        let mut seen = HashSet::new();
         
-        for line in header.lines() {
-            if line.starts_with("NOTEBOOK_ID: ") {
-                notebook.id = line[13..].to_string();
-            } else if line.starts_with("NOTEBOOK_TITLE: ") {
-                notebook.title = line[16..].to_string();
-            } else if line.starts_with("CREATED: ") {
-                if let Ok(created) = DateTime::parse_from_rfc3339(&line[9..]) {
-                    notebook.created = created.with_timezone(&Utc);
+        for (line_offset, line) in lines_with_offsets(offset, header).skip(1) {
+            let Some((key, value_offset, value)) = split_field(line_offset, line) else {
+                if !line.trim().is_empty() {
+                    diagnostics.report(line_offset, format!("Unrecognized notebook header line \"{}\"", line))?;
                 }
-            } else if line.starts_with("MODIFIED: ") {
-                if let Ok(modified) = DateTime::parse_from_rfc3339(&line[10..]) {
-                    notebook.modified = modified.with_timezone(&Utc);
+                continue;
+            };
+            seen.insert(key);
+            match key {
+                "FORMAT_VERSION" => {}
+                "CHECKSUM" => checksum = Some((value_offset, parse_checksum(value_offset, value, diagnostics)?)),
+                "NOTEBOOK_ID" => notebook.id = value.to_string(),
+                "NOTEBOOK_TITLE" => notebook.title = value.to_string(),
+                "CREATED" => {
+                    if let Some(created) = parse_timestamp(key, value_offset, value, diagnostics)? {
+                        notebook.created = created;
+                    }
+                }
+                "MODIFIED" => {
+                    if let Some(modified) = parse_timestamp(key, value_offset, value, diagnostics)? {
+                        notebook.modified = modified;
+                    }
                 }
+                _ => diagnostics.report(line_offset, format!("Unknown notebook header field {}", key))?,
+            }
+        }
+        
+        for key in ["NOTEBOOK_ID", "NOTEBOOK_TITLE", "CREATED", "MODIFIED"] {
+            if !seen.contains(key) {
+                diagnostics.report(offset, format!("Notebook header has no {} line", key))?;
             }
         }
         
-        Ok(notebook)
+        Ok((notebook, checksum))
     }
     
-    fn parse_page_section(&self, section: &str) -> io::Result<Page> {
+    /// Parse one page, returning it with the offset it starts at. Sections
+    /// that hold nothing are skipped.
+    fn parse_page_section(
+        &self,
+        offset: usize,
+        section: &str,
+        diagnostics: &mut Diagnostics,
+    ) -> io::Result<Option<(usize, Page)>> {
         let mut page = Page::new("Untitled".to_string(), String::new(), None);
         
-        if let Some(metadata_start) = section.find("--- NOTEBOOKX METADATA ---") {
-            if let Some(metadata_end) = section.find("--- END METADATA ---") {
-                let metadata = &section[metadata_start..metadata_end];
-                let content_start = metadata_end + "--- END METADATA ---".len();
-                let content = section[content_start..].trim().to_string();
-                
-                // Parse metadata
-                for line in metadata.lines() {
-                    if line.starts_with("PAGE_ID: ") {
-                        page.id = line[9..].to_string();
-                    } else if line.starts_with("TITLE: ") {
-                        page.title = line[7..].to_string();
-                    } else if line.starts_with("NUMBER: ") {
-                        if let Ok(number) = line[8..].parse::<u32>() {
-                            page.number = Some(number);
-                        }
-                    } else if line.starts_with("CREATED: ") {
-                        if let Ok(created) = DateTime::parse_from_rfc3339(&line[9..]) {
-                            page.created = created.with_timezone(&Utc);
-                        }
-                    } else if line.starts_with("MODIFIED: ") {
-                        if let Ok(modified) = DateTime::parse_from_rfc3339(&line[10..]) {
-                            page.modified = modified.with_timezone(&Utc);
-                        }
+        let Some((metadata_start, _)) = find_line(section, METADATA_START) else {
+            // Fallback: treat entire section as content
+            page.content = section.trim().to_string();
+            if page.content.is_empty() {
+                diagnostics.report(offset, "Empty page skipped")?;
+                return Ok(None);
+            }
+            let text_start = offset + section.len() - section.trim_start().len();
+            diagnostics.report(text_start, "Page has no metadata block; loaded as an untitled page")?;
+            return Ok(Some((text_start, page)));
+        };
+        let Some((metadata_end, content_start)) = find_line(&section[metadata_start..], METADATA_END) else {
+            diagnostics.report(
+                offset + metadata_start,
+                format!("Page metadata is not closed by a `{}` line; loaded as page text", METADATA_END),
+            )?;
+            page.content = section.trim().to_string();
+            return Ok(Some((offset + metadata_start, page)));
+        };
+        let metadata = &section[metadata_start..metadata_start + metadata_end];
+        let mut checksum = None;
+        let mut seen = HashSet::new();
+        
+        for (line_offset, line) in lines_with_offsets(offset + metadata_start, metadata).skip(1) {
+            let Some((key, value_offset, value)) = split_field(line_offset, line) else {
+                if !line.trim().is_empty() {
+                    diagnostics.report(line_offset, format!("Unrecognized metadata line \"{}\"", line))?;
+                }
+                continue;
+            };
+            seen.insert(key);
+            match key {
+                "PAGE_ID" => page.id = value.to_string(),
+                "TITLE" => page.title = value.to_string(),
+                "NUMBER" => match value.parse::<u32>() {
+                    Ok(number) => page.number = Some(number),
+                    Err(_) => diagnostics.report(value_offset, format!("Invalid page number \"{}\"", value))?,
+                },
+                "PARENT_ID" => page.parent_id = Some(value.to_string()),
+                "SECTION" => page.section = Some(value.to_string()),
+                "CHECKSUM" => checksum = Some((value_offset, parse_checksum(value_offset, value, diagnostics)?)),
+                "ATTACHMENT" => {
+                    // Format: <mime type> <base64 data> <file name>
+                    let mut parts = value.splitn(3, ' ');
+                    match (parts.next(), parts.next(), parts.next()) {
+                        (Some(mime_type), Some(data), Some(name)) => match BASE64.decode(data) {
+                            Ok(data) => page.attachments.push(Attachment {
+                                name: name.to_string(),
+                                mime_type: mime_type.to_string(),
+                                data,
+                            }),
+                            Err(e) => diagnostics.report(
+                                value_offset + mime_type.len() + 1,
+                                format!("Attachment {} has invalid data: {}", name, e),
+                            )?,
+                        },
+                        _ => diagnostics.report(
+                            value_offset,
+                            "Attachment needs a MIME type, base64 data and a file name",
+                        )?,
                     }
                 }
-                
-                page.content = content;
-                return Ok(page);
+                "DRAWING" => {
+                    // Format: <width> <height>, followed by its STROKE lines
+                    let size = value
+                        .split_once(' ')
+                        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)));
+                    match size {
+                        Some((width, height)) => page.drawings.push(Drawing { width, height, strokes: Vec::new() }),
+                        None => diagnostics.report(value_offset, format!("Invalid drawing size \"{}\"", value))?,
+                    }
+                }
+                "STROKE" => match (page.drawings.last_mut(), Stroke::parse_line(value)) {
+                    (Some(drawing), Some(stroke)) => drawing.strokes.push(stroke),
+                    (None, _) => diagnostics.report(line_offset, "Stroke does not follow a DRAWING line")?,
+                    (_, None) => diagnostics.report(value_offset, "Invalid stroke")?,
+                },
+                "CREATED" => {
+                    if let Some(created) = parse_timestamp(key, value_offset, value, diagnostics)? {
+                        page.created = created;
+                    }
+                }
+                "MODIFIED" => {
+                    if let Some(modified) = parse_timestamp(key, value_offset, value, diagnostics)? {
+                        page.modified = modified;
+                    }
+                }
+                _ => diagnostics.report(line_offset, format!("Unknown page metadata field {}", key))?,
+            }
+        }
+        
+        for key in ["PAGE_ID", "TITLE", "CREATED", "MODIFIED"] {
+            if !seen.contains(key) {
+                diagnostics.report(offset + metadata_start, format!("Page metadata has no {} line", key))?;
+            }
+        }
+        if let Some((checksum_offset, Some(_))) = checksum {
+            if let Some((_, Some(expected), actual)) = page_checksum(&section[metadata_start..]) {
+                if expected != actual {
+                    diagnostics.report(
+                        checksum_offset,
+                        "Page checksum does not match; the page was damaged or edited outside NotebookX",
+                    )?;
+                }
             }
         }
         
-        // Fallback: treat entire section as content
-        page.content = section.trim().to_string();
-        if page.content.is_empty() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Empty page content"
-            ));
+        page.content = unescape_content(unframe_body(&section[metadata_start + content_start..]));
+        Ok(Some((offset + metadata_start, page)))
+    }
+    
+    /// Read just the metadata of the page files of a folder notebook into
+    /// `notebook`, in order. `files` holds each file's path relative to the
+    /// notebook folder, for diagnostics, and its full path.
+    pub(crate) fn index_page_files(&self, notebook: &mut Notebook, files: &[(PathBuf, PathBuf)]) -> io::Result<Vec<Diagnostic>> {
+        let mut page_origins = Vec::new();
+        for (relative, path) in files {
+            let mut reader = BufReader::new(File::open(path)?);
+            let mut section = SectionIndex::new(0, 1);
+            let mut buffer = String::new();
+            let mut line = 1;
+            while !section.metadata_closed {
+                buffer.clear();
+                if reader.read_line(&mut buffer)? == 0 {
+                    break;
+                }
+                section.add_line(&buffer, line);
+                line += 1;
+            }
+            if let Some((origin_line, page)) = section.finish(self, PageSource::File(path.clone()))? {
+                notebook.pages.push(page);
+                page_origins.push(Diagnostic {
+                    file: Some(relative.clone()),
+                    line: origin_line,
+                    column: 1,
+                    message: String::new(),
+                });
+            }
         }
         
-        Ok(page)
+        let mut diagnostics = Diagnostics::new("", None, false);
+        repair_pages(notebook, page_origins, &mut diagnostics)?;
+        Ok(diagnostics.found)
+    }
+    
+    /// Read the page files of a folder notebook into `notebook`, in order.
+    /// Each file holds one page as written by [`Self::serialize_page`].
+    pub(crate) fn parse_page_files(&self, notebook: &mut Notebook, files: &[(PathBuf, String)]) -> io::Result<Vec<Diagnostic>> {
+        let mut found = Vec::new();
+        let mut page_origins = Vec::new();
+        for (path, text) in files {
+            let mut diagnostics = Diagnostics::new(text, Some(path), self.strict);
+            if let Some((offset, page)) = self.parse_page_section(0, text, &mut diagnostics)? {
+                notebook.pages.push(page);
+                page_origins.push(diagnostics.locate(offset, ""));
+            }
+            found.append(&mut diagnostics.found);
+        }
+        
+        let mut diagnostics = Diagnostics::new("", None, self.strict);
+        diagnostics.found = found;
+        repair_pages(notebook, page_origins, &mut diagnostics)?;
+        Ok(diagnostics.found)
+    }
+}
+
+/// Give pages that repeat an earlier page's ID a new one and move subpages
+/// whose parent is missing to the top level. `page_origins` locates each page.
+fn repair_pages(notebook: &mut Notebook, page_origins: Vec<Diagnostic>, diagnostics: &mut Diagnostics) -> io::Result<()> {
+    let mut seen_ids = HashSet::new();
+    for (page, origin) in notebook.pages.iter_mut().zip(&page_origins) {
+        if !seen_ids.insert(page.id.clone()) {
+            let id = std::mem::replace(&mut page.id, Uuid::new_v4().to_string());
+            diagnostics.push(Diagnostic {
+                message: format!("Page ID {} is used by an earlier page; a new ID was assigned", id),
+                ..origin.clone()
+            })?;
+        }
+    }
+    
+    let parents: Vec<Option<String>> = notebook.pages.iter().map(|page| page.parent_id.clone()).collect();
+    notebook.repair_hierarchy();
+    for ((page, parent), origin) in notebook.pages.iter().zip(parents).zip(page_origins) {
+        if let (Some(parent), None) = (parent, &page.parent_id) {
+            diagnostics.push(Diagnostic {
+                message: format!("Parent page {} is missing or nested inside this page; moved to the top level", parent),
+                ..origin
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// A problem found while reading a NotebookX file. Strict loading returns
+/// the first one as the error of an [`io::ErrorKind::InvalidData`] error;
+/// lenient loading recovers and returns them all next to the notebook.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// The file the problem is in, for notebooks stored as several files
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column, in characters
+    pub column: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Locate byte `offset` of `content`. Migrations never add or remove
+    /// lines, so line numbers match the file on disk.
+    fn at(content: &str, offset: usize, message: impl Into<String>) -> Self {
+        let before = &content[..offset];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        Self {
+            file: None,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}, ", file.display())?;
+        }
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+impl From<Diagnostic> for io::Error {
+    fn from(diagnostic: Diagnostic) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, diagnostic)
+    }
+}
+
+/// The problems found so far in the file being read.
+struct Diagnostics<'a> {
+    content: &'a str,
+    file: Option<&'a Path>,
+    strict: bool,
+    /// Line of the file that `content` starts at
+    first_line: usize,
+    found: Vec<Diagnostic>,
+}
+
+impl<'a> Diagnostics<'a> {
+    fn new(content: &'a str, file: Option<&'a Path>, strict: bool) -> Self {
+        Self {
+            content,
+            file,
+            strict,
+            first_line: 1,
+            found: Vec::new(),
+        }
+    }
+    
+    fn locate(&self, offset: usize, message: impl Into<String>) -> Diagnostic {
+        let diagnostic = Diagnostic::at(self.content, offset, message);
+        Diagnostic {
+            file: self.file.map(Path::to_path_buf),
+            line: diagnostic.line + self.first_line - 1,
+            ..diagnostic
+        }
+    }
+    
+    /// Record a problem at byte `offset`, or fail with it in strict mode.
+    fn report(&mut self, offset: usize, message: impl Into<String>) -> io::Result<()> {
+        let diagnostic = self.locate(offset, message);
+        self.push(diagnostic)
+    }
+    
+    fn push(&mut self, diagnostic: Diagnostic) -> io::Result<()> {
+        if self.strict {
+            return Err(diagnostic.into());
+        }
+        self.found.push(diagnostic);
+        Ok(())
+    }
+}
+
+/// The metadata of one page section, gathered line by line while indexing.
+struct SectionIndex {
+    start: u64,
+    line: usize,
+    /// The metadata block without attachment and drawing lines
+    metadata: String,
+    metadata_line: Option<usize>,
+    metadata_closed: bool,
+    /// Line of the first text, for sections without metadata
+    text_line: Option<usize>,
+}
+
+impl SectionIndex {
+    fn new(start: u64, line: usize) -> Self {
+        Self {
+            start,
+            line,
+            metadata: String::new(),
+            metadata_line: None,
+            metadata_closed: false,
+            text_line: None,
+        }
+    }
+    
+    fn add_line(&mut self, text: &str, line: usize) {
+        if self.metadata_closed {
+            return;
+        }
+        if self.metadata_line.is_some() {
+            self.metadata_closed = is_line(text, METADATA_END);
+            if !is_bulky(text) {
+                self.metadata.push_str(text);
+            }
+        } else if is_line(text, METADATA_START) {
+            self.metadata_line = Some(line);
+            self.metadata.push_str(text);
+        } else if self.text_line.is_none() && !text.trim().is_empty() {
+            self.text_line = Some(line);
+        }
+    }
+    
+    /// The page listed by this section with the line it starts at, or `None`
+    /// for an empty section.
+    fn finish(&self, handler: &NotebookFileHandler, source: PageSource) -> io::Result<Option<(usize, Page)>> {
+        let Some(origin_line) = self.metadata_line.or(self.text_line) else {
+            return Ok(None);
+        };
+        let mut page = if self.metadata_closed {
+            handler.index_page(&self.metadata)?
+        } else {
+            Page::new("Untitled".to_string(), String::new(), None)
+        };
+        page.source = Some(source);
+        Ok(Some((origin_line, page)))
+    }
+}
+
+/// Whether a metadata line holds an attachment or drawing.
+fn is_bulky(line: &str) -> bool {
+    split_field(0, line).is_some_and(|(key, _, _)| BULKY_FIELDS.contains(&key))
+}
+
+/// Fail unless every page of `notebook` is loaded, for code such as
+/// exporters that need all of them; see [`NotebookFileHandler::load_all_pages`].
+pub(crate) fn require_loaded(notebook: &Notebook) -> io::Result<()> {
+    match notebook.pages.iter().find(|page| page.source.is_some()) {
+        Some(page) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Page \"{}\" is not loaded", page.title),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// The stored text of a page that is not loaded yet.
+fn read_source(source: &PageSource) -> io::Result<String> {
+    match source {
+        PageSource::Section { file, start, end, .. } => {
+            let mut file = File::open(file)?;
+            file.seek(SeekFrom::Start(*start))?;
+            let mut text = String::new();
+            file.take(end - start).read_to_string(&mut text)?;
+            if text.len() as u64 != end - start {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "The notebook file is shorter than when it was opened",
+                ));
+            }
+            Ok(text)
+        }
+        PageSource::File(path) => fs::read_to_string(path),
+    }
+}
+
+/// How diagnostics name a page file: relative to its notebook folder.
+fn page_file_label(path: &Path) -> &Path {
+    path.parent()
+        .and_then(Path::parent)
+        .and_then(|folder| path.strip_prefix(folder).ok())
+        .unwrap_or(path)
+}
+
+/// Lines of `text` without their line endings, each with its byte offset
+/// in the file given that `text` starts at `offset`.
+fn lines_with_offsets(offset: usize, text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_inclusive('\n').scan(offset, |pos, line| {
+        let start = *pos;
+        *pos += line.len();
+        Some((start, line.trim_end_matches(['\n', '\r'])))
+    })
+}
+
+/// Split a `KEY: value` line into its key and value, with the value's offset.
+fn split_field(offset: usize, line: &str) -> Option<(&str, usize, &str)> {
+    let (key, value) = line.split_once(':')?;
+    if key.is_empty() || !key.bytes().all(|byte| byte.is_ascii_uppercase() || byte == b'_') {
+        return None;
+    }
+    let value = value.strip_prefix(' ').unwrap_or(value);
+    Some((key, offset + line.len() - value.len(), value))
+}
+
+fn parse_timestamp(key: &str, offset: usize, value: &str, diagnostics: &mut Diagnostics) -> io::Result<Option<DateTime<Utc>>> {
+    match DateTime::parse_from_rfc3339(value) {
+        Ok(timestamp) => Ok(Some(timestamp.with_timezone(&Utc))),
+        Err(e) => {
+            diagnostics.report(offset, format!("Invalid {} timestamp \"{}\": {}", key, value, e))?;
+            Ok(None)
+        }
+    }
+}
+
+/// Copy the file about to be replaced to `<file name>.<UTC time>.bak` and
+/// remove the oldest backups beyond `keep`. Nothing happens when `keep` is 0,
+/// there is no file yet, or the newest backup is less than `minutes` old.
+pub(crate) fn back_up(file_path: &Path, keep: usize, minutes: u32) -> io::Result<()> {
+    if keep == 0 || !file_path.is_file() {
+        return Ok(());
+    }
+    let now = Utc::now();
+    let mut backups = list_backups(file_path)?;
+    let newest = backups.last().and_then(|backup| backup_time(backup));
+    if newest.is_some_and(|newest| newest <= now && now - newest < chrono::Duration::minutes(minutes as i64)) {
+        return Ok(());
+    }
+    let backup = backup_path(file_path, &now.format(BACKUP_TIME_FORMAT).to_string());
+    fs::copy(file_path, &backup)?;
+    backups.push(backup);
+    
+    let excess = backups.len().saturating_sub(keep);
+    for old in backups.drain(..excess) {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// Write `data` to a temporary file next to `path`, flush it to disk and
+/// rename it over `path`, so readers never see a partly written file.
+pub(crate) fn write_atomically(path: &Path, data: &[u8]) -> io::Result<()> {
+    let directory = parent_directory(path);
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a file path", path.display()))
+    })?;
+    let temp_path = directory.join(format!(".{}.{}.tmp", file_name.to_string_lossy(), Uuid::new_v4()));
+    
+    let result = (|| {
+        let mut file = fs::OpenOptions::new().write(true).create_new(true).open(&temp_path)?;
+        file.write_all(data)?;
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result?;
+    
+    // Make the rename itself durable; directories cannot be opened on Windows
+    #[cfg(unix)]
+    fs::File::open(directory)?.sync_all()?;
+    Ok(())
+}
+
+/// The directory holding `path`, also for bare file names.
+fn parent_directory(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+fn backup_path(file_path: &Path, timestamp: &str) -> PathBuf {
+    let mut name = file_path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.bak", timestamp));
+    file_path.with_file_name(name)
+}
+
+/// The file that `backup` is a copy of, if it is named like the backups
+/// [`back_up`] writes, such as `Notes.txt.20261016T143005.123456Z.bak`.
+pub(crate) fn backed_up_file(backup: &Path) -> Option<PathBuf> {
+    let (original, _) = split_backup_name(backup)?;
+    Some(backup.with_file_name(original))
+}
+
+/// When a backup was taken, from its name.
+fn backup_time(backup: &Path) -> Option<DateTime<Utc>> {
+    split_backup_name(backup).map(|(_, time)| time.and_utc())
+}
+
+/// The backed up file's name and the time in a backup's name.
+fn split_backup_name(backup: &Path) -> Option<(&str, NaiveDateTime)> {
+    let name = backup.file_name()?.to_str()?.strip_suffix(".bak")?;
+    let (original, timestamp) = name.split_at_checked(name.len().checked_sub(24)?)?;
+    let time = NaiveDateTime::parse_from_str(timestamp.strip_prefix('.')?, BACKUP_TIME_FORMAT).ok()?;
+    if original.is_empty() {
+        return None;
+    }
+    Some((original, time))
+}
+
+/// Backups of `file_path`, oldest first.
+fn list_backups(file_path: &Path) -> io::Result<Vec<PathBuf>> {
+    let Some(file_name) = file_path.file_name() else {
+        return Ok(Vec::new());
+    };
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(parent_directory(file_path))? {
+        let path = entry?.path();
+        if backed_up_file(&path).is_some_and(|original| original.file_name() == Some(file_name)) {
+            backups.push(path);
+        }
+    }
+    // The timestamps have a fixed width, so names sort by age
+    backups.sort();
+    Ok(backups)
+}
+
+/// The version a file was written in, from the FORMAT_VERSION line of its
+/// notebook header. Files without one are version 1.
+fn format_version(content: &str) -> io::Result<u32> {
+    let Some((header_start, _)) = find_line(content, NOTEBOOK_HEADER_START) else {
+        return Ok(1);
+    };
+    for (line_offset, line) in lines_with_offsets(header_start, &content[header_start..]).skip(1) {
+        if line == NOTEBOOK_HEADER_END {
+            break;
+        }
+        let Some(("FORMAT_VERSION", offset, version)) = split_field(line_offset, line) else {
+            continue;
+        };
+        let message = match version.trim().parse::<u32>() {
+            Ok(version @ 1..=FORMAT_VERSION) => return Ok(version),
+            Ok(version) if version > FORMAT_VERSION => format!(
+                "This notebook uses file format version {}, but this version of NotebookX only reads up to version {}. Please update NotebookX to open it.",
+                version, FORMAT_VERSION
+            ),
+            _ => format!("Unknown NotebookX file format version \"{}\"", version.trim()),
+        };
+        return Err(Diagnostic::at(content, offset, message).into());
+    }
+    Ok(1)
+}
+
+/// Bring the text of a file of any supported version up to [`FORMAT_VERSION`].
+fn migrate(content: &str) -> io::Result<Cow<'_, str>> {
+    let version = format_version(content)?;
+    let mut content = Cow::Borrowed(content);
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        content = Cow::Owned(migration(&content));
+    }
+    Ok(content)
+}
+
+/// Version 3 added optional checksums; version 2 files are valid as they are.
+fn migrate_v2_to_v3(content: &str) -> String {
+    content.to_string()
+}
+
+/// Version 1 wrote page content as is. Content lines that would now be read
+/// as escaped delimiters get the extra backslash version 2 expects.
+fn migrate_v1_to_v2(content: &str) -> String {
+    let mut migrated = String::with_capacity(content.len());
+    let mut in_body = false;
+    for line in content.split_inclusive('\n') {
+        if is_line(line, PAGE_BREAK) {
+            in_body = false;
+        } else if in_body && is_escapable(line) {
+            migrated.push('\\');
+        } else if is_line(line, METADATA_END) {
+            in_body = true;
+        }
+        migrated.push_str(line);
+    }
+    migrated
+}
+
+/// The rest of a page after its metadata lines: a checksum of the page,
+/// `checksum` if given, then the end of the metadata block and `body`.
+fn finish_page(mut metadata: String, body: &str, checksum: Option<&str>) -> String {
+    let checksum = match checksum {
+        Some(checksum) => checksum.to_string(),
+        None => format!("{:08x}", text_checksum(&[&metadata, METADATA_END, "\n", body])),
+    };
+    metadata.push_str(&format!("CHECKSUM: {}\n", checksum));
+    metadata.push_str(METADATA_END);
+    metadata.push('\n');
+    metadata.push_str(body);
+    metadata
+}
+
+/// The checksum line of `page`, a page from its metadata block on: the
+/// value written, that value read as a checksum and the checksum the page
+/// has now, without that line.
+fn page_checksum(page: &str) -> Option<(&str, Option<u32>, u32)> {
+    for (line_offset, line) in lines_with_offsets(0, page).skip(1) {
+        if is_line(line, METADATA_END) {
+            break;
+        }
+        if let Some(("CHECKSUM", _, value)) = split_field(line_offset, line) {
+            let line_end = page[line_offset..].find('\n').map_or(page.len(), |end| line_offset + end + 1);
+            let actual = text_checksum(&[&page[..line_offset], &page[line_end..]]);
+            return Some((value, u32::from_str_radix(value, 16).ok(), actual));
+        }
+    }
+    None
+}
+
+fn parse_checksum(offset: usize, value: &str, diagnostics: &mut Diagnostics) -> io::Result<Option<u32>> {
+    match u32::from_str_radix(value, 16) {
+        Ok(checksum) => Ok(Some(checksum)),
+        Err(_) => {
+            diagnostics.report(offset, format!("Invalid checksum \"{}\"", value))?;
+            Ok(None)
+        }
+    }
+}
+
+/// CRC-32 of `parts` joined together. CRLF line endings count as LF, so
+/// that copies whose line endings were converted still verify.
+fn text_checksum(parts: &[&str]) -> u32 {
+    parts.iter().fold(0, |crc, part| update_checksum(crc, part))
+}
+
+fn update_checksum(mut crc: u32, text: &str) -> u32 {
+    for line in text.split_inclusive('\n') {
+        let (line, ending) = match line.strip_suffix("\r\n") {
+            Some(line) => (line, "\n"),
+            None => (line, ""),
+        };
+        crc = !line
+            .bytes()
+            .chain(ending.bytes())
+            .fold(!crc, |crc, byte| (crc >> 8) ^ CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize]);
+    }
+    crc
+}
+
+/// Whether `line`, without its line ending, is exactly `delimiter`.
+fn is_line(line: &str, delimiter: &str) -> bool {
+    line.trim_end_matches(['\n', '\r']) == delimiter
+}
+
+/// Byte offsets of the first line that is exactly `delimiter`: where the
+/// line starts and where the line after it starts.
+fn find_line(text: &str, delimiter: &str) -> Option<(usize, usize)> {
+    let mut pos = 0;
+    for line in text.split_inclusive('\n') {
+        if is_line(line, delimiter) {
+            return Some((pos, pos + line.len()));
+        }
+        pos += line.len();
+    }
+    None
+}
+
+/// Split `text` at every line that is exactly `delimiter`, dropping those
+/// lines. Each part comes with its byte offset.
+fn split_at_line<'a>(text: &'a str, delimiter: &str) -> Vec<(usize, &'a str)> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut pos = 0;
+    for line in text.split_inclusive('\n') {
+        if is_line(line, delimiter) {
+            parts.push((start, &text[start..pos]));
+            start = pos + line.len();
+        }
+        pos += line.len();
+    }
+    parts.push((start, &text[start..]));
+    parts
+}
+
+/// A content line that reads as a delimiter once any leading backslashes
+/// are removed is written with one more backslash, so no content line can
+/// ever be taken for a delimiter and the escaping can always be undone.
+fn is_escapable(line: &str) -> bool {
+    let bare = line.trim_start_matches('\\');
+    DELIMITERS.iter().any(|delimiter| is_line(bare, delimiter))
+}
+
+fn escape_content(content: &str) -> Cow<'_, str> {
+    if !content.split_inclusive('\n').any(is_escapable) {
+        return Cow::Borrowed(content);
+    }
+    let mut escaped = String::with_capacity(content.len() + 16);
+    for line in content.split_inclusive('\n') {
+        if is_escapable(line) {
+            escaped.push('\\');
+        }
+        escaped.push_str(line);
+    }
+    Cow::Owned(escaped)
+}
+
+fn unescape_content(content: &str) -> String {
+    content
+        .split_inclusive('\n')
+        .map(|line| match line.strip_prefix('\\') {
+            Some(unescaped) if is_escapable(line) => unescaped,
+            _ => line,
+        })
+        .collect()
+}
+
+/// The content of a page body written as a blank line, the content and
+/// another blank line. Bodies framed any other way, as in hand-edited
+/// files, are trimmed instead.
+fn unframe_body(body: &str) -> &str {
+    body.strip_prefix('\n')
+        .and_then(|body| body.strip_suffix("\n\n"))
+        .unwrap_or_else(|| body.trim())
+}
+
+/// Metadata values take up one line; line breaks in them become spaces.
+fn single_line(value: &str) -> Cow<'_, str> {
+    if value.contains(['\n', '\r']) {
+        Cow::Owned(value.replace("\r\n", " ").replace(['\n', '\r'], " "))
+    } else {
+        Cow::Borrowed(value)
     }
 }
 
@@ -179,4 +1258,417 @@ impl Default for NotebookFileHandler {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use proptest::sample::{select, Index};
+
+    #[test]
+    fn test_content_round_trips_byte_for_byte() {
+        let contents = [
+            "--- PAGE BREAK ---\nnot a new page",
+            "--- NOTEBOOKX METADATA ---\nTITLE: Injected\n--- END METADATA ---",
+            "\\--- END METADATA ---\n\\\\--- PAGE BREAK ---\n\\plain backslash",
+            "Windows lines\r\n--- PAGE BREAK ---\r\nend\r\n",
+            "inline --- PAGE BREAK --- stays inline",
+            "\n\n  surrounding whitespace  \n\n",
+            "",
+            "--- END NOTEBOOK HEADER ---",
+        ];
+        let mut notebook = Notebook::new("Hostile".to_string());
+        for (index, content) in contents.iter().enumerate() {
+            notebook.pages.push(Page::new(format!("Page {}", index), content.to_string(), None));
+        }
+        notebook.pages[0].title = "Two\nlines".to_string();
+
+        let handler = NotebookFileHandler::new();
+        let (text, _) = handler.serialize_notebook(&notebook).unwrap();
+        let (loaded, _) = handler.deserialize_notebook(&text).unwrap();
+
+        assert_eq!(loaded.pages.len(), contents.len());
+        for (page, content) in loaded.pages.iter().zip(contents) {
+            assert_eq!(page.content, content);
+        }
+        assert_eq!(loaded.pages[0].title, "Two lines");
+        assert_eq!(loaded.pages[1].title, "Page 1");
+    }
+
+    fn format_fixture(name: &str) -> String {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/format").join(name);
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_older_versions_migrate_to_golden_file() {
+        let handler = NotebookFileHandler::new();
+        let current = format_fixture(&format!("v{}.txt", FORMAT_VERSION));
+        for version in 1..=FORMAT_VERSION {
+            let (notebook, diagnostics) = handler
+                .deserialize_notebook(&format_fixture(&format!("v{}.txt", version)))
+                .unwrap();
+            assert_eq!(diagnostics, Vec::new(), "migrating version {}", version);
+            assert_eq!(handler.serialize_notebook(&notebook).unwrap().0, current, "migrating version {}", version);
+        }
+
+        let (notebook, _) = handler.deserialize_notebook(&format_fixture("v1.txt")).unwrap();
+        assert_eq!(
+            notebook.pages[0].content,
+            "Escaped paths are kept as typed:\n\\--- PAGE BREAK ---\nC:\\Users\\notes\n--- END METADATA ---"
+        );
+        assert_eq!(notebook.pages[1].parent_id.as_deref(), Some(notebook.pages[0].id.as_str()));
+    }
+
+    #[test]
+    fn test_refuses_newer_format_versions() {
+        let handler = NotebookFileHandler::new();
+        let current = format_fixture(&format!("v{}.txt", FORMAT_VERSION));
+        let newer = current.replace(
+            &format!("FORMAT_VERSION: {}", FORMAT_VERSION),
+            &format!("FORMAT_VERSION: {}", FORMAT_VERSION + 1),
+        );
+        let error = handler.deserialize_notebook(&newer).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains(&format!("version {}", FORMAT_VERSION + 1)));
+
+        let garbled = current.replace(&format!("FORMAT_VERSION: {}", FORMAT_VERSION), "FORMAT_VERSION: two");
+        assert!(handler.deserialize_notebook(&garbled).is_err());
+    }
+
+    #[test]
+    fn test_strict_mode_reports_line_and_column() {
+        let text = format_fixture("v2.txt").replace("NUMBER: 2", "NUMBER: two");
+        let strict = NotebookFileHandler { strict: true, ..NotebookFileHandler::new() };
+        assert!(strict.deserialize_notebook(&format_fixture("v2.txt")).is_ok());
+
+        let error = strict.deserialize_notebook(&text).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let diagnostic = error.get_ref().and_then(|e| e.downcast_ref::<Diagnostic>()).unwrap();
+        assert_eq!((diagnostic.line, diagnostic.column), (30, 9));
+        assert_eq!(error.to_string(), "line 30, column 9: Invalid page number \"two\"");
+    }
+
+    #[test]
+    fn test_index_loads_pages_on_demand() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Notes.txt");
+        let text = format_fixture(&format!("v{}.txt", FORMAT_VERSION));
+        fs::write(&path, &text).unwrap();
+        let handler = NotebookFileHandler { backups: 0, ..NotebookFileHandler::new() };
+        let (full, _) = handler.load_notebook(path.clone()).unwrap();
+
+        let (mut index, diagnostics) = handler.load_notebook_index(path.clone()).unwrap();
+        assert_eq!(diagnostics, Vec::new());
+        assert_eq!(index.pages.len(), full.pages.len());
+        for (page, full_page) in index.pages.iter().zip(&full.pages) {
+            assert_eq!((&page.id, &page.title, &page.parent_id), (&full_page.id, &full_page.title, &full_page.parent_id));
+            assert!(page.source.is_some());
+            assert!(page.content.is_empty() && page.attachments.is_empty() && page.drawings.is_empty());
+        }
+
+        // Saving copies unloaded pages as they are and points them at the new file
+        let copy = dir.path().join("Copy.txt");
+        index.pages[1].title = "Renamed".to_string();
+        handler.save_notebook(&mut index, copy.clone()).unwrap();
+        let mut renamed = full.clone();
+        renamed.pages[1].title = "Renamed".to_string();
+        assert_eq!(fs::read_to_string(&copy).unwrap(), handler.serialize_notebook(&renamed).unwrap().0);
+        fs::remove_file(&path).unwrap();
+        handler.load_all_pages(&mut index).unwrap();
+        for (page, full_page) in index.pages.iter().zip(&full.pages) {
+            assert!(page.source.is_none());
+            assert_eq!(page.content, full_page.content);
+            assert_eq!(page.attachments.len(), full_page.attachments.len());
+            assert_eq!(page.drawings.len(), full_page.drawings.len());
+        }
+
+        // Problems in a page are reported where they are in the file once it loads
+        fs::write(&path, text.replace("NUMBER: 2", "NUMBER: two")).unwrap();
+        let (mut index, diagnostics) = handler.load_notebook_index(path).unwrap();
+        assert_eq!(diagnostics, vec![Diagnostic { file: None, line: 7, column: 11, message: FILE_CHECKSUM_MISMATCH.to_string() }]);
+        let diagnostics = handler.load_page(&mut index.pages[1]).unwrap();
+        assert_eq!(diagnostics[0].to_string(), "line 32, column 9: Invalid page number \"two\"");
+        assert_eq!((diagnostics[1].line, diagnostics[1].column), (37, 11));
+    }
+
+    #[test]
+    fn test_checksums_flag_damaged_pages() {
+        let handler = NotebookFileHandler { backups: 0, ..NotebookFileHandler::new() };
+        let text = format_fixture(&format!("v{}.txt", FORMAT_VERSION));
+        let located = |text: &str| -> Vec<(usize, usize)> {
+            let (_, diagnostics) = handler.deserialize_notebook(text).unwrap();
+            diagnostics.iter().map(|diagnostic| (diagnostic.line, diagnostic.column)).collect()
+        };
+        assert_eq!(located(&text), vec![]);
+        assert_eq!(located(&text.replace('\n', "\r\n")), vec![]);
+
+        // Bit rot in the second page flags it and the file
+        let damaged = text.replace("with a drawing", "with a dr4wing");
+        assert_eq!(located(&damaged), vec![(7, 11), (37, 11)]);
+        let strict = NotebookFileHandler { strict: true, ..handler.clone() };
+        assert!(strict.deserialize_notebook(&damaged).is_err());
+
+        // A copy cut off after the first page only fails the file checksum
+        let truncated = &text[..text.find("\n--- PAGE BREAK ---\n").unwrap() + 1];
+        assert_eq!(located(truncated), vec![(7, 11)]);
+
+        // Copying a damaged page that was never loaded keeps it flagged
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Notes.txt");
+        fs::write(&path, &damaged).unwrap();
+        let (mut index, _) = handler.load_notebook_index(path.clone()).unwrap();
+        handler.save_notebook(&mut index, path.clone()).unwrap();
+        assert_eq!(located(&fs::read_to_string(&path).unwrap()), vec![(37, 11)]);
+    }
+
+    #[test]
+    fn test_lenient_mode_recovers_with_diagnostics() {
+        let text = "--- NOTEBOOKX NOTEBOOK ---\n\
+                    FORMAT_VERSION: 2\n\
+                    NOTEBOOK_ID: nb\n\
+                    NOTEBOOK_TITLE: Damaged\n\
+                    CREATED: yesterday\n\
+                    MODIFIED: 2025-08-14T10:30:00+00:00\n\
+                    --- END NOTEBOOK HEADER ---\n\n\
+                    --- NOTEBOOKX METADATA ---\n\
+                    PAGE_ID: a\n\
+                    PARENT_ID: missing\n\
+                    TITLE: Kept\n\
+                    COLOUR: blue\n\
+                    CREATED: 2025-08-14T10:30:00+00:00\n\
+                    MODIFIED: 2025-08-14T10:30:00+00:00\n\
+                    --- END METADATA ---\n\n\
+                    Still here\n\n\
+                    --- PAGE BREAK ---\n\n\
+                    --- PAGE BREAK ---\n\n\
+                    Loose text\n";
+
+        let (notebook, diagnostics) = NotebookFileHandler::new().deserialize_notebook(text).unwrap();
+        assert_eq!(notebook.title, "Damaged");
+        assert_eq!(notebook.pages.len(), 2);
+        assert_eq!(notebook.pages[0].content, "Still here");
+        assert_eq!(notebook.pages[0].parent_id, None);
+        assert_eq!(notebook.pages[1].content, "Loose text");
+
+        let found: Vec<(usize, usize, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.column, d.message.split(':').next().unwrap()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (5, 10, "Invalid CREATED timestamp \"yesterday\""),
+                (13, 1, "Unknown page metadata field COLOUR"),
+                (21, 1, "Empty page skipped"),
+                (24, 1, "Page has no metadata block; loaded as an untitled page"),
+                (9, 1, "Parent page missing is missing or nested inside this page; moved to the top level"),
+            ]
+        );
+        assert!(NotebookFileHandler { strict: true, ..NotebookFileHandler::new() }.deserialize_notebook(text).is_err());
+    }
+
+    #[test]
+    fn test_saves_keep_rolling_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Notes.txt");
+        fs::write(dir.path().join("Notes.txt.old.bak"), "kept").unwrap();
+        let handler = NotebookFileHandler {
+            backups: 2,
+            backup_minutes: 0,
+            ..NotebookFileHandler::new()
+        };
+
+        let mut notebook = Notebook::new("Version 1".to_string());
+        for version in 1..=4 {
+            notebook.title = format!("Version {}", version);
+            handler.save_notebook(&mut notebook, path.clone()).unwrap();
+        }
+
+        let (loaded, _) = handler.load_notebook(path.clone()).unwrap();
+        assert_eq!(loaded.title, "Version 4");
+        let backups: Vec<String> = list_backups(&path)
+            .unwrap()
+            .iter()
+            .map(|backup| handler.load_notebook(backup.clone()).unwrap().0.title)
+            .collect();
+        assert_eq!(backups, vec!["Version 2", "Version 3"]);
+
+        let mut names: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 4);
+        assert_eq!(names[0], "Notes.txt");
+        assert_eq!(names[3], "Notes.txt.old.bak");
+    }
+
+    #[test]
+    fn test_backups_are_taken_at_most_once_per_interval() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Notes.txt");
+        let handler = NotebookFileHandler::new();
+
+        let mut notebook = Notebook::new("Version 1".to_string());
+        for version in 1..=5 {
+            notebook.title = format!("Version {}", version);
+            handler.save_notebook(&mut notebook, path.clone()).unwrap();
+        }
+        let backups = list_backups(&path).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(handler.load_notebook(backups[0].clone()).unwrap().0.title, "Version 1");
+
+        // An old enough backup lets the next save make another
+        let old = backup_path(&path, "20200101T000000.000000Z");
+        fs::rename(&backups[0], &old).unwrap();
+        handler.save_notebook(&mut notebook, path.clone()).unwrap();
+        assert_eq!(list_backups(&path).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_failed_save_leaves_no_temporary_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Notes.txt");
+        fs::create_dir(&path).unwrap();
+        fs::write(path.join("inside"), "").unwrap();
+
+        assert!(write_atomically(&path, b"notebook").is_err());
+        assert!(path.join("inside").is_file());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_loads_files_written_before_escaping() {
+        let text = "--- NOTEBOOKX NOTEBOOK ---\n\
+                    NOTEBOOK_ID: nb\n\
+                    NOTEBOOK_TITLE: Old Notes\n\
+                    --- END NOTEBOOK HEADER ---\n\n\
+                    --- NOTEBOOKX METADATA ---\n\
+                    PAGE_ID: first\n\
+                    TITLE: First\n\
+                    NUMBER: 1\n\
+                    --- END METADATA ---\n\n\
+                    Line one\n\\ not an escape\n\n\
+                    --- PAGE BREAK ---\n\n\
+                    --- NOTEBOOKX METADATA ---\n\
+                    PAGE_ID: second\n\
+                    TITLE: Second\n\
+                    --- END METADATA ---\n\n\
+                    Second page\n\n";
+
+        let (notebook, _) = NotebookFileHandler::new().deserialize_notebook(text).unwrap();
+        assert_eq!(notebook.title, "Old Notes");
+        assert_eq!(notebook.pages.len(), 2);
+        assert_eq!(notebook.pages[0].content, "Line one\n\\ not an escape");
+        assert_eq!(notebook.pages[1].id, "second");
+        assert_eq!(notebook.pages[1].content, "Second page");
+    }
+
+    /// Text for metadata values, which are always one line
+    const VALUE_PIECES: [&str; 14] = [
+        "Plans", " ", "  padded  ", "\t", ": ", "key: value", "TITLE: nested", "Ünïcödé", "日本語のノート", "📓", "\\",
+        "--- PAGE BREAK ---", "CHECKSUM: 00000000", "",
+    ];
+
+    const CONTENT_PIECES: [&str; 16] = [
+        "\n", "\r\n", "\n\n", "\r", "   ", "\t", "text", "key: value", "Ωμέγα ✓", "--- PAGE BREAK ---",
+        "--- NOTEBOOKX METADATA ---", "--- END METADATA ---", "--- NOTEBOOKX NOTEBOOK ---", "--- END NOTEBOOK HEADER ---",
+        "\\", "CHECKSUM: 00000000",
+    ];
+
+    /// One line of text: the format's own markers, `": "`, padding, and any
+    /// other characters but line breaks.
+    fn value() -> impl Strategy<Value = String> {
+        let piece = prop_oneof![select(&VALUE_PIECES[..]).prop_map(str::to_string), "[^\r\n]{1,6}"];
+        prop::collection::vec(piece, 0..5).prop_map(|pieces| pieces.concat())
+    }
+
+    /// Page text, possibly empty, with CRLF and lone CR line endings.
+    fn content() -> impl Strategy<Value = String> {
+        let piece = prop_oneof![select(&CONTENT_PIECES[..]).prop_map(str::to_string), ".{1,8}"];
+        prop::collection::vec(piece, 0..12).prop_map(|pieces| pieces.concat())
+    }
+
+    fn timestamp() -> impl Strategy<Value = DateTime<Utc>> {
+        (0..4_000_000_000i64, prop_oneof![Just(0u32), 0..1_000_000_000u32])
+            .prop_map(|(seconds, nanos)| DateTime::from_timestamp(seconds, nanos).unwrap())
+    }
+
+    /// Coordinates in quarter points up to `max`, which print exactly.
+    fn quarter(max: u32) -> impl Strategy<Value = f32> {
+        (0..max * 4).prop_map(|quarters| quarters as f32 / 4.0)
+    }
+
+    fn attachment() -> impl Strategy<Value = Attachment> {
+        (value(), select(vec!["image/png", "application/pdf", "text/plain"]), prop::collection::vec(any::<u8>(), 0..40))
+            .prop_map(|(name, mime_type, data)| Attachment { name, mime_type: mime_type.to_string(), data })
+    }
+
+    fn drawing() -> impl Strategy<Value = Drawing> {
+        let point = (quarter(800), quarter(800)).prop_map(|(x, y)| [x, y]);
+        let stroke = (prop::collection::vec(point, 0..5), quarter(20), any::<[u8; 4]>())
+            .prop_map(|(points, width, color)| Stroke { points, width, color });
+        (quarter(800), quarter(800), prop::collection::vec(stroke, 0..3))
+            .prop_map(|(width, height, strokes)| Drawing { width, height, strokes })
+    }
+
+    /// A page without a parent; its ID, if given, is made unique by the notebook.
+    fn page() -> impl Strategy<Value = (Page, Option<String>)> {
+        (
+            (value(), content(), prop::option::of(value())),
+            (prop::option::of(0..1000u32), prop::option::of(value())),
+            prop::collection::vec(attachment(), 0..3),
+            prop::collection::vec(drawing(), 0..3),
+            (timestamp(), timestamp()),
+        )
+            .prop_map(|((title, content, id), (number, section), attachments, drawings, (created, modified))| {
+                let mut page = Page::new(title, content, number);
+                page.section = section;
+                page.attachments = attachments;
+                page.drawings = drawings;
+                page.created = created;
+                page.modified = modified;
+                (page, id)
+            })
+    }
+
+    fn notebook() -> impl Strategy<Value = Notebook> {
+        let pages = prop::collection::vec((page(), prop::option::of(any::<Index>())), 0..6);
+        (value(), pages, timestamp(), timestamp()).prop_map(|(title, pages, created, modified)| {
+            let mut notebook = Notebook::new(title);
+            for (index, ((mut page, id), parent)) in pages.into_iter().enumerate() {
+                if let Some(id) = id {
+                    page.id = format!("{}{}", id, index);
+                }
+                if let Some(parent) = parent.filter(|_| index > 0) {
+                    page.parent_id = Some(notebook.pages[parent.index(index)].id.clone());
+                }
+                notebook.pages.push(page);
+            }
+            notebook.created = created;
+            notebook.modified = modified;
+            notebook
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(300))]
+
+        #[test]
+        fn test_save_and_load_reproduce_random_notebooks(mut notebook in notebook()) {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("Notes.txt");
+            let handler = NotebookFileHandler { backups: 0, strict: true, ..NotebookFileHandler::new() };
+            handler.save_notebook(&mut notebook, path.clone()).unwrap();
+
+            let (loaded, diagnostics) = handler.load_notebook(path.clone()).unwrap();
+            prop_assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+            prop_assert_eq!(&loaded, &notebook);
+
+            let (mut index, _) = handler.load_notebook_index(path.clone()).unwrap();
+            handler.load_all_pages(&mut index).unwrap();
+            prop_assert_eq!(&index, &notebook);
+        }
+    }
+}
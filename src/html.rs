@@ -0,0 +1,235 @@
+use crate::file_io;
+use crate::notebook::{Notebook, Page};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The look of the app's page list and editor, from `dist/index.html`, for
+/// reading rather than editing.
+const STYLE: &str = "
+* { margin: 0; padding: 0; box-sizing: border-box; }
+body { font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif; height: 100vh; display: flex; }
+.sidebar { width: 300px; flex-shrink: 0; background: #f8f9fa; border-right: 1px solid #dee2e6; display: flex; flex-direction: column; }
+.sidebar-header { padding: 20px; border-bottom: 1px solid #dee2e6; }
+.pages-list { flex: 1; overflow-y: auto; }
+.pages-list hr { border: none; border-top: 1px solid #dee2e6; }
+.section-title { padding: 12px 20px 4px; font-weight: 600; }
+.page-item { display: block; padding: 12px 20px; border-bottom: 1px solid #e9ecef; color: inherit; text-decoration: none; transition: background-color 0.2s; }
+.page-item:hover { background-color: #e9ecef; }
+.page-title { font-weight: 500; margin-bottom: 4px; }
+.page-meta { font-size: 0.85em; color: #6c757d; }
+.main-content { flex: 1; overflow-y: auto; background: white; }
+.page { border-bottom: 1px solid #dee2e6; }
+.page:target .content-header { background: #e7f1ff; }
+.content-header { padding: 20px; border-bottom: 1px solid #dee2e6; }
+.content-header h1 { font-size: 18px; margin-bottom: 4px; }
+.page-content { padding: 20px; font-size: 14px; line-height: 1.6; white-space: pre-wrap; overflow-wrap: anywhere; }
+.drawing, .attachments { padding: 0 20px 20px; list-style: none; }
+.drawing svg, .attachments img { max-width: 100%; height: auto; border: 1px solid #e9ecef; border-radius: 4px; }
+.attachments a { color: #007bff; }
+@media print {
+  body { display: block; height: auto; }
+  .sidebar { display: none; }
+  .main-content { overflow: visible; }
+  .page { break-after: page; border: none; }
+}
+";
+
+/// Write a notebook as a single HTML file that needs nothing else to be
+/// read: the stylesheet, pictures, other attachments and drawings are all
+/// inside it. A sidebar lists the pages like the app does and links to
+/// each page. All pages must be loaded. Returns the path of the file.
+pub fn export_notebook(notebook: &Notebook, output: &Path) -> io::Result<PathBuf> {
+    file_io::require_loaded(notebook)?;
+    fs::write(output, notebook_html(notebook))?;
+    Ok(output.to_path_buf())
+}
+
+fn notebook_html(notebook: &Notebook) -> String {
+    let anchors = page_anchors(notebook);
+    let title = escape(&notebook.title);
+    let mut html = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n");
+    html.push_str(&format!("<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n", title, STYLE));
+
+    html.push_str("<nav class=\"sidebar\">\n<div class=\"sidebar-header\">\n");
+    html.push_str(&format!("<h2>{}</h2>\n", title));
+    let count = notebook.pages.len();
+    html.push_str(&format!(
+        "<div class=\"page-meta\">{} page{}</div>\n",
+        count,
+        if count == 1 { "" } else { "s" }
+    ));
+    html.push_str("</div>\n<div class=\"pages-list\">\n");
+    let tree = notebook.subpages_by_parent();
+    let mut current_section = None;
+    for page in tree.get(&None).into_iter().flatten() {
+        // Imported pages are grouped under their OneNote section
+        if page.section.as_ref() != current_section {
+            current_section = page.section.as_ref();
+            match current_section {
+                Some(section) => html.push_str(&format!("<div class=\"section-title\">{}</div>\n", escape(section))),
+                None => html.push_str("<hr>\n"),
+            }
+        }
+        list_page(&mut html, &tree, page, &anchors, 0);
+    }
+    html.push_str("</div>\n</nav>\n<main class=\"main-content\">\n");
+
+    for page in &notebook.pages {
+        page_html(&mut html, page, &anchors[page.id.as_str()]);
+    }
+    html.push_str("</main>\n</body>\n</html>\n");
+    html
+}
+
+/// A sidebar entry as in the app: title, number and creation date.
+fn list_page(
+    html: &mut String,
+    tree: &HashMap<Option<&str>, Vec<&Page>>,
+    page: &Page,
+    anchors: &HashMap<&str, String>,
+    depth: usize,
+) {
+    html.push_str(&format!(
+        "<a class=\"page-item\" href=\"#{}\" style=\"padding-left: {}px\">\
+         <div class=\"page-title\">{}</div><div class=\"page-meta\">#{} • {}</div></a>\n",
+        anchors[page.id.as_str()],
+        20 + 16 * depth,
+        escape(display_title(page)),
+        page.number.unwrap_or(0),
+        page.created.format("%m/%d/%Y")
+    ));
+    for subpage in tree.get(&Some(page.id.as_str())).into_iter().flatten() {
+        list_page(html, tree, subpage, anchors, depth + 1);
+    }
+}
+
+fn page_html(html: &mut String, page: &Page, anchor: &str) {
+    html.push_str(&format!("<section class=\"page\" id=\"{}\">\n<header class=\"content-header\">\n", anchor));
+    html.push_str(&format!("<h1>{}</h1>\n", escape(display_title(page))));
+    let mut meta = Vec::new();
+    if let Some(number) = page.number {
+        meta.push(format!("Page #{}", number));
+    }
+    meta.push(format!("Created {}", page.created.format("%Y-%m-%d %H:%M UTC")));
+    meta.push(format!("Modified {}", page.modified.format("%Y-%m-%d %H:%M UTC")));
+    html.push_str(&format!("<div class=\"page-meta\">{}</div>\n</header>\n", meta.join(" • ")));
+    html.push_str(&format!("<div class=\"page-content\">{}</div>\n", escape(&page.content)));
+
+    for drawing in &page.drawings {
+        html.push_str(&format!("<figure class=\"drawing\">\n{}</figure>\n", drawing.to_svg()));
+    }
+    if !page.attachments.is_empty() {
+        html.push_str("<ul class=\"attachments\">\n");
+        for attachment in &page.attachments {
+            let data = format!("data:{};base64,{}", escape(&attachment.mime_type), BASE64.encode(&attachment.data));
+            let name = escape(&attachment.name);
+            if attachment.mime_type.starts_with("image/") {
+                html.push_str(&format!("<li><img src=\"{}\" alt=\"{}\"></li>\n", data, name));
+            } else {
+                html.push_str(&format!("<li><a href=\"{}\" download=\"{}\">{}</a></li>\n", data, name, name));
+            }
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str("</section>\n");
+}
+
+/// An element ID for each page, made from the page ID and unique within
+/// the file.
+fn page_anchors(notebook: &Notebook) -> HashMap<&str, String> {
+    let mut used = HashSet::new();
+    let mut anchors = HashMap::new();
+    for page in &notebook.pages {
+        let id: String = page
+            .id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        let mut anchor = format!("page-{}", id);
+        let mut count = 1;
+        while !used.insert(anchor.clone()) {
+            count += 1;
+            anchor = format!("page-{}-{}", id, count);
+        }
+        anchors.insert(page.id.as_str(), anchor);
+    }
+    anchors
+}
+
+fn display_title(page: &Page) -> &str {
+    if page.title.is_empty() {
+        "Untitled"
+    } else {
+        &page.title
+    }
+}
+
+/// Escape text for use in HTML content and quoted attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notebook::Attachment;
+
+    #[test]
+    fn test_export_escapes_pages_and_links_them_from_the_sidebar() {
+        let mut notebook = Notebook::new("R&D <Notes>".to_string());
+        notebook.add_page(Page::new("Plans".to_string(), "<script>alert(\"x\")</script>\n& more".to_string(), None));
+        let parent_id = notebook.pages[0].id.clone();
+        let mut subpage = Page::new(String::new(), String::new(), None);
+        subpage.id = "sub page/1".to_string();
+        subpage.attachments.push(Attachment {
+            name: "a\"b.txt".to_string(),
+            mime_type: "text/plain".to_string(),
+            data: b"hi".to_vec(),
+        });
+        notebook.add_subpage(&parent_id, subpage);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = export_notebook(&notebook, &dir.path().join("notes.html")).unwrap();
+        let html = fs::read_to_string(path).unwrap();
+
+        assert!(html.contains("<title>R&amp;D &lt;Notes&gt;</title>"));
+        assert!(html.contains("&lt;script&gt;alert(&quot;x&quot;)&lt;/script&gt;\n&amp; more"));
+        assert!(!html.contains("<script>"));
+        let anchor = format!("page-{}", parent_id);
+        assert!(html.contains(&format!("href=\"#{}\" style=\"padding-left: 20px\"", anchor)));
+        assert!(html.contains(&format!("<section class=\"page\" id=\"{}\">", anchor)));
+        assert!(html.contains("href=\"#page-sub_page_1\" style=\"padding-left: 36px\"><div class=\"page-title\">Untitled</div>"));
+        assert!(html.contains("<div class=\"page-meta\">Page #2 • Created "));
+        assert!(html.contains("<a href=\"data:text/plain;base64,aGk=\" download=\"a&quot;b.txt\">a&quot;b.txt</a>"));
+    }
+
+    #[test]
+    fn test_anchors_are_unique() {
+        let mut notebook = Notebook::new("Anchors".to_string());
+        for id in ["a b", "a/b", "c"] {
+            let mut page = Page::new(id.to_string(), String::new(), None);
+            page.id = id.to_string();
+            notebook.pages.push(page);
+        }
+        let anchors = page_anchors(&notebook);
+        assert_eq!(anchors["a b"], "page-a_b");
+        assert_eq!(anchors["a/b"], "page-a_b-2");
+        assert_eq!(anchors["c"], "page-c");
+    }
+}
@@ -0,0 +1,357 @@
+use crate::store::DirectoryStore;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How often a held lock is rewritten to show that its owner is still running.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Locks not refreshed for this long are left over from an instance that
+/// stopped without releasing them, such as after a crash or power loss.
+const STALE_AFTER: Duration = Duration::from_secs(5 * 60);
+
+/// An advisory lock on a notebook, held while it is open for editing. It is a
+/// lock file next to the notebook naming the process that holds it, so that
+/// other NotebookX instances, including ones on other machines sharing the
+/// drive, can offer to open the notebook read-only instead. A background
+/// thread refreshes the lock file while it is held, so that it does not go
+/// stale while the window is minimized and not redrawn.
+#[derive(Debug)]
+pub struct NotebookLock {
+    path: PathBuf,
+    owner: LockOwner,
+    keeper: Option<Keeper>,
+}
+
+/// The thread refreshing a held lock.
+#[derive(Debug)]
+struct Keeper {
+    /// Set to stop the thread, which waits on the condition variable
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    /// Set by the thread when another instance took the lock over
+    lost: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+/// The instance holding a lock, as written in the lock file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LockOwner {
+    pub pid: u32,
+    pub hostname: String,
+    pub since: DateTime<Utc>,
+}
+
+impl fmt::Display for LockOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The notebook is open in NotebookX (process {} on {}) since {}",
+            self.pid,
+            self.hostname,
+            self.since.format("%Y-%m-%d %H:%M UTC")
+        )
+    }
+}
+
+impl std::error::Error for LockOwner {}
+
+impl From<LockOwner> for io::Error {
+    fn from(owner: LockOwner) -> Self {
+        io::Error::new(io::ErrorKind::ResourceBusy, owner)
+    }
+}
+
+impl NotebookLock {
+    /// Lock the notebook at `file_path`. Fails with a [`LockOwner`] error when
+    /// another running instance holds the lock; stale locks are replaced.
+    pub fn acquire(file_path: &Path) -> io::Result<Self> {
+        let path = lock_path(file_path);
+        let owner = LockOwner {
+            pid: std::process::id(),
+            hostname: hostname(),
+            since: Utc::now(),
+        };
+        let text = serde_json::to_string(&owner)?;
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(text.as_bytes())?;
+                    let keeper = Keeper::start(path.clone(), owner.clone())?;
+                    return Ok(Self {
+                        path,
+                        owner,
+                        keeper: Some(keeper),
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    let Some(held) = read_lock_text(&path)? else {
+                        // Released meanwhile
+                        continue;
+                    };
+                    let holder: Option<LockOwner> = serde_json::from_str(&held).ok();
+                    if !is_stale(&path, holder.as_ref())? {
+                        // Unreadable lock files are respected until they go stale
+                        return Err(holder
+                            .unwrap_or_else(|| LockOwner {
+                                pid: 0,
+                                hostname: "an unknown computer".to_string(),
+                                since: Utc::now(),
+                            })
+                            .into());
+                    }
+                    remove_stale(&path, &held)?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::ResourceBusy, "The notebook lock keeps changing"))
+    }
+
+    /// Whether the lock is still ours. It is lost when another instance took
+    /// it over, for example after this machine slept long enough for it to
+    /// look stale.
+    pub fn is_held(&self) -> bool {
+        self.keeper.as_ref().is_some_and(|keeper| !keeper.lost.load(Ordering::Relaxed))
+    }
+}
+
+impl Keeper {
+    fn start(path: PathBuf, owner: LockOwner) -> io::Result<Self> {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let lost = Arc::new(AtomicBool::new(false));
+        let (thread_stop, thread_lost) = (stop.clone(), lost.clone());
+        let thread = thread::Builder::new().name("notebook-lock".to_string()).spawn(move || {
+            let (stopped, wake) = &*thread_stop;
+            let mut stopped = stopped.lock().unwrap();
+            loop {
+                // Stopping may come before the thread first gets the mutex
+                if *stopped {
+                    return;
+                }
+                stopped = wake.wait_timeout(stopped, REFRESH_INTERVAL).unwrap().0;
+                if *stopped {
+                    return;
+                }
+                match refresh(&path, &owner) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        thread_lost.store(true, Ordering::Relaxed);
+                        return;
+                    }
+                    Err(e) => eprintln!("Failed to refresh notebook lock: {}", e),
+                }
+            }
+        })?;
+        Ok(Self { stop, lost, thread })
+    }
+
+    fn stop(self) {
+        let (stopped, wake) = &*self.stop;
+        *stopped.lock().unwrap() = true;
+        wake.notify_one();
+        let _ = self.thread.join();
+    }
+}
+
+/// Show other instances that the owner of the lock at `path` is still
+/// running. Returns false if the lock was taken over.
+fn refresh(path: &Path, owner: &LockOwner) -> io::Result<bool> {
+    if read_lock(path)? != Some(Some(owner.clone())) {
+        return Ok(false);
+    }
+    fs::write(path, serde_json::to_string(owner)?)?;
+    Ok(true)
+}
+
+impl Drop for NotebookLock {
+    fn drop(&mut self) {
+        if let Some(keeper) = self.keeper.take() {
+            keeper.stop();
+        }
+        // A lock taken over by another instance is theirs to release
+        if let Ok(Some(Some(owner))) = read_lock(&self.path) {
+            if owner == self.owner {
+                let _ = fs::remove_file(&self.path);
+            }
+        }
+    }
+}
+
+/// The lock file of a notebook: `.<name>.lock` next to the notebook file or,
+/// for folder notebooks, next to the folder.
+fn lock_path(file_path: &Path) -> PathBuf {
+    let notebook = DirectoryStore::folder(file_path).unwrap_or_else(|| file_path.to_path_buf());
+    let name = notebook.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    notebook.with_file_name(format!(".{}.lock", name))
+}
+
+/// The owner named in a lock file: `None` if there is no lock file, and
+/// `Some(None)` if it cannot be read, such as while it is being written.
+fn read_lock(path: &Path) -> io::Result<Option<Option<LockOwner>>> {
+    Ok(read_lock_text(path)?.map(|text| serde_json::from_str(&text).ok()))
+}
+
+fn read_lock_text(path: &Path) -> io::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// A lock is stale when its process is known to have exited, or when it was
+/// not refreshed for [`STALE_AFTER`].
+fn is_stale(path: &Path, owner: Option<&LockOwner>) -> io::Result<bool> {
+    if let Some(owner) = owner {
+        if owner.hostname == hostname() && process_is_running(owner.pid) == Some(false) {
+            return Ok(true);
+        }
+    }
+    let modified = match fs::metadata(path).and_then(|metadata| metadata.modified()) {
+        Ok(modified) => modified,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e),
+    };
+    Ok(modified.elapsed().is_ok_and(|age| age > STALE_AFTER))
+}
+
+/// Remove a lock file found stale with the text `stale`. Another instance
+/// may have replaced it with a fresh lock since it was read, so it is first
+/// moved aside under a unique name and only deleted if it is still the
+/// stale lock; a fresh lock is put back unless yet another took its place.
+fn remove_stale(path: &Path, stale: &str) -> io::Result<()> {
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let aside = path.with_file_name(format!("{}.{}.stale", name, Uuid::new_v4()));
+    match fs::rename(path, &aside) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    }
+    let moved = fs::read_to_string(&aside);
+    if !moved.as_ref().is_ok_and(|moved| moved == stale) {
+        match fs::hard_link(&aside, path) {
+            Err(e) if e.kind() != io::ErrorKind::AlreadyExists => {
+                let _ = fs::remove_file(&aside);
+                return Err(e);
+            }
+            _ => {}
+        }
+    }
+    fs::remove_file(&aside)
+}
+
+/// Whether a process on this machine is running, where that can be told
+/// without platform APIs.
+fn process_is_running(pid: u32) -> Option<bool> {
+    if cfg!(target_os = "linux") {
+        Some(Path::new("/proc").join(pid.to_string()).exists())
+    } else {
+        None
+    }
+}
+
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    #[test]
+    fn test_second_lock_is_refused_until_released() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Notes.txt");
+        let lock = NotebookLock::acquire(&path).unwrap();
+        assert!(dir.path().join(".Notes.txt.lock").is_file());
+
+        let error = NotebookLock::acquire(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::ResourceBusy);
+        let owner = error.get_ref().and_then(|e| e.downcast_ref::<LockOwner>()).unwrap();
+        assert_eq!(owner.pid, std::process::id());
+
+        drop(lock);
+        assert!(!dir.path().join(".Notes.txt.lock").exists());
+        assert!(NotebookLock::acquire(&path).is_ok());
+    }
+
+    #[test]
+    fn test_stale_locks_are_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Notes.nbx").join("manifest.json");
+        let lock_file = dir.path().join(".Notes.nbx.lock");
+
+        // Left by a process on this machine that has exited
+        if cfg!(target_os = "linux") {
+            let exited = LockOwner {
+                pid: u32::MAX,
+                hostname: hostname(),
+                since: Utc::now(),
+            };
+            fs::write(&lock_file, serde_json::to_string(&exited).unwrap()).unwrap();
+            drop(NotebookLock::acquire(&path).unwrap());
+        }
+
+        // Left by an instance on another machine that stopped refreshing it
+        let owner = LockOwner {
+            pid: 1,
+            hostname: "elsewhere".to_string(),
+            since: Utc::now(),
+        };
+        fs::write(&lock_file, serde_json::to_string(&owner).unwrap()).unwrap();
+        assert!(NotebookLock::acquire(&path).is_err());
+        let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options().write(true).open(&lock_file).unwrap().set_modified(an_hour_ago).unwrap();
+        let lock = NotebookLock::acquire(&path).unwrap();
+        assert!(lock.is_held());
+        assert!(refresh(&lock.path, &lock.owner).unwrap());
+
+        // Taken over while this instance was away
+        fs::write(&lock_file, serde_json::to_string(&owner).unwrap()).unwrap();
+        assert!(!refresh(&lock.path, &lock.owner).unwrap());
+        drop(lock);
+        assert!(lock_file.exists());
+    }
+
+    #[test]
+    fn test_removing_a_stale_lock_keeps_a_fresh_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock_file = dir.path().join(".Notes.txt.lock");
+        let owner = |hostname: &str| {
+            serde_json::to_string(&LockOwner {
+                pid: 1,
+                hostname: hostname.to_string(),
+                since: Utc::now(),
+            })
+            .unwrap()
+        };
+
+        let (stale, winner) = (owner("stale"), owner("winner"));
+
+        // Another instance replaced the stale lock after it was read
+        fs::write(&lock_file, &winner).unwrap();
+        remove_stale(&lock_file, &stale).unwrap();
+        assert_eq!(fs::read_to_string(&lock_file).unwrap(), winner);
+
+        fs::write(&lock_file, &stale).unwrap();
+        remove_stale(&lock_file, &stale).unwrap();
+        assert!(!lock_file.exists());
+        remove_stale(&lock_file, &stale).unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
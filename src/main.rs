@@ -1,15 +1,36 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use eframe::egui;
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 mod notebook;
 mod file_io;
 mod onenote_converter;
+mod onestore;
+mod cab;
+mod conversion_report;
+mod batch;
+mod text_scan;
+mod drawing;
+mod store;
+mod merge;
+mod lock;
+mod markdown;
+mod html;
+mod pdf;
+mod enex;
 
-use notebook::{Notebook, Page};
-use file_io::NotebookFileHandler;
+use drawing::Drawing;
+use notebook::{Attachment, Notebook, Page};
+use file_io::{Diagnostic, NotebookFileHandler};
 use onenote_converter::OneNoteConverter;
+use lock::{LockOwner, NotebookLock};
+use merge::NotebookMerge;
+use store::{store_for_path, FileStamp};
 
 // Standard US Letter page dimensions for text content
 // US Letter: 8.5" x 11" at 96 DPI with 1" margins = 6.5" x 9" text area
@@ -18,6 +39,10 @@ const PAGE_MAX_LINES: usize = 46;
 const PAGE_MAX_CHARS_PER_LINE: usize = 80;
 const PAGE_MAX_CHARS: usize = PAGE_MAX_LINES * PAGE_MAX_CHARS_PER_LINE; // ~3680 chars
 const HINT_TEXT: &str = "Start writing your notes here...";
+// Subpages nested deeper than this are listed flat at this depth in the sidebar
+const MAX_PAGE_DEPTH: usize = 32;
+// How often the open notebook's file is checked for changes by other programs
+const FILE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Parser)]
 #[command(name = "notebookx")]
@@ -25,6 +50,39 @@ const HINT_TEXT: &str = "Start writing your notes here...";
 struct Args {
     #[arg(long, help = "Enable debug mode (shows console on Windows)")]
     debug: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Convert every .one file under a directory without opening the GUI
+    Convert {
+        /// Directory searched recursively for .one files
+        input_dir: PathBuf,
+        /// Directory the NotebookX files are written to, mirroring the input layout
+        output_dir: PathBuf,
+    },
+    /// Export a notebook to another format without opening the GUI
+    Export {
+        /// The notebook to export
+        notebook: PathBuf,
+        /// Where to write the export: a folder for Markdown, a file for HTML and PDF
+        output: PathBuf,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Markdown)]
+        format: ExportFormat,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// A folder with one Markdown file per page and an index file
+    Markdown,
+    /// A single HTML file with a table of contents
+    Html,
+    /// A PDF on US Letter paper
+    Pdf,
 }
 
 fn main() -> Result<(), eframe::Error> {
@@ -44,6 +102,22 @@ fn main() -> Result<(), eframe::Error> {
             SetConsoleTitleA(title.as_ptr());
         }
     }
+
+    if let Some(command) = args.command {
+        // Release builds have no console of their own; reuse the caller's
+        #[cfg(windows)]
+        unsafe {
+            extern "system" {
+                fn AttachConsole(process_id: u32) -> i32;
+            }
+            AttachConsole(u32::MAX);
+        }
+        std::process::exit(match command {
+            Command::Convert { input_dir, output_dir } => run_batch_conversion(&input_dir, &output_dir),
+            Command::Export { notebook, output, format } => run_export(&notebook, &output, format),
+        });
+    }
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1200.0, 800.0])
@@ -72,6 +146,26 @@ struct NotebookXApp {
     show_convert_dialog: bool,
     autosave_enabled: bool,
     current_file_path: Option<std::path::PathBuf>,
+    // Problems recovered from while opening the current notebook
+    load_diagnostics: Vec<Diagnostic>,
+    // The notebook as last read from or written to current_file_path, the
+    // base for merging changes other programs make to the file
+    disk_notebook: Option<Notebook>,
+    file_stamp: Option<FileStamp>,
+    last_file_check: Option<Instant>,
+    pending_merge: Option<PendingMerge>,
+    // Held while the open notebook is editable; without it the notebook is read-only
+    notebook_lock: Option<NotebookLock>,
+    read_only: bool,
+    // A notebook that another instance has open, waiting for the user's choice
+    locked_notebook: Option<(PathBuf, LockOwner)>,
+}
+
+// Changes another program made to the open notebook while it had local edits
+struct PendingMerge {
+    merge: NotebookMerge,
+    theirs: Notebook,
+    stamp: Option<FileStamp>,
 }
 
 impl NotebookXApp {
@@ -118,8 +212,28 @@ impl NotebookXApp {
     }
     
     fn select_page(&mut self, page_id: &str) {
-        if let Some(notebook) = &self.notebook {
-            if let Some(page) = notebook.get_page(page_id) {
+        // Unloaded pages are read at the offsets their file had when it was
+        // opened, so a file changed since then is reloaded or merged first
+        let unloaded = self
+            .notebook
+            .as_ref()
+            .and_then(|notebook| notebook.get_page(page_id))
+            .is_some_and(|page| page.source.is_some());
+        if unloaded && !self.check_for_external_changes() {
+            return;
+        }
+        
+        if let Some(notebook) = &mut self.notebook {
+            if let Some(page) = notebook.get_page_mut(page_id) {
+                // Pages opened from an index are read when first shown
+                match self.file_handler.load_page(page) {
+                    Ok(diagnostics) => self.load_diagnostics.extend(diagnostics),
+                    Err(e) => {
+                        eprintln!("Failed to load page: {}", e);
+                        return;
+                    }
+                }
+                
                 self.current_page_id = Some(page_id.to_string());
                 self.page_title_buffer = page.title.clone();
                 self.page_content_buffer = page.content.clone();
@@ -128,6 +242,15 @@ impl NotebookXApp {
     }
     
     fn save_current_page(&mut self) {
+        self.store_current_page();
+        
+        // Auto-save to file if enabled and file path exists
+        if self.autosave_enabled && self.current_page_id.is_some() {
+            self.autosave();
+        }
+    }
+    
+    fn store_current_page(&mut self) {
         let clean_content = self.clean_content(&self.page_content_buffer);
         
         if let (Some(notebook), Some(page_id)) = (&mut self.notebook, &self.current_page_id) {
@@ -136,12 +259,108 @@ impl NotebookXApp {
                 self.page_title_buffer.clone(),
                 clean_content,
             );
-            
-            // Auto-save to file if enabled and file path exists
-            if self.autosave_enabled {
-                if let Some(file_path) = &self.current_file_path {
-                    let _ = self.file_handler.save_notebook(notebook, file_path.clone());
+        }
+    }
+    
+    fn autosave(&mut self) {
+        // Never write over changes another program made since the last save
+        self.check_for_external_changes();
+        if self.pending_merge.is_some() || self.read_only {
+            return;
+        }
+        if let (Some(notebook), Some(file_path)) = (&mut self.notebook, &self.current_file_path) {
+            if store_for_path(file_path, &self.file_handler).save_notebook(notebook, file_path.clone()).is_ok() {
+                self.mark_saved();
+            }
+        }
+    }
+    
+    // Remember the notebook as it now is on disk
+    fn mark_saved(&mut self) {
+        self.disk_notebook = self.notebook.clone();
+        self.file_stamp = self.current_file_path.as_deref().and_then(|path| FileStamp::of(path).ok());
+    }
+    
+    // Reload the notebook if another program changed its file and there are
+    // no local edits; prepare a merge when both sides changed. Returns
+    // whether unloaded pages can still be read from the file, which they
+    // cannot while a merge is pending or after the file failed to reload
+    fn check_for_external_changes(&mut self) -> bool {
+        let Some(file_path) = self.current_file_path.clone() else {
+            return true;
+        };
+        if self.pending_merge.is_some() {
+            return false;
+        }
+        let stamp = FileStamp::of(&file_path).ok();
+        // A deleted file is written again by the next save
+        if stamp.is_none() || stamp == self.file_stamp {
+            return true;
+        }
+        
+        self.store_current_page();
+        let store = store_for_path(&file_path, &self.file_handler);
+        let (Some(base), Some(ours)) = (&self.disk_notebook, &self.notebook) else {
+            return true;
+        };
+        if !merge::has_changes(base, ours) {
+            return match store.load_notebook_index(file_path) {
+                Ok((notebook, diagnostics)) => {
+                    self.notebook = Some(notebook);
+                    self.load_diagnostics.extend(diagnostics);
+                    self.mark_saved();
+                    self.reselect_page();
+                    true
+                }
+                Err(e) => {
+                    eprintln!("Failed to reload notebook: {}", e);
+                    self.file_stamp = stamp;
+                    false
                 }
+            };
+        }
+        match store.load_notebook(file_path) {
+            Ok((theirs, diagnostics)) => {
+                let merge = merge::merge_notebooks(base, ours, &theirs);
+                self.load_diagnostics.extend(diagnostics);
+                self.pending_merge = Some(PendingMerge { merge, theirs, stamp });
+            }
+            Err(e) => {
+                eprintln!("Failed to read changed notebook: {}", e);
+                self.file_stamp = stamp;
+            }
+        }
+        false
+    }
+    
+    // Replace the notebook with the result of an external change
+    fn finish_merge(&mut self, notebook: Notebook, theirs: Notebook, stamp: Option<FileStamp>) {
+        self.notebook = Some(notebook);
+        self.disk_notebook = Some(theirs);
+        self.file_stamp = stamp;
+        self.reselect_page();
+        if self.autosave_enabled {
+            self.autosave();
+        }
+    }
+    
+    // Show the current page again after the notebook was replaced, or the
+    // first page if it is gone
+    fn reselect_page(&mut self) {
+        let Some(notebook) = &self.notebook else {
+            return;
+        };
+        let page_id = self
+            .current_page_id
+            .clone()
+            .filter(|page_id| notebook.get_page(page_id).is_some())
+            .or_else(|| notebook.pages.first().map(|page| page.id.clone()));
+        match page_id {
+            Some(page_id) => self.select_page(&page_id),
+            None => {
+                self.current_page_id = None;
+                self.page_title_buffer.clear();
+                self.page_content_buffer.clear();
             }
         }
     }
@@ -207,7 +426,16 @@ impl NotebookXApp {
             );
             
             let new_page_id = new_page.id.clone();
-            notebook.add_page(new_page);
+            // A continuation of a subpage stays under the same parent
+            let parent_id = self
+                .current_page_id
+                .as_deref()
+                .and_then(|id| notebook.get_page(id))
+                .and_then(|page| page.parent_id.clone());
+            match parent_id {
+                Some(parent_id) => notebook.add_subpage(&parent_id, new_page),
+                None => notebook.add_page(new_page),
+            }
             
             // Switch to the new page immediately
             self.current_page_id = Some(new_page_id);
@@ -232,23 +460,69 @@ impl NotebookXApp {
         }
     }
     
+    fn create_new_subpage(&mut self) {
+        self.save_current_page();
+        if let (Some(notebook), Some(parent_id)) = (&mut self.notebook, self.current_page_id.clone()) {
+            let new_page = Page::new(
+                "New Page".to_string(),
+                "".to_string(),
+                None,
+            );
+            let page_id = new_page.id.clone();
+            notebook.add_subpage(&parent_id, new_page);
+            self.select_page(&page_id);
+        }
+    }
+    
     fn open_notebook(&mut self) {
+        // Folder notebooks are opened through the manifest.json inside them
         if let Some(file_path) = rfd::FileDialog::new()
-            .add_filter("NotebookX Files", &["txt"])
+            .add_filter("NotebookX Files", &["txt", "json"])
+            .add_filter("NotebookX Backups", &["bak"])
             .pick_file()
         {
-            match self.file_handler.load_notebook(file_path.clone()) {
-                Ok(notebook) => {
-                    self.notebook = Some(notebook);
-                    self.current_file_path = Some(file_path);
-                    if let Some(first_page) = self.notebook.as_ref().unwrap().pages.first() {
-                        let page_id = first_page.id.clone();
-                        self.select_page(&page_id);
+            match self.lock_notebook(&file_path) {
+                Ok(lock) => self.load_notebook(file_path, Some(lock)),
+                Err(e) => match e.get_ref().and_then(|e| e.downcast_ref::<LockOwner>()) {
+                    Some(owner) => self.locked_notebook = Some((file_path, owner.clone())),
+                    None => {
+                        // For example a folder this user cannot write to
+                        eprintln!("Failed to lock notebook, opening it read-only: {}", e);
+                        self.load_notebook(file_path, None);
                     }
+                },
+            }
+        }
+    }
+    
+    // Lock a notebook for editing, keeping the lock already held on it
+    fn lock_notebook(&mut self, file_path: &Path) -> std::io::Result<NotebookLock> {
+        if self.current_file_path.as_deref() == Some(file_path) {
+            if let Some(lock) = self.notebook_lock.take() {
+                return Ok(lock);
+            }
+        }
+        self.file_handler.lock_notebook(file_path)
+    }
+    
+    // Open a notebook, read-only when it could not be locked
+    fn load_notebook(&mut self, file_path: PathBuf, lock: Option<NotebookLock>) {
+        match store_for_path(&file_path, &self.file_handler).load_notebook_index(file_path.clone()) {
+            Ok((notebook, diagnostics)) => {
+                self.notebook = Some(notebook);
+                self.load_diagnostics = diagnostics;
+                self.current_file_path = Some(file_path);
+                self.read_only = lock.is_none();
+                self.notebook_lock = lock;
+                self.pending_merge = None;
+                self.mark_saved();
+                if let Some(first_page) = self.notebook.as_ref().unwrap().pages.first() {
+                    let page_id = first_page.id.clone();
+                    self.select_page(&page_id);
                 }
-                Err(e) => {
-                    eprintln!("Failed to load notebook: {}", e);
-                }
+            }
+            Err(e) => {
+                eprintln!("Failed to load notebook: {}", e);
             }
         }
     }
@@ -256,18 +530,36 @@ impl NotebookXApp {
     fn save_notebook(&mut self) {
         self.save_current_page(); // Save current changes first
         
-        if let Some(notebook) = &self.notebook {
+        if self.notebook.is_some() {
             if let Some(file_path) = rfd::FileDialog::new()
                 .add_filter("NotebookX Files", &["txt"])
+                .add_filter("JSON Notebooks", &["json"])
+                .add_filter("Notebook Folders", &[store::DIRECTORY_EXTENSION])
                 .save_file()
             {
-                match self.file_handler.save_notebook(notebook, file_path.clone()) {
+                // Never write to a notebook another instance is editing
+                let lock = match self.lock_notebook(&file_path) {
+                    Ok(lock) => lock,
+                    Err(e) => {
+                        eprintln!("Failed to save notebook: {}", e);
+                        return;
+                    }
+                };
+                let notebook = self.notebook.as_mut().unwrap();
+                match store_for_path(&file_path, &self.file_handler).save_notebook(notebook, file_path.clone()) {
                     Ok(_) => {
                         self.current_file_path = Some(file_path);
+                        self.notebook_lock = Some(lock);
+                        self.read_only = false;
+                        self.pending_merge = None;
+                        self.mark_saved();
                         println!("Notebook saved successfully");
                     }
                     Err(e) => {
                         eprintln!("Failed to save notebook: {}", e);
+                        if self.current_file_path.as_ref() == Some(&file_path) {
+                            self.notebook_lock = Some(lock);
+                        }
                     }
                 }
             }
@@ -276,26 +568,34 @@ impl NotebookXApp {
     
     fn convert_onenote_file(&mut self) {
         if let Some(file_path) = rfd::FileDialog::new()
-            .add_filter("OneNote Files", &["one"])
+            .add_filter("OneNote Files", &["one", "onetoc2", "onepkg"])
             .pick_file()
         {
-            match self.onenote_converter.convert_to_notebookx(file_path.clone()) {
+            let extension = file_path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.to_ascii_lowercase())
+                .unwrap_or_default();
+            
+            let result = match extension.as_str() {
+                "onetoc2" => self.onenote_converter.convert_notebook(file_path.clone()),
+                "onepkg" => self.onenote_converter.convert_package(file_path.clone()),
+                _ => self.onenote_converter.convert_to_notebookx(file_path.clone()),
+            };
+            
+            match result {
                 Ok(mut converted_notebook) => {
                     // Add a conversion report page
                     match self.onenote_converter.create_conversion_report(file_path) {
-                        Ok(report_page) => {
-                            converted_notebook.add_page(report_page);
+                        Ok(report) => {
+                            converted_notebook.add_page(report.to_page());
                         }
                         Err(e) => {
                             eprintln!("Failed to create conversion report: {}", e);
                         }
                     }
                     
-                    self.notebook = Some(converted_notebook);
-                    if let Some(first_page) = self.notebook.as_ref().unwrap().pages.first() {
-                        let page_id = first_page.id.clone();
-                        self.select_page(&page_id);
-                    }
+                    self.show_imported_notebook(converted_notebook);
                 }
                 Err(e) => {
                     eprintln!("Failed to convert OneNote file: {}", e);
@@ -303,12 +603,104 @@ impl NotebookXApp {
             }
         }
     }
+    
+    fn import_markdown_folder(&mut self) {
+        if let Some(folder) = rfd::FileDialog::new().set_title("Import Markdown Folder").pick_folder() {
+            match markdown::import_directory(&folder) {
+                Ok(notebook) => self.show_imported_notebook(notebook),
+                Err(e) => eprintln!("Failed to import Markdown folder: {}", e),
+            }
+        }
+    }
+    
+    fn import_evernote_export(&mut self) {
+        if let Some(file_path) = rfd::FileDialog::new()
+            .add_filter("Evernote Export", &["enex"])
+            .pick_file()
+        {
+            match enex::import_enex(&file_path) {
+                Ok((mut notebook, report)) => {
+                    // Add a page listing what could not be imported
+                    notebook.add_page(report.to_page());
+                    self.show_imported_notebook(notebook);
+                }
+                Err(e) => eprintln!("Failed to import Evernote export: {}", e),
+            }
+        }
+    }
+    
+    // Show a converted or imported notebook, which is not saved anywhere yet
+    fn show_imported_notebook(&mut self, notebook: Notebook) {
+        self.notebook = Some(notebook);
+        self.current_file_path = None;
+        self.notebook_lock = None;
+        self.read_only = false;
+        self.disk_notebook = None;
+        self.pending_merge = None;
+        self.load_diagnostics.clear();
+        if let Some(first_page) = self.notebook.as_ref().unwrap().pages.first() {
+            let page_id = first_page.id.clone();
+            self.select_page(&page_id);
+        }
+    }
+    
+    // Export the open notebook to a file or folder the user picks
+    fn export_current_notebook(&mut self, format: ExportFormat) {
+        self.store_current_page();
+        let title = self.notebook.as_ref().map(|notebook| notebook.title.clone()).unwrap_or_default();
+        let output = match format {
+            ExportFormat::Markdown => rfd::FileDialog::new().set_title("Export as Markdown").pick_folder(),
+            ExportFormat::Html => rfd::FileDialog::new()
+                .add_filter("HTML File", &["html"])
+                .set_file_name(format!("{}.html", title))
+                .save_file(),
+            ExportFormat::Pdf => rfd::FileDialog::new()
+                .add_filter("PDF File", &["pdf"])
+                .set_file_name(format!("{}.pdf", title))
+                .save_file(),
+        };
+        let Some(output) = output else {
+            return;
+        };
+        if !self.check_for_external_changes() {
+            eprintln!("The notebook file changed; export again once it is reloaded");
+            return;
+        }
+        let Some(notebook) = &mut self.notebook else {
+            return;
+        };
+        
+        // Pages not shown yet are still on disk
+        match self.file_handler.load_all_pages(notebook) {
+            Ok(diagnostics) => self.load_diagnostics.extend(diagnostics),
+            Err(e) => {
+                eprintln!("Failed to load pages for export: {}", e);
+                return;
+            }
+        }
+        if let Err(e) = export_notebook(notebook, &output, format) {
+            eprintln!("Failed to export notebook: {}", e);
+        }
+    }
 }
 
 impl eframe::App for NotebookXApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.ensure_notebook();
         
+        // Notice changes other programs make to the open notebook
+        if self.last_file_check.is_none_or(|checked| checked.elapsed() >= FILE_CHECK_INTERVAL) {
+            self.last_file_check = Some(Instant::now());
+            self.check_for_external_changes();
+            // The lock refreshes itself; it can still be taken over
+            if self.notebook_lock.as_ref().is_some_and(|lock| !lock.is_held()) {
+                eprintln!("Another NotebookX window took over this notebook; it is now read-only");
+                self.notebook_lock = None;
+                self.read_only = true;
+            }
+        }
+        ctx.request_repaint_after(FILE_CHECK_INTERVAL);
+        
         egui::SidePanel::left("pages_panel")
             .min_width(300.0)
             .max_width(400.0)
@@ -318,9 +710,12 @@ impl eframe::App for NotebookXApp {
                     ui.separator();
                     
                     ui.horizontal(|ui| {
-                        if ui.button("New Page").clicked() {
+                        if ui.add_enabled(!self.read_only, egui::Button::new("New Page")).clicked() {
                             self.create_new_page();
                         }
+                        if ui.add_enabled(self.current_page_id.is_some() && !self.read_only, egui::Button::new("New Subpage")).clicked() {
+                            self.create_new_subpage();
+                        }
                         if ui.button("Open").clicked() {
                             self.open_notebook();
                         }
@@ -333,6 +728,30 @@ impl eframe::App for NotebookXApp {
                         if ui.button("Convert OneNote").clicked() {
                             self.convert_onenote_file();
                         }
+                        ui.menu_button("Import", |ui| {
+                            if ui.button("Markdown Folder...").clicked() {
+                                ui.close_menu();
+                                self.import_markdown_folder();
+                            }
+                            if ui.button("Evernote Export (.enex)...").clicked() {
+                                ui.close_menu();
+                                self.import_evernote_export();
+                            }
+                        });
+                        ui.menu_button("Export", |ui| {
+                            if ui.button("Markdown Folder...").clicked() {
+                                ui.close_menu();
+                                self.export_current_notebook(ExportFormat::Markdown);
+                            }
+                            if ui.button("HTML File...").clicked() {
+                                ui.close_menu();
+                                self.export_current_notebook(ExportFormat::Html);
+                            }
+                            if ui.button("PDF File...").clicked() {
+                                ui.close_menu();
+                                self.export_current_notebook(ExportFormat::Pdf);
+                            }
+                        });
                     });
                     
                     ui.separator();
@@ -345,6 +764,14 @@ impl eframe::App for NotebookXApp {
                         } else if self.autosave_enabled {
                             ui.colored_label(egui::Color32::from_rgb(255, 165, 0), "⚠ No file");
                         }
+                        if self.read_only {
+                            ui.colored_label(egui::Color32::from_rgb(255, 165, 0), "Read-only")
+                                .on_hover_text("Open in another NotebookX window. Save under a new name to edit a copy.");
+                        }
+                        ui.separator();
+                        ui.label("Backups:");
+                        ui.add(egui::DragValue::new(&mut self.file_handler.backups).range(0..=20))
+                            .on_hover_text("Earlier saves kept as timestamped .bak files next to the notebook");
                     });
                     
                     ui.separator();
@@ -353,20 +780,31 @@ impl eframe::App for NotebookXApp {
                     
                     egui::ScrollArea::vertical().show(ui, |ui| {
                         if let Some(notebook) = &self.notebook {
-                            for page in &notebook.pages {
-                                let is_selected = self.current_page_id.as_ref() == Some(&page.id);
-                                let response = ui.selectable_label(
-                                    is_selected,
-                                    format!("{}\n#{} • {}", 
-                                        if page.title.is_empty() { "Untitled" } else { &page.title },
-                                        page.number.unwrap_or(0),
-                                        page.created.format("%m/%d/%Y")
-                                    )
-                                );
-                                
-                                if response.clicked() {
-                                    selected_page_id = Some(page.id.clone());
+                            let tree = notebook.subpages_by_parent();
+                            let mut current_section: Option<&String> = None;
+                            for page in tree.get(&None).into_iter().flatten() {
+                                // Group imported pages under their OneNote section
+                                if page.section.as_ref() != current_section {
+                                    current_section = page.section.as_ref();
+                                    match current_section {
+                                        Some(section) => {
+                                            ui.add_space(4.0);
+                                            ui.strong(section);
+                                        }
+                                        None => {
+                                            ui.separator();
+                                        }
+                                    }
                                 }
+                                
+                                show_page_tree(
+                                    ui,
+                                    &tree,
+                                    page,
+                                    self.current_page_id.as_deref(),
+                                    &mut selected_page_id,
+                                    0,
+                                );
                             }
                         }
                     });
@@ -383,7 +821,7 @@ impl eframe::App for NotebookXApp {
                 // Header
                 ui.horizontal(|ui| {
                     ui.label("Title:");
-                    let title_response = ui.text_edit_singleline(&mut self.page_title_buffer);
+                    let title_response = ui.add_enabled(!self.read_only, egui::TextEdit::singleline(&mut self.page_title_buffer));
                     if title_response.changed() {
                         // Auto-save on title change with a delay would be implemented here
                     }
@@ -398,6 +836,26 @@ impl eframe::App for NotebookXApp {
                             page.created.format("%m/%d/%Y %H:%M"),
                             page.modified.format("%m/%d/%Y %H:%M")
                         ));
+                        if !page.attachments.is_empty() {
+                            ui.horizontal_wrapped(|ui| {
+                                for attachment in &page.attachments {
+                                    let label = format!("📎 {} ({} bytes)", attachment.name, attachment.data.len());
+                                    if ui.button(label).on_hover_text(&attachment.mime_type).clicked() {
+                                        save_attachment(attachment);
+                                    }
+                                }
+                            });
+                        }
+                        if !page.drawings.is_empty() {
+                            ui.horizontal_wrapped(|ui| {
+                                for (index, drawing) in page.drawings.iter().enumerate() {
+                                    let label = format!("✏ Drawing {} ({} strokes)", index + 1, drawing.strokes.len());
+                                    if ui.button(label).on_hover_text("Export as SVG").clicked() {
+                                        export_drawing(drawing, &format!("{} - Drawing {}.svg", page.title, index + 1));
+                                    }
+                                }
+                            });
+                        }
                     }
                 }
                 
@@ -425,12 +883,23 @@ impl eframe::App for NotebookXApp {
                     .stick_to_bottom(false)
                     .auto_shrink([false, false])
                     .show(ui, |ui| {
+                        let drawings = self
+                            .current_page_id
+                            .as_deref()
+                            .and_then(|id| self.notebook.as_ref()?.get_page(id))
+                            .map(|page| page.drawings.as_slice())
+                            .unwrap_or_default();
+                        for drawing in drawings {
+                            paint_drawing(ui, drawing);
+                        }
+                        
                         let text_edit = egui::TextEdit::multiline(&mut self.page_content_buffer)
                             .desired_width(f32::INFINITY)
                             .desired_rows(30)
                             .hint_text(HINT_TEXT)
                             .font(egui::TextStyle::Monospace)
-                            .code_editor();
+                            .code_editor()
+                            .interactive(!self.read_only);
                         
                         let content_response = ui.add_sized([ui.available_width(), ui.available_height()], text_edit);
                         
@@ -467,9 +936,257 @@ impl eframe::App for NotebookXApp {
             });
         });
         
+        if !self.load_diagnostics.is_empty() {
+            let mut open = true;
+            egui::Window::new("Problems in Notebook File")
+                .open(&mut open)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.label("These problems were found while opening the notebook. The rest of the file was loaded.");
+                    ui.separator();
+                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        for diagnostic in &self.load_diagnostics {
+                            ui.label(diagnostic.to_string());
+                        }
+                    });
+                });
+            if !open {
+                self.load_diagnostics.clear();
+            }
+        }
+        
+        let mut open_read_only = None;
+        if let Some((_, owner)) = &self.locked_notebook {
+            egui::Window::new("Notebook In Use")
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.label(format!("{}.", owner));
+                    ui.label("You can open it read-only; changes saved in the other window appear here automatically.");
+                    ui.horizontal(|ui| {
+                        if ui.button("Open Read-Only").clicked() {
+                            open_read_only = Some(true);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            open_read_only = Some(false);
+                        }
+                    });
+                });
+        }
+        if let (Some(open_read_only), Some((file_path, _))) = (open_read_only, self.locked_notebook.take()) {
+            if open_read_only {
+                self.save_current_page();
+                self.load_notebook(file_path, None);
+            }
+        }
+        
+        let mut apply_merge = None;
+        if let Some(pending) = &mut self.pending_merge {
+            egui::Window::new("Notebook Changed on Disk")
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.label("Another program changed the notebook file while this window had unsaved changes.");
+                    if pending.merge.updated_pages > 0 {
+                        ui.label(format!("{} pages will be updated from the file.", pending.merge.updated_pages));
+                    }
+                    if !pending.merge.conflicts.is_empty() {
+                        ui.separator();
+                        ui.label("These pages were changed in both places. Choose which version to keep:");
+                        egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                            for conflict in &mut pending.merge.conflicts {
+                                let mine = if conflict.ours.is_some() { "Mine" } else { "Mine (deleted)" };
+                                let theirs = if conflict.theirs.is_some() { "From file" } else { "From file (deleted)" };
+                                ui.horizontal(|ui| {
+                                    ui.label(conflict.title().to_string());
+                                    ui.radio_value(&mut conflict.take_theirs, false, mine);
+                                    ui.radio_value(&mut conflict.take_theirs, true, theirs);
+                                });
+                            }
+                        });
+                    }
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.button("Merge").clicked() {
+                            apply_merge = Some(true);
+                        }
+                        if ui.button("Discard My Changes").clicked() {
+                            apply_merge = Some(false);
+                        }
+                    });
+                });
+        }
+        if let (Some(apply_merge), Some(pending)) = (apply_merge, self.pending_merge.take()) {
+            let notebook = if apply_merge {
+                pending.merge.finish()
+            } else {
+                pending.theirs.clone()
+            };
+            self.finish_merge(notebook, pending.theirs, pending.stamp);
+        }
     }
     
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
         self.save_current_page();
+        self.notebook_lock = None;
+    }
+}
+
+/// Show a page in the sidebar, with its subpages under a collapsible arrow.
+/// At `MAX_PAGE_DEPTH` all deeper descendants are listed flat.
+fn show_page_tree(
+    ui: &mut egui::Ui,
+    tree: &HashMap<Option<&str>, Vec<&Page>>,
+    page: &Page,
+    current_page_id: Option<&str>,
+    selected_page_id: &mut Option<String>,
+    depth: usize,
+) {
+    let Some(subpages) = tree.get(&Some(page.id.as_str())) else {
+        show_page_label(ui, page, current_page_id, selected_page_id);
+        return;
+    };
+    let id = ui.make_persistent_id(("page_tree", &page.id));
+    egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, true)
+        .show_header(ui, |ui| show_page_label(ui, page, current_page_id, selected_page_id))
+        .body(|ui| {
+            if depth + 1 < MAX_PAGE_DEPTH {
+                for subpage in subpages {
+                    show_page_tree(ui, tree, subpage, current_page_id, selected_page_id, depth + 1);
+                }
+                return;
+            }
+            // Depth first without recursing, so deep pages stay reachable
+            let mut pending: Vec<&Page> = subpages.iter().rev().copied().collect();
+            while let Some(descendant) = pending.pop() {
+                show_page_label(ui, descendant, current_page_id, selected_page_id);
+                if let Some(children) = tree.get(&Some(descendant.id.as_str())) {
+                    pending.extend(children.iter().rev());
+                }
+            }
+        });
+}
+
+/// A page's entry in the sidebar: title, number and creation date.
+fn show_page_label(ui: &mut egui::Ui, page: &Page, current_page_id: Option<&str>, selected_page_id: &mut Option<String>) {
+    let is_selected = current_page_id == Some(page.id.as_str());
+    let label = format!(
+        "{}\n#{} • {}",
+        if page.title.is_empty() { "Untitled" } else { &page.title },
+        page.number.unwrap_or(0),
+        page.created.format("%m/%d/%Y")
+    );
+    if ui.selectable_label(is_selected, label).clicked() {
+        *selected_page_id = Some(page.id.clone());
+    }
+}
+
+/// Run the `convert` subcommand and return the process exit code.
+fn run_batch_conversion(input_dir: &Path, output_dir: &Path) -> i32 {
+    match batch::convert_directory(input_dir, output_dir) {
+        Ok(summary) => {
+            for path in &summary.converted {
+                println!("Converted {}", path.display());
+            }
+            for (path, error) in &summary.failed {
+                eprintln!("Failed to convert {}: {}", path.display(), error);
+            }
+            println!(
+                "Converted {} of {} OneNote files ({} failed)",
+                summary.converted.len(),
+                summary.total(),
+                summary.failed.len()
+            );
+            if summary.failed.is_empty() { 0 } else { 1 }
+        }
+        Err(e) => {
+            eprintln!("Failed to convert {}: {}", input_dir.display(), e);
+            1
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Run the `export` subcommand and return the process exit code.
+fn run_export(notebook_path: &Path, output: &Path, format: ExportFormat) -> i32 {
+    let store = store_for_path(notebook_path, &NotebookFileHandler::new());
+    let (notebook, diagnostics) = match store.load_notebook(notebook_path.to_path_buf()) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("Failed to open {}: {}", notebook_path.display(), e);
+            return 1;
+        }
+    };
+    for diagnostic in &diagnostics {
+        eprintln!("{}: {}", notebook_path.display(), diagnostic);
+    }
+    match export_notebook(&notebook, output, format) {
+        Ok(path) => {
+            println!("Exported {} pages to {}", notebook.pages.len(), path.display());
+            0
+        }
+        Err(e) => {
+            eprintln!("Failed to export {}: {}", notebook_path.display(), e);
+            1
+        }
+    }
+}
+
+/// Write a fully loaded notebook in an export format, returning the main
+/// file written.
+fn export_notebook(notebook: &Notebook, output: &Path, format: ExportFormat) -> io::Result<PathBuf> {
+    match format {
+        ExportFormat::Markdown => markdown::export_notebook(notebook, output),
+        ExportFormat::Html => html::export_notebook(notebook, output),
+        ExportFormat::Pdf => pdf::export_notebook(notebook, output),
+    }
+}
+
+/// Paint a drawing on a light background, shrunk to fit the available width.
+fn paint_drawing(ui: &mut egui::Ui, drawing: &Drawing) {
+    let scale = if drawing.width > ui.available_width() {
+        ui.available_width() / drawing.width
+    } else {
+        1.0
+    };
+    let size = egui::vec2(drawing.width, drawing.height) * scale;
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    painter.rect_filled(response.rect, 4.0, egui::Color32::WHITE);
+
+    let origin = response.rect.min;
+    for stroke in &drawing.strokes {
+        let [r, g, b, a] = stroke.color;
+        let color = egui::Color32::from_rgba_unmultiplied(r, g, b, a);
+        let width = stroke.width * scale;
+        let points: Vec<egui::Pos2> = stroke
+            .points
+            .iter()
+            .map(|&[x, y]| origin + egui::vec2(x, y) * scale)
+            .collect();
+        if let [point] = points[..] {
+            painter.circle_filled(point, width / 2.0, color);
+        } else {
+            painter.add(egui::Shape::line(points, egui::Stroke::new(width, color)));
+        }
+    }
+}
+
+fn export_drawing(drawing: &Drawing, file_name: &str) {
+    if let Some(file_path) = rfd::FileDialog::new()
+        .add_filter("SVG image", &["svg"])
+        .set_file_name(file_name)
+        .save_file()
+    {
+        if let Err(e) = std::fs::write(&file_path, drawing.to_svg()) {
+            eprintln!("Failed to export drawing: {}", e);
+        }
+    }
+}
+
+fn save_attachment(attachment: &Attachment) {
+    if let Some(file_path) = rfd::FileDialog::new()
+        .set_file_name(&attachment.name)
+        .save_file()
+    {
+        if let Err(e) = std::fs::write(&file_path, &attachment.data) {
+            eprintln!("Failed to save attachment: {}", e);
+        }
+    }
+}
@@ -0,0 +1,528 @@
+use crate::file_io;
+use crate::notebook::{Notebook, Page};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// The file listing the pages of an exported notebook in order.
+pub const INDEX_FILE: &str = "index.md";
+
+const ATTACHMENTS_DIRECTORY: &str = "attachments";
+
+/// Write a notebook to `output_dir` as one Markdown file per page, named
+/// after its title, and an index listing the pages in notebook order. Each
+/// page starts with YAML front matter holding its title, ID, number and
+/// timestamps. Attachments and drawings go into an `attachments` folder and
+/// are linked at the end of their page. All pages must be loaded. Returns
+/// the path of the index.
+pub fn export_notebook(notebook: &Notebook, output_dir: &Path) -> io::Result<PathBuf> {
+    file_io::require_loaded(notebook)?;
+    fs::create_dir_all(output_dir)?;
+
+    let mut used = HashSet::from([INDEX_FILE.to_string()]);
+    let mut used_attachments = HashSet::new();
+    let mut file_names = HashMap::new();
+    for page in &notebook.pages {
+        let file_name = unique_file_name(&page.title, "md", &mut used);
+        let mut text = front_matter(page);
+        text.push_str(&page.content);
+        let links = export_attachments(page, output_dir, &mut used_attachments)?;
+        if !links.is_empty() {
+            // Each link is a paragraph of its own after the page text
+            if !page.content.is_empty() {
+                text.push_str(if page.content.ends_with('\n') { "\n" } else { "\n\n" });
+            }
+            text.push_str(&links.join("\n\n"));
+        }
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        fs::write(output_dir.join(&file_name), text)?;
+        file_names.insert(page.id.as_str(), file_name);
+    }
+
+    let index_path = output_dir.join(INDEX_FILE);
+    fs::write(&index_path, index(notebook, &file_names))?;
+    Ok(index_path)
+}
+
+fn front_matter(page: &Page) -> String {
+    let mut text = String::from("---\n");
+    text.push_str(&format!("title: {}\n", yaml_string(&page.title)));
+    text.push_str(&format!("id: {}\n", yaml_string(&page.id)));
+    if let Some(number) = page.number {
+        text.push_str(&format!("number: {}\n", number));
+    }
+    text.push_str(&format!("created: {}\n", page.created.to_rfc3339()));
+    text.push_str(&format!("modified: {}\n", page.modified.to_rfc3339()));
+    text.push_str("---\n\n");
+    text
+}
+
+/// Write a page's attachments and drawings, returning the Markdown linking
+/// to each.
+fn export_attachments(page: &Page, output_dir: &Path, used: &mut HashSet<String>) -> io::Result<Vec<String>> {
+    if page.attachments.is_empty() && page.drawings.is_empty() {
+        return Ok(Vec::new());
+    }
+    let directory = output_dir.join(ATTACHMENTS_DIRECTORY);
+    fs::create_dir_all(&directory)?;
+
+    let mut links = Vec::new();
+    for attachment in &page.attachments {
+        let (stem, extension) = match attachment.name.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => (stem, extension),
+            _ => (attachment.name.as_str(), ""),
+        };
+        let file_name = unique_file_name(stem, extension, used);
+        fs::write(directory.join(&file_name), &attachment.data)?;
+        let image = if attachment.mime_type.starts_with("image/") { "!" } else { "" };
+        links.push(format!(
+            "{}[{}](<{}/{}>)",
+            image,
+            escape_link_text(&attachment.name),
+            ATTACHMENTS_DIRECTORY,
+            file_name
+        ));
+    }
+    for (index, drawing) in page.drawings.iter().enumerate() {
+        let file_name = unique_file_name(&format!("{} drawing {}", page.title, index + 1), "svg", used);
+        fs::write(directory.join(&file_name), drawing.to_svg())?;
+        links.push(format!("![Drawing {}](<{}/{}>)", index + 1, ATTACHMENTS_DIRECTORY, file_name));
+    }
+    Ok(links)
+}
+
+/// The index file: the notebook title and a nested list of links to the
+/// pages, grouped under their OneNote sections as in the sidebar.
+fn index(notebook: &Notebook, file_names: &HashMap<&str, String>) -> String {
+    let mut text = format!("# {}\n", single_line(&notebook.title));
+    let tree = notebook.subpages_by_parent();
+    let mut current_section = None;
+    let mut in_list = false;
+    for page in tree.get(&None).into_iter().flatten() {
+        if !in_list || page.section != current_section {
+            current_section = page.section.clone();
+            text.push('\n');
+            if let Some(section) = &current_section {
+                text.push_str(&format!("## {}\n\n", single_line(section)));
+            }
+            in_list = true;
+        }
+        list_page(&mut text, &tree, page, file_names, 0);
+    }
+    text
+}
+
+fn list_page(
+    text: &mut String,
+    tree: &HashMap<Option<&str>, Vec<&Page>>,
+    page: &Page,
+    file_names: &HashMap<&str, String>,
+    depth: usize,
+) {
+    let title = if page.title.trim().is_empty() { "Untitled" } else { &page.title };
+    text.push_str(&format!(
+        "{}- [{}](<{}>)\n",
+        "  ".repeat(depth),
+        escape_link_text(&single_line(title)),
+        file_names[page.id.as_str()]
+    ));
+    for subpage in tree.get(&Some(page.id.as_str())).into_iter().flatten() {
+        list_page(text, tree, subpage, file_names, depth + 1);
+    }
+}
+
+/// Build a notebook from a folder of Markdown notes, such as an Obsidian
+/// vault. Each `.md` file becomes a page, titled from its front matter, its
+/// first heading or its file name, and dated from its front matter or the
+/// file's times. Each subfolder becomes a page with the notes inside it as
+/// subpages; a note named like the folder, next to it, provides that page's
+/// text. Hidden files and folders, such as `.obsidian`, are skipped.
+pub fn import_directory(dir: &Path) -> io::Result<Notebook> {
+    if !dir.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Folder {} does not exist", dir.display()),
+        ));
+    }
+    let title = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Imported Notes".to_string());
+    let mut notebook = Notebook::new(title);
+    import_folder(dir, None, &mut notebook.pages, &mut HashSet::new())?;
+
+    // Number pages in order without touching their timestamps
+    for (index, page) in notebook.pages.iter_mut().enumerate() {
+        page.number = Some(index as u32 + 1);
+    }
+    if let Some(created) = notebook.pages.iter().map(|page| page.created).min() {
+        notebook.created = created;
+    }
+    if let Some(modified) = notebook.pages.iter().map(|page| page.modified).max() {
+        notebook.modified = modified;
+    }
+    Ok(notebook)
+}
+
+/// Add the notes in `dir` to `pages` as subpages of `parent_id`, depth
+/// first, returning how many pages were added.
+fn import_folder(dir: &Path, parent_id: Option<&str>, pages: &mut Vec<Page>, ids: &mut HashSet<String>) -> io::Result<usize> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let file_type = entry.file_type()?;
+        if name.starts_with('.') {
+            continue;
+        }
+        if file_type.is_dir() || (file_type.is_file() && is_note(&name)) {
+            entries.push((name, file_type.is_dir()));
+        }
+    }
+    entries.sort_by_key(|(name, _)| name.to_lowercase());
+    // Notes named like a folder next to them, by the folder's name
+    let folder_notes: HashMap<String, String> = entries
+        .iter()
+        .filter(|(name, is_folder)| {
+            !is_folder && entries.iter().any(|(folder, is_folder)| *is_folder && folder.to_lowercase() == note_stem(name).to_lowercase())
+        })
+        .map(|(name, _)| (note_stem(name).to_lowercase(), name.clone()))
+        .collect();
+
+    let start = pages.len();
+    for (name, is_folder) in &entries {
+        let path = dir.join(name);
+        if !is_folder {
+            // Folder notes are read with their folder
+            if !folder_notes.contains_key(&note_stem(name).to_lowercase()) {
+                let mut page = import_note(&path, ids)?;
+                page.parent_id = parent_id.map(str::to_string);
+                pages.push(page);
+            }
+            continue;
+        }
+
+        let folder_note = folder_notes.get(&name.to_lowercase());
+        let mut page = match folder_note {
+            Some(note) => import_note(&dir.join(note), ids)?,
+            None => {
+                let mut page = Page::new(name.clone(), String::new(), None);
+                page.id = unique_id(None, ids);
+                if let Ok(modified) = fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+                    page.created = modified.into();
+                    page.modified = modified.into();
+                }
+                page
+            }
+        };
+        page.parent_id = parent_id.map(str::to_string);
+        let page_id = page.id.clone();
+        let index = pages.len();
+        pages.push(page);
+        // Folders without notes anywhere inside them are left out
+        if import_folder(&path, Some(&page_id), pages, ids)? == 0 && folder_note.is_none() {
+            pages.remove(index);
+        }
+    }
+    Ok(pages.len() - start)
+}
+
+/// A page from one Markdown note. Its front matter is not kept in the page
+/// text.
+fn import_note(path: &Path, ids: &mut HashSet<String>) -> io::Result<Page> {
+    let text = String::from_utf8_lossy(&fs::read(path)?).replace("\r\n", "\n");
+    let metadata = fs::metadata(path)?;
+    let (fields, body) = split_front_matter(&text);
+    let body = body.trim_start_matches('\n').trim_end();
+
+    let title = fields
+        .get("title")
+        .cloned()
+        .or_else(|| first_heading(body))
+        .unwrap_or_else(|| path.file_name().map(|name| note_stem(&name.to_string_lossy()).to_string()).unwrap_or_default());
+    let mut page = Page::new(title, body.to_string(), None);
+    page.id = unique_id(fields.get("id").map(String::as_str), ids);
+
+    let field_timestamp = |keys: &[&str]| keys.iter().find_map(|key| fields.get(*key).and_then(|value| parse_timestamp(value)));
+    let file_modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+    if let Some(modified) = field_timestamp(&["modified", "updated"]).or(file_modified) {
+        page.modified = modified;
+    }
+    let file_created = metadata.created().ok().map(DateTime::<Utc>::from).or(file_modified);
+    if let Some(created) = field_timestamp(&["created", "date"]).or(file_created) {
+        page.created = created;
+    }
+    Ok(page)
+}
+
+/// The `key: value` fields of a note's YAML front matter, with keys in
+/// lowercase, and the text after it. Only plain and quoted single-line
+/// values are read; lists and nested values are ignored.
+fn split_front_matter(text: &str) -> (HashMap<String, String>, &str) {
+    let mut fields = HashMap::new();
+    let Some(rest) = text.strip_prefix("---\n") else {
+        return (fields, text);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end();
+        if line == "---" || line == "..." {
+            return (fields, &rest[offset..]);
+        }
+        if line.starts_with([' ', '\t', '-', '#']) {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            if !value.is_empty() {
+                fields.insert(key.trim().to_lowercase(), unquote(value));
+            }
+        }
+    }
+    // Not closed, so not front matter
+    (HashMap::new(), text)
+}
+
+fn unquote(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        serde_json::from_str(value).unwrap_or_else(|_| value[1..value.len() - 1].to_string())
+    } else if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        value[1..value.len() - 1].replace("''", "'")
+    } else {
+        value.to_string()
+    }
+}
+
+/// The text of the first ATX heading (`# Title`) outside code blocks.
+fn first_heading(body: &str) -> Option<String> {
+    let mut in_code = false;
+    for line in body.lines() {
+        let line = line.trim_start();
+        if line.starts_with("```") || line.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        let text = line.trim_start_matches('#');
+        let level = line.len() - text.len();
+        if !in_code && (1..=6).contains(&level) && (text.is_empty() || text.starts_with([' ', '\t'])) {
+            let title = text.trim().trim_end_matches('#').trim_end();
+            if !title.is_empty() {
+                return Some(title.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Front matter timestamps: RFC 3339, or a date with an optional time,
+/// taken as UTC.
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))
+        .map(|timestamp| timestamp.and_utc())
+}
+
+/// `id` if it is set and not taken yet, otherwise a new ID.
+fn unique_id(id: Option<&str>, ids: &mut HashSet<String>) -> String {
+    match id {
+        Some(id) if !id.is_empty() && ids.insert(id.to_string()) => id.to_string(),
+        _ => {
+            let id = Uuid::new_v4().to_string();
+            ids.insert(id.clone());
+            id
+        }
+    }
+}
+
+fn is_note(name: &str) -> bool {
+    let extension = name.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase());
+    matches!(extension.as_deref(), Some("md" | "markdown"))
+}
+
+fn note_stem(name: &str) -> &str {
+    name.rsplit_once('.').map_or(name, |(stem, _)| stem)
+}
+
+/// A file name made from `text` that is valid on Windows, macOS and Linux
+/// and unique among `used` regardless of case. Path separators, characters
+/// Windows forbids and control characters become `_`, and device names
+/// Windows reserves get a trailing `_`.
+fn unique_file_name(text: &str, extension: &str, used: &mut HashSet<String>) -> String {
+    let stem: String = text
+        .trim()
+        .chars()
+        .take(80)
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '_' } else { c })
+        .collect();
+    let stem = stem.trim_start_matches('.').trim_end_matches(['.', ' ']);
+    let mut stem = if stem.is_empty() { "Untitled".to_string() } else { stem.to_string() };
+    if is_reserved_name(&stem) {
+        stem.push('_');
+    }
+
+    let with_extension = |name: String| if extension.is_empty() { name } else { format!("{}.{}", name, extension) };
+    let mut name = with_extension(stem.clone());
+    let mut count = 1;
+    while !used.insert(name.to_lowercase()) {
+        count += 1;
+        name = with_extension(format!("{}-{}", stem, count));
+    }
+    name
+}
+
+fn is_reserved_name(stem: &str) -> bool {
+    let base = stem.split('.').next().unwrap_or_default().trim_end().to_ascii_uppercase();
+    match base.as_str() {
+        "CON" | "PRN" | "AUX" | "NUL" => true,
+        _ => base.len() == 4 && (base.starts_with("COM") || base.starts_with("LPT")) && matches!(base.as_bytes()[3], b'1'..=b'9'),
+    }
+}
+
+/// A double-quoted YAML string. JSON strings are valid YAML.
+fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn escape_link_text(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('[', "\\[").replace(']', "\\]")
+}
+
+fn single_line(value: &str) -> String {
+    value.replace("\r\n", " ").replace(['\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drawing::{Drawing, Stroke};
+    use crate::notebook::Attachment;
+
+    #[test]
+    fn test_export_writes_pages_with_front_matter_and_index() {
+        let mut notebook = Notebook::new("Team [Meetings]".to_string());
+        notebook.add_page(Page::new("Plans: Q3".to_string(), "- ship it".to_string(), None));
+        notebook.add_page(Page::new("plans: q3".to_string(), String::new(), None));
+        let parent_id = notebook.pages[0].id.clone();
+        let mut subpage = Page::new("CON".to_string(), "Nested".to_string(), None);
+        subpage.attachments.push(Attachment {
+            name: "diagram.png".to_string(),
+            mime_type: "image/png".to_string(),
+            data: vec![1, 2, 3],
+        });
+        subpage.drawings.push(Drawing::from_strokes(vec![Stroke {
+            points: vec![[0.0, 0.0], [10.0, 10.0]],
+            width: 2.0,
+            color: [0, 0, 0, 255],
+        }]));
+        notebook.add_subpage(&parent_id, subpage);
+
+        let dir = tempfile::tempdir().unwrap();
+        let index_path = export_notebook(&notebook, dir.path()).unwrap();
+        assert_eq!(
+            fs::read_to_string(index_path).unwrap(),
+            "# Team [Meetings]\n\n\
+             - [Plans: Q3](<Plans_ Q3.md>)\n  \
+               - [CON](<CON_.md>)\n\
+             - [plans: q3](<plans_ q3-2.md>)\n"
+        );
+
+        let page = &notebook.pages[0];
+        assert_eq!(
+            fs::read_to_string(dir.path().join("Plans_ Q3.md")).unwrap(),
+            format!(
+                "---\ntitle: \"Plans: Q3\"\nid: \"{}\"\nnumber: 1\ncreated: {}\nmodified: {}\n---\n\n- ship it\n",
+                page.id,
+                page.created.to_rfc3339(),
+                page.modified.to_rfc3339()
+            )
+        );
+        let subpage = fs::read_to_string(dir.path().join("CON_.md")).unwrap();
+        assert!(subpage.ends_with(
+            "Nested\n\n![diagram.png](<attachments/diagram.png>)\n\n![Drawing 1](<attachments/CON drawing 1.svg>)\n"
+        ));
+        assert_eq!(fs::read(dir.path().join("attachments/diagram.png")).unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_import_reads_front_matter_headings_and_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault = dir.path().join("Vault");
+        for folder in ["Projects/Archive", "Pictures", ".obsidian"] {
+            fs::create_dir_all(vault.join(folder)).unwrap();
+        }
+        let note = |path: &str, text: &str| fs::write(vault.join(path), text).unwrap();
+        note(
+            "Inbox.md",
+            "---\r\ntitle: \"Inbox: \\\"today\\\"\"\r\nid: 'inbox'\r\ntags:\r\n  - todo\r\ncreated: 2024-05-01\r\nupdated: 2024-05-02T10:30:00+02:00\r\n---\r\n\r\n# Ignored heading\r\nBuy milk\r\n",
+        );
+        note("Ideas.md", "```\n# not a heading\n```\n\n## Big Idea ##\nText");
+        note("plain.markdown", "Just text");
+        note("Projects.md", "About projects");
+        note("Projects/Alpha.md", "---\nid: inbox\n---\nAlpha");
+        note("Projects/Archive/Old.md", "Old");
+        note(".obsidian/workspace.md", "hidden");
+        fs::write(vault.join("Pictures/photo.png"), [0u8; 4]).unwrap();
+        let an_hour_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        fs::File::options().write(true).open(vault.join("plain.markdown")).unwrap().set_modified(an_hour_ago).unwrap();
+
+        let notebook = import_directory(&vault).unwrap();
+        assert_eq!(notebook.title, "Vault");
+        let titles: Vec<&str> = notebook.pages.iter().map(|page| page.title.as_str()).collect();
+        assert_eq!(titles, vec!["Big Idea", "Inbox: \"today\"", "plain", "Projects", "Alpha", "Archive", "Old"]);
+        let numbers: Vec<u32> = notebook.pages.iter().filter_map(|page| page.number).collect();
+        assert_eq!(numbers, vec![1, 2, 3, 4, 5, 6, 7]);
+
+        let inbox = &notebook.pages[1];
+        assert_eq!(inbox.id, "inbox");
+        assert_eq!(inbox.content, "# Ignored heading\nBuy milk");
+        assert_eq!(inbox.created.to_rfc3339(), "2024-05-01T00:00:00+00:00");
+        assert_eq!(inbox.modified.to_rfc3339(), "2024-05-02T08:30:00+00:00");
+        assert_eq!(notebook.pages[2].modified, DateTime::<Utc>::from(an_hour_ago));
+        assert_eq!(notebook.created, inbox.created);
+
+        let (projects, alpha, archive, old) = (&notebook.pages[3], &notebook.pages[4], &notebook.pages[5], &notebook.pages[6]);
+        assert_eq!(projects.content, "About projects");
+        assert_eq!(alpha.parent_id.as_deref(), Some(projects.id.as_str()));
+        assert_ne!(alpha.id, "inbox");
+        assert_eq!(archive.parent_id.as_deref(), Some(projects.id.as_str()));
+        assert_eq!(old.parent_id.as_deref(), Some(archive.id.as_str()));
+    }
+
+    #[test]
+    fn test_exported_pages_import_unchanged() {
+        let mut notebook = Notebook::new("Round Trip".to_string());
+        notebook.add_page(Page::new("First: \"quoted\"".to_string(), "  indented\n\n# Heading".to_string(), None));
+        notebook.add_page(Page::new(String::new(), String::new(), None));
+
+        let dir = tempfile::tempdir().unwrap();
+        export_notebook(&notebook, &dir.path().join("Round Trip")).unwrap();
+        let imported = import_directory(&dir.path().join("Round Trip")).unwrap();
+        for page in &notebook.pages {
+            let copy = imported.get_page(&page.id).unwrap();
+            assert_eq!((&copy.title, &copy.content), (&page.title, &page.content));
+            assert_eq!((copy.created, copy.modified), (page.created, page.modified));
+        }
+    }
+
+    #[test]
+    fn test_file_names_are_unique_and_safe() {
+        let mut used = HashSet::new();
+        assert_eq!(unique_file_name("../Secret: Page?", "md", &mut used), "_Secret_ Page_.md");
+        assert_eq!(unique_file_name("../secret: page?", "md", &mut used), "_secret_ page_-2.md");
+        assert_eq!(unique_file_name(" . ", "md", &mut used), "Untitled.md");
+        assert_eq!(unique_file_name("lpt1", "md", &mut used), "lpt1_.md");
+        assert_eq!(unique_file_name("LPT0", "md", &mut used), "LPT0.md");
+        assert_eq!(unique_file_name("日本語のノート", "", &mut used), "日本語のノート");
+    }
+}
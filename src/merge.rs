@@ -0,0 +1,228 @@
+use crate::notebook::{Notebook, Page};
+use std::collections::HashMap;
+
+/// A three-way merge of the notebook in memory ("ours") with the copy found
+/// on disk after another program changed it ("theirs"). Pages are matched by
+/// `PAGE_ID`; a side changed a page when its `MODIFIED` differs from `base`.
+#[derive(Clone, Debug)]
+pub struct NotebookMerge {
+    /// The merged notebook, with every conflict resolved to our side
+    pub notebook: Notebook,
+    pub conflicts: Vec<PageConflict>,
+    /// Pages taken from the file because only it changed them
+    pub updated_pages: usize,
+}
+
+/// A page both sides changed differently. A side is `None` when it deleted
+/// the page.
+#[derive(Clone, Debug)]
+pub struct PageConflict {
+    pub ours: Option<Page>,
+    pub theirs: Option<Page>,
+    pub take_theirs: bool,
+}
+
+impl PageConflict {
+    pub fn title(&self) -> &str {
+        match (&self.ours, &self.theirs) {
+            (Some(page), _) | (None, Some(page)) => &page.title,
+            (None, None) => "",
+        }
+    }
+}
+
+impl NotebookMerge {
+    /// The merged notebook with each conflict resolved as chosen.
+    pub fn finish(self) -> Notebook {
+        let mut notebook = self.notebook;
+        for conflict in self.conflicts.into_iter().filter(|conflict| conflict.take_theirs) {
+            match (conflict.ours, conflict.theirs) {
+                (Some(ours), Some(theirs)) => {
+                    if let Some(page) = notebook.get_page_mut(&ours.id) {
+                        *page = theirs;
+                    }
+                }
+                (Some(ours), None) => notebook.pages.retain(|page| page.id != ours.id),
+                (None, Some(theirs)) => notebook.pages.push(theirs),
+                (None, None) => {}
+            }
+        }
+        notebook.repair_hierarchy();
+        notebook
+    }
+}
+
+/// Whether `ours` has changes that `base` does not: a new title, added,
+/// removed or reordered pages, or edited pages.
+pub fn has_changes(base: &Notebook, ours: &Notebook) -> bool {
+    ours.title != base.title
+        || ours.pages.len() != base.pages.len()
+        || ours
+            .pages
+            .iter()
+            .zip(&base.pages)
+            .any(|(page, base_page)| page.id != base_page.id || page.modified != base_page.modified)
+}
+
+pub fn merge_notebooks(base: &Notebook, ours: &Notebook, theirs: &Notebook) -> NotebookMerge {
+    let base_pages = pages_by_id(base);
+    let our_pages = pages_by_id(ours);
+    let their_pages = pages_by_id(theirs);
+    let changed = |page: &Page| base_pages.get(page.id.as_str()).is_none_or(|base_page| base_page.modified != page.modified);
+
+    let mut notebook = Notebook {
+        id: ours.id.clone(),
+        title: if ours.title == base.title { theirs.title.clone() } else { ours.title.clone() },
+        pages: Vec::new(),
+        created: ours.created,
+        modified: ours.modified.max(theirs.modified),
+    };
+    let mut conflicts = Vec::new();
+    let mut updated_pages = 0;
+
+    for page in &ours.pages {
+        match their_pages.get(page.id.as_str()) {
+            Some(their_page) if !changed(page) => {
+                if changed(their_page) {
+                    updated_pages += 1;
+                }
+                // Their copy is also the one whose body is on disk now
+                notebook.pages.push((*their_page).clone());
+            }
+            Some(their_page) => {
+                if changed(their_page) && !same_page(page, their_page) {
+                    conflicts.push(PageConflict {
+                        ours: Some(page.clone()),
+                        theirs: Some((*their_page).clone()),
+                        take_theirs: false,
+                    });
+                }
+                notebook.pages.push(page.clone());
+            }
+            // Deleted in the file
+            None if base_pages.contains_key(page.id.as_str()) => {
+                if changed(page) {
+                    conflicts.push(PageConflict {
+                        ours: Some(page.clone()),
+                        theirs: None,
+                        take_theirs: false,
+                    });
+                    notebook.pages.push(page.clone());
+                }
+            }
+            None => notebook.pages.push(page.clone()),
+        }
+    }
+
+    for (index, page) in theirs.pages.iter().enumerate() {
+        if our_pages.contains_key(page.id.as_str()) {
+            continue;
+        }
+        if base_pages.contains_key(page.id.as_str()) {
+            // Deleted here but changed in the file
+            if changed(page) {
+                conflicts.push(PageConflict {
+                    ours: None,
+                    theirs: Some(page.clone()),
+                    take_theirs: false,
+                });
+            }
+            continue;
+        }
+        // Added in the file: after the page it follows there
+        let position = theirs.pages[..index]
+            .iter()
+            .rev()
+            .find_map(|previous| notebook.pages.iter().position(|merged| merged.id == previous.id))
+            .map_or(0, |position| position + 1);
+        notebook.pages.insert(position, page.clone());
+        updated_pages += 1;
+    }
+
+    notebook.repair_hierarchy();
+    NotebookMerge {
+        notebook,
+        conflicts,
+        updated_pages,
+    }
+}
+
+fn pages_by_id(notebook: &Notebook) -> HashMap<&str, &Page> {
+    notebook.pages.iter().map(|page| (page.id.as_str(), page)).collect()
+}
+
+fn same_page(a: &Page, b: &Page) -> bool {
+    a.title == b.title
+        && a.content == b.content
+        && a.number == b.number
+        && a.section == b.section
+        && a.parent_id == b.parent_id
+        && a.attachments == b.attachments
+        && a.drawings == b.drawings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn edit(notebook: &mut Notebook, index: usize, content: &str) {
+        let page = &mut notebook.pages[index];
+        page.content = content.to_string();
+        page.modified += Duration::seconds(1);
+    }
+
+    fn base_notebook() -> Notebook {
+        let mut notebook = Notebook::new("Meetings".to_string());
+        for title in ["Monday", "Tuesday", "Wednesday"] {
+            notebook.add_page(Page::new(title.to_string(), format!("{} notes", title), None));
+        }
+        notebook
+    }
+
+    fn contents(notebook: &Notebook) -> Vec<&str> {
+        notebook.pages.iter().map(|page| page.content.as_str()).collect()
+    }
+
+    #[test]
+    fn test_merge_takes_each_sides_changes() {
+        let base = base_notebook();
+        let mut ours = base.clone();
+        let mut theirs = base.clone();
+        edit(&mut ours, 0, "ours");
+        edit(&mut theirs, 1, "theirs");
+        theirs.pages.remove(2);
+        theirs.pages.insert(1, Page::new("Added".to_string(), "new".to_string(), None));
+        assert!(has_changes(&base, &ours));
+        assert!(!has_changes(&base, &base.clone()));
+
+        let merge = merge_notebooks(&base, &ours, &theirs);
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(merge.updated_pages, 2);
+        assert_eq!(contents(&merge.notebook), vec!["ours", "new", "theirs"]);
+    }
+
+    #[test]
+    fn test_merge_reports_pages_changed_on_both_sides() {
+        let base = base_notebook();
+        let mut ours = base.clone();
+        let mut theirs = base.clone();
+        edit(&mut ours, 0, "ours");
+        edit(&mut theirs, 0, "theirs");
+        // The same edit on both sides is not a conflict
+        edit(&mut ours, 1, "same");
+        edit(&mut theirs, 1, "same");
+        edit(&mut ours, 2, "kept");
+        theirs.pages.remove(2);
+
+        let mut merge = merge_notebooks(&base, &ours, &theirs);
+        let titles: Vec<&str> = merge.conflicts.iter().map(PageConflict::title).collect();
+        assert_eq!(titles, vec!["Monday", "Wednesday"]);
+        assert_eq!(contents(&merge.notebook), vec!["ours", "same", "kept"]);
+
+        for conflict in &mut merge.conflicts {
+            conflict.take_theirs = true;
+        }
+        assert_eq!(contents(&merge.finish()), vec!["theirs", "same"]);
+    }
+}
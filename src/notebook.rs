@@ -1,15 +1,73 @@
+use crate::drawing::Drawing;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::path::PathBuf;
 use uuid::Uuid;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// A file embedded in a page, such as a picture or attached document.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub name: String,
+    pub mime_type: String,
+    #[serde(with = "base64_data")]
+    pub data: Vec<u8>,
+}
+
+/// Attachment data as a base64 string rather than a list of numbers.
+mod base64_data {
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64.encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        BASE64.decode(text).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Page {
     pub id: String,
     pub title: String,
     pub content: String,
     pub number: Option<u32>,
+    /// Name of the OneNote section (and section groups) the page was imported from
+    #[serde(default)]
+    pub section: Option<String>,
+    /// ID of the page this one is a subpage of
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// Handwriting and sketches, such as OneNote ink
+    #[serde(default)]
+    pub drawings: Vec<Drawing>,
     pub created: DateTime<Utc>,
     pub modified: DateTime<Utc>,
+    /// Where the content, attachments and drawings of a page listed from a
+    /// notebook's index still are on disk. `None` once they are loaded.
+    #[serde(skip)]
+    pub source: Option<PageSource>,
+}
+
+/// The stored form of a page that has not been loaded yet.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PageSource {
+    /// The page's section of a NotebookX text file, between its page breaks
+    Section {
+        file: PathBuf,
+        start: u64,
+        end: u64,
+        /// Line number of `start`, for diagnostics
+        line: usize,
+    },
+    /// A page file of a folder notebook
+    File(PathBuf),
 }
 
 impl Page {
@@ -20,8 +78,13 @@ impl Page {
             title,
             content,
             number,
+            section: None,
+            parent_id: None,
+            attachments: Vec::new(),
+            drawings: Vec::new(),
             created: now,
             modified: now,
+            source: None,
         }
     }
     
@@ -32,12 +95,15 @@ impl Page {
     }
     
     pub fn set_number(&mut self, number: Option<u32>) {
-        self.number = number;
-        self.modified = Utc::now();
+        // Renumbering leaves pages that keep their number unmodified
+        if self.number != number {
+            self.number = number;
+            self.modified = Utc::now();
+        }
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Notebook {
     pub id: String,
     pub title: String,
@@ -64,9 +130,80 @@ impl Notebook {
         self.update_page_numbers();
     }
     
+    /// Add `page` as the last subpage of `parent_id`, placed after the
+    /// parent's existing subpages so page numbers follow the tree.
+    pub fn add_subpage(&mut self, parent_id: &str, mut page: Page) {
+        let Some(parent_index) = self.pages.iter().position(|p| p.id == parent_id) else {
+            self.add_page(page);
+            return;
+        };
+        let indices = self.page_indices();
+        let mut index = parent_index + 1;
+        while index < self.pages.len() && self.is_descendant_of(&indices, &self.pages[index].id, parent_id) {
+            index += 1;
+        }
+        page.parent_id = Some(parent_id.to_string());
+        self.pages.insert(index, page);
+        self.modified = Utc::now();
+        self.update_page_numbers();
+    }
+    
+    /// Pages grouped by the ID of their parent page, in notebook order;
+    /// top-level pages are under `None`.
+    pub fn subpages_by_parent(&self) -> HashMap<Option<&str>, Vec<&Page>> {
+        let mut tree: HashMap<Option<&str>, Vec<&Page>> = HashMap::new();
+        for page in &self.pages {
+            tree.entry(page.parent_id.as_deref()).or_default().push(page);
+        }
+        tree
+    }
+    
+    /// The index of each page by ID, keeping the first of duplicate IDs as
+    /// `get_page` does.
+    fn page_indices(&self) -> HashMap<String, usize> {
+        let mut indices = HashMap::with_capacity(self.pages.len());
+        for (index, page) in self.pages.iter().enumerate() {
+            indices.entry(page.id.clone()).or_insert(index);
+        }
+        indices
+    }
+    
+    fn is_descendant_of(&self, indices: &HashMap<String, usize>, page_id: &str, ancestor_id: &str) -> bool {
+        let parent_of = |id: &str| indices.get(id).and_then(|&index| self.pages[index].parent_id.as_deref());
+        let mut current = parent_of(page_id);
+        // Bounded by the page count in case the tree has not been repaired
+        for _ in 0..self.pages.len() {
+            match current {
+                Some(id) if id == ancestor_id => return true,
+                Some(id) => current = parent_of(id),
+                None => return false,
+            }
+        }
+        false
+    }
+    
+    /// Make the page tree well formed: pages whose parent is missing, is the
+    /// page itself or lies on a cycle become top-level pages.
+    pub fn repair_hierarchy(&mut self) {
+        let indices = self.page_indices();
+        for index in 0..self.pages.len() {
+            let Some(parent_id) = self.pages[index].parent_id.clone() else {
+                continue;
+            };
+            let page_id = self.pages[index].id.clone();
+            if !indices.contains_key(&parent_id) || self.is_descendant_of(&indices, &parent_id, &page_id) || parent_id == page_id {
+                self.pages[index].parent_id = None;
+            }
+        }
+    }
+    
     pub fn remove_page(&mut self, page_id: &str) -> Option<Page> {
         if let Some(pos) = self.pages.iter().position(|p| p.id == page_id) {
             let page = self.pages.remove(pos);
+            // Subpages move up to the removed page's parent
+            for child in self.pages.iter_mut().filter(|p| p.parent_id.as_deref() == Some(page_id)) {
+                child.parent_id = page.parent_id.clone();
+            }
             self.modified = Utc::now();
             self.update_page_numbers();
             Some(page)
@@ -85,8 +222,11 @@ impl Notebook {
     
     pub fn update_page(&mut self, page_id: &str, title: String, content: String) -> bool {
         if let Some(page) = self.get_page_mut(page_id) {
-            page.update_content(title, content);
-            self.modified = Utc::now();
+            // Only real edits change the timestamps merges compare
+            if page.title != title || page.content != content {
+                page.update_content(title, content);
+                self.modified = Utc::now();
+            }
             true
         } else {
             false
@@ -107,4 +247,86 @@ impl Notebook {
             self.modified = Utc::now();
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(title: &str) -> Page {
+        Page::new(title.to_string(), String::new(), None)
+    }
+
+    fn child_titles(notebook: &Notebook, parent_id: Option<&str>) -> Vec<String> {
+        notebook
+            .subpages_by_parent()
+            .get(&parent_id)
+            .map(|pages| pages.iter().map(|page| page.title.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_add_subpage_places_it_after_existing_subpages() {
+        let mut notebook = Notebook::new("Tree".to_string());
+        notebook.add_page(page("Projects"));
+        notebook.add_page(page("Journal"));
+        let projects = notebook.pages[0].id.clone();
+
+        notebook.add_subpage(&projects, page("Garden"));
+        let garden = notebook.pages[1].id.clone();
+        notebook.add_subpage(&garden, page("Seeds"));
+        notebook.add_subpage(&projects, page("Kitchen"));
+
+        let order: Vec<(&str, Option<u32>)> = notebook
+            .pages
+            .iter()
+            .map(|page| (page.title.as_str(), page.number))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("Projects", Some(1)),
+                ("Garden", Some(2)),
+                ("Seeds", Some(3)),
+                ("Kitchen", Some(4)),
+                ("Journal", Some(5)),
+            ]
+        );
+        assert_eq!(child_titles(&notebook, None), vec!["Projects", "Journal"]);
+        assert_eq!(child_titles(&notebook, Some(&projects)), vec!["Garden", "Kitchen"]);
+
+        notebook.remove_page(&garden);
+        assert_eq!(child_titles(&notebook, Some(&projects)), vec!["Seeds", "Kitchen"]);
+    }
+
+    #[test]
+    fn test_repair_hierarchy_breaks_cycles_and_dangling_parents() {
+        let mut notebook = Notebook::new("Broken".to_string());
+        for title in ["A", "B", "C", "D"] {
+            notebook.add_page(page(title));
+        }
+        let ids: Vec<String> = notebook.pages.iter().map(|page| page.id.clone()).collect();
+        notebook.pages[0].parent_id = Some(ids[1].clone());
+        notebook.pages[1].parent_id = Some(ids[0].clone());
+        notebook.pages[2].parent_id = Some("missing".to_string());
+        notebook.pages[3].parent_id = Some(ids[2].clone());
+
+        notebook.repair_hierarchy();
+        assert_eq!(child_titles(&notebook, None), vec!["A", "C"]);
+        assert_eq!(child_titles(&notebook, Some(&ids[0])), vec!["B"]);
+        assert_eq!(child_titles(&notebook, Some(&ids[2])), vec!["D"]);
+    }
+
+    #[test]
+    fn test_repair_hierarchy_keeps_long_chains() {
+        let mut notebook = Notebook::new("Deep".to_string());
+        for index in 0..1000 {
+            let mut page = page(&index.to_string());
+            page.parent_id = notebook.pages.last().map(|parent: &Page| parent.id.clone());
+            notebook.pages.push(page);
+        }
+        let parents: Vec<Option<String>> = notebook.pages.iter().map(|page| page.parent_id.clone()).collect();
+        notebook.repair_hierarchy();
+        assert!(notebook.pages.iter().map(|page| &page.parent_id).eq(parents.iter()));
+    }
+}
@@ -1,7 +1,128 @@
-use crate::notebook::{Notebook, Page};
+use crate::cab::Cabinet;
+use crate::conversion_report::{ConversionReport, PackagedFile, SkippedObject};
+use crate::drawing::{Drawing, Stroke};
+use crate::notebook::{Attachment, Notebook, Page};
+use crate::onestore::{ExtendedGuid, FileType, Object, ObjectSpace, PropertySet, RevisionStore};
+use crate::text_scan::Utf16Runs;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
 use std::fs;
-use std::io;
-use std::path::PathBuf;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use uuid::Uuid;
+
+// Root object roles within a page's object space (MS-ONE 2.1.8)
+const ROOT_ROLE_DEFAULT_CONTENT: u32 = 1;
+const ROOT_ROLE_METADATA: u32 = 2;
+
+// Object types (MS-ONE 2.1.13)
+const JCID_INK_DATA_NODE: u32 = 0x0002003B;
+const JCID_INK_STROKE_NODE: u32 = 0x00020047;
+const JCID_PAGE_SERIES_NODE: u32 = 0x00060008;
+const JCID_PAGE_NODE: u32 = 0x0006000B;
+const JCID_OUTLINE_NODE: u32 = 0x0006000C;
+const JCID_OUTLINE_ELEMENT_NODE: u32 = 0x0006000D;
+const JCID_RICH_TEXT_OE_NODE: u32 = 0x0006000E;
+const JCID_IMAGE_NODE: u32 = 0x00060011;
+const JCID_TITLE_NODE: u32 = 0x0006002C;
+const JCID_EMBEDDED_FILE_NODE: u32 = 0x00060035;
+const JCID_PAGE_MANIFEST_NODE: u32 = 0x00060037;
+const JCID_INK_CONTAINER: u32 = 0x00060047;
+const JCID_STROKE_PROPERTIES_NODE: u32 = 0x00120048;
+
+// Property IDs (MS-ONE 2.1.12)
+const PROP_CACHED_TITLE_STRING: u32 = 0x1C001CF3;
+const PROP_CHILD_GRAPH_SPACE_ELEMENT_NODES: u32 = 0x2C001D63;
+const PROP_CONTENT_CHILD_NODES: u32 = 0x24001C1F;
+const PROP_CREATION_TIME_STAMP: u32 = 0x14001D09;
+const PROP_ELEMENT_CHILD_NODES: u32 = 0x24001C20;
+const PROP_EMBEDDED_FILE_CONTAINER: u32 = 0x20001D9B;
+const PROP_EMBEDDED_FILE_NAME: u32 = 0x1C001D9C;
+const PROP_FOLDER_CHILD_FILENAME: u32 = 0x1C001D6B;
+const PROP_IMAGE_FILENAME: u32 = 0x1C001DD7;
+const PROP_INK_COLOR: u32 = 0x14003410;
+const PROP_INK_DATA: u32 = 0x20003415;
+const PROP_INK_DIMENSIONS: u32 = 0x1C00340A;
+const PROP_INK_HEIGHT: u32 = 0x1400340B;
+const PROP_INK_PATH: u32 = 0x1C00340D;
+const PROP_INK_SCALING_X: u32 = 0x14001C46;
+const PROP_INK_SCALING_Y: u32 = 0x14001C47;
+const PROP_INK_STROKE_PROPERTIES: u32 = 0x20003409;
+const PROP_INK_STROKES: u32 = 0x24003416;
+const PROP_INK_TRANSPARENCY: u32 = 0x0C003412;
+const PROP_INK_WIDTH: u32 = 0x1400340C;
+const PROP_LAST_MODIFIED_TIME: u32 = 0x14001D7A;
+const PROP_LAST_MODIFIED_TIME_STAMP: u32 = 0x18001D77;
+const PROP_NOTEBOOK_ELEMENT_ORDERING_ID: u32 = 0x14001CB9;
+const PROP_PAGE_LEVEL: u32 = 0x14001DFF;
+const PROP_PICTURE_CONTAINER: u32 = 0x20001C3F;
+const PROP_RICH_EDIT_TEXT_UNICODE: u32 = 0x1C001C22;
+const PROP_STRUCTURE_ELEMENT_CHILD_NODES: u32 = 0x24001D5F;
+const PROP_TEXT_EXTENDED_ASCII: u32 = 0x1C003498;
+const PROP_TOC_CHILDREN: u32 = 0x24001CF6;
+const PROP_TOPOLOGY_CREATION_TIME_STAMP: u32 = 0x18001C65;
+
+/// Properties that link a node to the nodes nested inside it, in reading order.
+const CHILD_NODE_PROPERTIES: [u32; 3] = [
+    PROP_CONTENT_CHILD_NODES,
+    PROP_ELEMENT_CHILD_NODES,
+    PROP_STRUCTURE_ELEMENT_CHILD_NODES,
+];
+
+/// Properties the converter reads; anything else is listed in the report.
+const KNOWN_PROPERTIES: [u32; 29] = [
+    PROP_CACHED_TITLE_STRING,
+    PROP_CHILD_GRAPH_SPACE_ELEMENT_NODES,
+    PROP_CONTENT_CHILD_NODES,
+    PROP_CREATION_TIME_STAMP,
+    PROP_ELEMENT_CHILD_NODES,
+    PROP_EMBEDDED_FILE_CONTAINER,
+    PROP_EMBEDDED_FILE_NAME,
+    PROP_FOLDER_CHILD_FILENAME,
+    PROP_IMAGE_FILENAME,
+    PROP_INK_COLOR,
+    PROP_INK_DATA,
+    PROP_INK_DIMENSIONS,
+    PROP_INK_HEIGHT,
+    PROP_INK_PATH,
+    PROP_INK_SCALING_X,
+    PROP_INK_SCALING_Y,
+    PROP_INK_STROKE_PROPERTIES,
+    PROP_INK_STROKES,
+    PROP_INK_TRANSPARENCY,
+    PROP_INK_WIDTH,
+    PROP_LAST_MODIFIED_TIME,
+    PROP_LAST_MODIFIED_TIME_STAMP,
+    PROP_NOTEBOOK_ELEMENT_ORDERING_ID,
+    PROP_PAGE_LEVEL,
+    PROP_PICTURE_CONTAINER,
+    PROP_RICH_EDIT_TEXT_UNICODE,
+    PROP_STRUCTURE_ELEMENT_CHILD_NODES,
+    PROP_TEXT_EXTENDED_ASCII,
+    PROP_TOPOLOGY_CREATION_TIME_STAMP,
+];
+
+/// Limit on how deeply outlines may nest before the rest is ignored.
+const MAX_OUTLINE_DEPTH: usize = 64;
+
+/// Limit on nested section group folders when walking a notebook.
+const MAX_SECTION_GROUP_DEPTH: usize = 16;
+
+/// OneNote refuses to grow a file past 2 GB; anything larger is not a
+/// OneNote file and is not read into memory.
+const MAX_INPUT_FILE_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Limit on the text salvaged into the report from an unreadable file.
+const MAX_PREVIEW_CHARS: usize = 1024 * 1024;
+
+/// Ink coordinates and pen sizes are in HIMETRIC units (0.01 mm).
+const PIXELS_PER_HIMETRIC: f32 = 96.0 / 2540.0;
+
+/// Pen size OneNote uses when a stroke has no stroke properties.
+const DEFAULT_INK_PEN_SIZE: f32 = 53.0;
+
+/// Size of one entry in an ink stroke's dimension list.
+const INK_DIMENSION_SIZE: usize = 32;
 
 pub struct OneNoteConverter {
     // Future: Could include configuration options for conversion
@@ -12,124 +133,819 @@ impl OneNoteConverter {
         Self {}
     }
     
-    /// Convert a OneNote .one file to NotebookX format
-    /// Note: This is a basic implementation that attempts to extract text content
-    /// Full OneNote parsing would require implementing the complete MS-ONESTORE specification
+    /// Convert a OneNote .one section file to NotebookX format, producing one
+    /// page per OneNote page with its title, text and timestamps.
     pub fn convert_to_notebookx(&self, one_file_path: PathBuf) -> io::Result<Notebook> {
-        // For now, we'll implement a basic converter that creates a placeholder
-        // In a full implementation, this would parse the binary OneNote format
-        
         let file_name = one_file_path
             .file_stem()
             .and_then(|stem| stem.to_str())
             .unwrap_or("Converted Notebook");
-            
+
+        // Pages are assigned directly so renumbering does not bump their
+        // modified timestamps
         let mut notebook = Notebook::new(format!("Converted from {}", file_name));
-        
-        // Placeholder implementation - in reality this would parse the binary format
-        let conversion_page = Page::new(
-            "OneNote Conversion Note".to_string(),
-            format!(
-                "This notebook was converted from a OneNote file: {}\n\n\
-                 IMPORTANT: This is a basic conversion placeholder.\n\n\
-                 To implement full OneNote conversion, the following would be needed:\n\
-                 \n\
-                 1. Parse the OneNote Revision Store File Format (.one)\n\
-                 2. Extract the header structure\n\
-                 3. Parse object spaces and property sets\n\
-                 4. Extract text content, formatting, and metadata\n\
-                 5. Convert images and attachments\n\
-                 6. Map OneNote sections and pages to NotebookX format\n\
-                 \n\
-                 For a complete implementation, refer to:\n\
-                 - MS-ONESTORE specification\n\
-                 - OneNote File Format documentation\n\
-                 \n\
-                 Original file: {}",
-                one_file_path.display(),
-                one_file_path.display()
-            ),
-            Some(1),
-        );
-        
-        notebook.add_page(conversion_page);
-        
+        notebook.pages = self.read_section(&one_file_path, None, &mut ConversionReport::default())?;
+        number_pages(&mut notebook.pages);
+
+        Ok(notebook)
+    }
+
+    /// Convert a whole OneNote notebook folder from its .onetoc2 table of
+    /// contents. Sections keep the order OneNote stored them in, and section
+    /// groups are walked through the .onetoc2 file in their own folders.
+    pub fn convert_notebook(&self, toc_path: PathBuf) -> io::Result<Notebook> {
+        self.read_notebook(&toc_path, &mut ConversionReport::default())
+    }
+
+    fn read_notebook(&self, toc_path: &Path, report: &mut ConversionReport) -> io::Result<Notebook> {
+        let folder_name = toc_path
+            .parent()
+            .and_then(|folder| folder.file_name())
+            .and_then(|name| name.to_str())
+            .unwrap_or("Converted Notebook");
+
+        let mut notebook = Notebook::new(format!("Converted from {}", folder_name));
+        self.collect_toc_sections(toc_path, None, &mut notebook.pages, report, 0)?;
+        number_pages(&mut notebook.pages);
+
+        Ok(notebook)
+    }
+
+    /// Convert an exported .onepkg notebook package. The cabinet is unpacked
+    /// in-process into a temporary folder and converted through its .onetoc2
+    /// file, or section by section if the package has none.
+    pub fn convert_package(&self, onepkg_path: PathBuf) -> io::Result<Notebook> {
+        self.read_package(&onepkg_path, &mut ConversionReport::default())
+    }
+
+    fn read_package(&self, onepkg_path: &Path, report: &mut ConversionReport) -> io::Result<Notebook> {
+        let data = read_input_file(onepkg_path)?;
+        let cabinet = Cabinet::parse(&data)?;
+        report.packaged_files = cabinet
+            .entries
+            .iter()
+            .map(|entry| PackagedFile {
+                name: entry.name.clone(),
+                size: entry.size,
+                compression: cabinet.compression_name(entry).to_string(),
+            })
+            .collect();
+        let files = cabinet.extract_all()?;
+
+        let package_name = onepkg_path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("Converted Notebook");
+
+        let unpack_dir = std::env::temp_dir().join(format!("notebookx-onepkg-{}", Uuid::new_v4()));
+        let result = self.convert_unpacked_package(&unpack_dir, files, report);
+        let _ = fs::remove_dir_all(&unpack_dir);
+
+        let mut notebook = result?;
+        notebook.title = format!("Converted from {}", package_name);
+        Ok(notebook)
+    }
+
+    fn convert_unpacked_package(
+        &self,
+        unpack_dir: &Path,
+        files: Vec<(String, Vec<u8>)>,
+        report: &mut ConversionReport,
+    ) -> io::Result<Notebook> {
+        let mut tocs = Vec::new();
+        let mut sections = Vec::new();
+        for (name, contents) in files {
+            // Only plain relative paths may be written; anything else could escape the folder
+            let relative = Path::new(&name);
+            if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+                report.warnings.push(format!("Skipped packaged file with unsafe path: {}", name));
+                continue;
+            }
+            let path = unpack_dir.join(relative);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, contents)?;
+
+            if has_extension(&path, "onetoc2") {
+                tocs.push(path);
+            } else if has_extension(&path, "one") {
+                sections.push(path);
+            }
+        }
+
+        // The notebook's own table of contents is the one closest to the root
+        if let Some(toc) = tocs.iter().min_by_key(|path| path.components().count()) {
+            return self.read_notebook(toc, report);
+        }
+
+        if sections.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Package contains no OneNote sections",
+            ));
+        }
+        sections.sort();
+        let mut notebook = Notebook::new("Converted Notebook".to_string());
+        for section in sections {
+            let section_name = section
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("Section")
+                .to_string();
+            notebook.pages.extend(self.read_section(&section, Some(&section_name), report)?);
+        }
+        number_pages(&mut notebook.pages);
         Ok(notebook)
     }
+
+    /// Read the entries of a .onetoc2 file: the file names of its sections
+    /// and the folder names of its section groups, in stored order.
+    pub fn read_toc_entries(&self, toc_path: &Path) -> io::Result<Vec<String>> {
+        self.parse_toc_entries(&read_input_file(toc_path)?)
+    }
+
+    /// Read the entries of a .onetoc2 file already in memory.
+    pub fn parse_toc_entries(&self, data: &[u8]) -> io::Result<Vec<String>> {
+        let store = RevisionStore::parse(data)?;
+        if store.file_type != FileType::TableOfContents {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Expected a OneNote table of contents (.onetoc2) file",
+            ));
+        }
+
+        let space = store
+            .root_space
+            .as_ref()
+            .and_then(|id| store.space(id))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Table of contents has no root object space"))?;
+        let root = space
+            .root(ROOT_ROLE_DEFAULT_CONTENT)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Table of contents has no root object"))?;
+
+        let mut entries: Vec<(u32, String)> = root
+            .properties
+            .object_ids(PROP_TOC_CHILDREN)
+            .iter()
+            .filter_map(|id| space.object(id))
+            .enumerate()
+            .filter_map(|(index, child)| {
+                let name = child.properties.bytes(PROP_FOLDER_CHILD_FILENAME).map(decode_utf16)?;
+                let order = child
+                    .properties
+                    .u32(PROP_NOTEBOOK_ELEMENT_ORDERING_ID)
+                    .unwrap_or(index as u32);
+                Some((order, name))
+            })
+            .collect();
+        entries.sort_by_key(|(order, _)| *order);
+
+        Ok(entries.into_iter().map(|(_, name)| name).collect())
+    }
+
+    fn collect_toc_sections(
+        &self,
+        toc_path: &Path,
+        group: Option<&str>,
+        pages: &mut Vec<Page>,
+        report: &mut ConversionReport,
+        depth: usize,
+    ) -> io::Result<()> {
+        if depth > MAX_SECTION_GROUP_DEPTH {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Section groups are nested too deeply"));
+        }
+        let folder = toc_path.parent().unwrap_or_else(|| Path::new("."));
+
+        for entry in self.read_toc_entries(toc_path)? {
+            // Entries are plain names; anything that could escape the folder is ignored
+            if entry.is_empty() || entry.contains(['/', '\\']) || entry == "." || entry == ".." {
+                continue;
+            }
+            let path = folder.join(&entry);
+            let qualified = |name: &str| match group {
+                Some(group) => format!("{} / {}", group, name),
+                None => name.to_string(),
+            };
+
+            if path.is_dir() {
+                if let Some(child_toc) = find_toc_file(&path)? {
+                    self.collect_toc_sections(&child_toc, Some(&qualified(&entry)), pages, report, depth + 1)?;
+                }
+            } else {
+                let section_name = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or(&entry)
+                    .to_string();
+                let section_pages = self
+                    .read_section(&path, Some(&qualified(&section_name)), report)
+                    .map_err(|e| {
+                        io::Error::new(e.kind(), format!("Failed to convert section {}: {}", path.display(), e))
+                    })?;
+                pages.extend(section_pages);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read the pages of one section file, recording each page and anything
+    /// skipped along the way in `report`.
+    fn read_section(
+        &self,
+        one_file_path: &Path,
+        section: Option<&str>,
+        report: &mut ConversionReport,
+    ) -> io::Result<Vec<Page>> {
+        self.parse_section(&read_input_file(one_file_path)?, section, report)
+    }
+
+    /// Read the pages of a section file already in memory.
+    pub fn parse_section(&self, data: &[u8], section: Option<&str>, report: &mut ConversionReport) -> io::Result<Vec<Page>> {
+        let store = RevisionStore::parse(data)?;
+        if store.file_type != FileType::Section {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Expected a OneNote section (.one) file",
+            ));
+        }
+        Ok(self.extract_pages(&store, section, report))
+    }
     
-    /// Attempt to extract basic text content from OneNote file
-    /// This is a very basic approach and won't work for all OneNote files
-    pub fn extract_basic_text(&self, one_file_path: PathBuf) -> io::Result<Vec<String>> {
-        let content = fs::read(&one_file_path)?;
-        
-        // Basic text extraction - look for UTF-16 strings
-        let mut extracted_text = Vec::new();
-        let mut current_text = String::new();
-        
-        // Simple approach: look for readable text sequences
-        // This is very basic and won't capture all content
-        for chunk in content.chunks(2) {
-            if chunk.len() == 2 {
-                let utf16_char = u16::from_le_bytes([chunk[0], chunk[1]]);
-                
-                if let Some(ch) = char::from_u32(utf16_char as u32) {
-                    if ch.is_ascii_graphic() || ch.is_whitespace() {
-                        current_text.push(ch);
-                    } else if !current_text.trim().is_empty() {
-                        extracted_text.push(current_text.trim().to_string());
-                        current_text.clear();
+    /// Map the object spaces of a parsed section onto NotebookX pages.
+    fn extract_pages(&self, store: &RevisionStore, section: Option<&str>, report: &mut ConversionReport) -> Vec<Page> {
+        let mut page_spaces = Vec::new();
+
+        // The section's root object space lists its pages in order via the
+        // page series nodes; each page lives in an object space of its own.
+        if let Some(section) = store.root_space.as_ref().and_then(|id| store.space(id)) {
+            if let Some(section_node) = section.root(ROOT_ROLE_DEFAULT_CONTENT) {
+                for series_id in section_node.properties.object_ids(PROP_ELEMENT_CHILD_NODES) {
+                    if let Some(series) = section.object(series_id) {
+                        if series.jcid == JCID_PAGE_SERIES_NODE {
+                            page_spaces.extend(
+                                series
+                                    .properties
+                                    .object_space_ids(PROP_CHILD_GRAPH_SPACE_ELEMENT_NODES)
+                                    .iter()
+                                    .copied(),
+                            );
+                        }
                     }
                 }
             }
         }
-        
-        if !current_text.trim().is_empty() {
-            extracted_text.push(current_text.trim().to_string());
+
+        // Fall back to manifest order if the section node could not be read
+        if page_spaces.is_empty() {
+            page_spaces = store
+                .spaces
+                .iter()
+                .map(|space| space.id)
+                .filter(|id| Some(*id) != store.root_space)
+                .collect();
+        }
+
+        let mut pages = Vec::new();
+        // Pages at a deeper level than the page before them are its subpages;
+        // this holds the level and ID of the current page at each depth
+        let mut ancestors: Vec<(u32, String)> = Vec::new();
+        for id in &page_spaces {
+            let Some(space) = store.space(id) else {
+                report.skipped_objects.push(SkippedObject {
+                    page: None,
+                    jcid: None,
+                    reason: "page object space is missing".to_string(),
+                });
+                continue;
+            };
+            match self.extract_page(store, space, section, report) {
+                Some(mut page) => {
+                    let level = space
+                        .root(ROOT_ROLE_METADATA)
+                        .and_then(|metadata| metadata.properties.u32(PROP_PAGE_LEVEL))
+                        .unwrap_or(1);
+                    while ancestors.last().is_some_and(|(ancestor_level, _)| *ancestor_level >= level) {
+                        ancestors.pop();
+                    }
+                    page.parent_id = ancestors.last().map(|(_, id)| id.clone());
+                    ancestors.push((level, page.id.clone()));
+                    pages.push(page);
+                }
+                None => report.skipped_objects.push(SkippedObject {
+                    page: None,
+                    jcid: space.root(ROOT_ROLE_DEFAULT_CONTENT).map(|root| root.jcid),
+                    reason: "object space has no page node".to_string(),
+                }),
+            }
+        }
+        pages
+    }
+
+    fn extract_page(
+        &self,
+        store: &RevisionStore,
+        space: &ObjectSpace,
+        section: Option<&str>,
+        report: &mut ConversionReport,
+    ) -> Option<Page> {
+        let root = space.root(ROOT_ROLE_DEFAULT_CONTENT)?;
+        let page_node = if root.jcid == JCID_PAGE_NODE {
+            root
+        } else {
+            root.properties
+                .object_ids(PROP_CONTENT_CHILD_NODES)
+                .iter()
+                .filter_map(|id| space.object(id))
+                .find(|object| object.jcid == JCID_PAGE_NODE)?
+        };
+        let metadata = space.root(ROOT_ROLE_METADATA);
+
+        let mut title_contents = PageContents::default();
+        let mut contents = PageContents::default();
+        let mut visited = HashSet::new();
+        for property in CHILD_NODE_PROPERTIES {
+            for child in page_node.properties.object_ids(property) {
+                let target = match space.object(child) {
+                    Some(object) if object.jcid == JCID_TITLE_NODE => &mut title_contents,
+                    _ => &mut contents,
+                };
+                collect_contents(store, space, child, target, &mut visited, 0);
+            }
+        }
+        let title_lines = title_contents.lines;
+
+        let title = metadata
+            .and_then(|metadata| metadata.properties.bytes(PROP_CACHED_TITLE_STRING))
+            .map(decode_utf16)
+            .filter(|title| !title.trim().is_empty())
+            .unwrap_or_else(|| title_lines.join(" "));
+        let title = if title.trim().is_empty() {
+            "Untitled Page".to_string()
+        } else {
+            title.trim().to_string()
+        };
+
+        let paragraphs = contents.lines.len();
+        let mut page = Page::new(title, contents.lines.join("\n"), None);
+        page.section = section.map(str::to_string);
+        page.attachments = contents.attachments;
+        page.attachments.extend(title_contents.attachments);
+        page.drawings = contents.drawings;
+        page.drawings.extend(title_contents.drawings);
+
+        let mut warnings = contents.warnings;
+        warnings.extend(title_contents.warnings);
+        for (jcid, reason) in contents.skipped.into_iter().chain(title_contents.skipped) {
+            report.skipped_objects.push(SkippedObject {
+                page: Some(page.title.clone()),
+                jcid,
+                reason,
+            });
         }
-        
-        // Filter out very short strings that are likely noise
-        let filtered: Vec<String> = extracted_text
+        let node_properties = [Some(page_node), metadata]
             .into_iter()
-            .filter(|s| s.len() > 5 && s.chars().any(|c| c.is_alphabetic()))
-            .take(50) // Limit to prevent overwhelming output
-            .collect();
-            
-        Ok(filtered)
+            .flatten()
+            .flat_map(|node| &node.properties.properties);
+        for property in node_properties {
+            if !KNOWN_PROPERTIES.contains(&property.id) {
+                report.add_unknown_property(property.id);
+            }
+        }
+        for id in contents.unknown_properties.into_iter().chain(title_contents.unknown_properties) {
+            report.add_unknown_property(id);
+        }
+
+        let created = metadata
+            .and_then(|metadata| {
+                metadata
+                    .properties
+                    .u64(PROP_TOPOLOGY_CREATION_TIME_STAMP)
+                    .and_then(filetime_to_datetime)
+                    .or_else(|| {
+                        metadata
+                            .properties
+                            .u32(PROP_CREATION_TIME_STAMP)
+                            .and_then(time32_to_datetime)
+                    })
+            });
+        let modified = page_node
+            .properties
+            .u64(PROP_LAST_MODIFIED_TIME_STAMP)
+            .and_then(filetime_to_datetime)
+            .or_else(|| {
+                page_node
+                    .properties
+                    .u32(PROP_LAST_MODIFIED_TIME)
+                    .and_then(time32_to_datetime)
+            });
+        if let Some(created) = created {
+            page.created = created;
+            page.modified = created;
+        }
+        if let Some(modified) = modified {
+            page.modified = modified;
+        }
+
+        report.add_page(&page, paragraphs, warnings);
+        Some(page)
     }
     
-    /// Create a detailed conversion report
-    pub fn create_conversion_report(&self, one_file_path: PathBuf) -> io::Result<Page> {
-        let file_size = fs::metadata(&one_file_path)?.len();
-        let extracted_text = self.extract_basic_text(one_file_path.clone())?;
-        
-        let report_content = format!(
-            "OneNote File Conversion Report\n\
-             ================================\n\
-             \n\
-             Source File: {}\n\
-             File Size: {} bytes\n\
-             Extracted Text Fragments: {}\n\
-             \n\
-             Extracted Content Preview:\n\
-             --------------------------\n\
-             {}\n\
-             \n\
-             Note: This is a basic extraction. For complete OneNote support,\n\
-             implement the full MS-ONESTORE specification.",
-            one_file_path.display(),
-            file_size,
-            extracted_text.len(),
-            extracted_text.join("\n")
-        );
-        
-        Ok(Page::new(
-            "Conversion Report".to_string(),
-            report_content,
-            Some(1),
-        ))
+    /// Scan a file for UTF-16 text without parsing it, as a fallback for
+    /// files the revision store reader rejects. The file is read lazily.
+    pub fn extract_basic_text(&self, one_file_path: PathBuf) -> io::Result<Utf16Runs<fs::File>> {
+        Ok(Utf16Runs::new(fs::File::open(one_file_path)?))
+    }
+    
+    /// Convert `path` and report what was found, skipped and warned about.
+    /// A file that cannot be converted still produces a report, with the
+    /// failure in `error`.
+    pub fn create_conversion_report(&self, path: PathBuf) -> io::Result<ConversionReport> {
+        let mut report = ConversionReport::new(path.clone(), fs::metadata(&path)?.len());
+        let result = if has_extension(&path, "onepkg") {
+            self.read_package(&path, &mut report).map(|_| ())
+        } else if has_extension(&path, "onetoc2") {
+            self.read_notebook(&path, &mut report).map(|_| ())
+        } else {
+            self.read_section(&path, None, &mut report).map(|_| ())
+        };
+
+        if let Err(e) = result {
+            report.error = Some(e.to_string());
+            if !has_extension(&path, "onepkg") {
+                let mut preview_chars = 0;
+                for run in self.extract_basic_text(path)? {
+                    let run = run?;
+                    preview_chars += run.chars().count();
+                    if preview_chars > MAX_PREVIEW_CHARS {
+                        report.warnings.push(format!(
+                            "Extracted content preview stops after {} characters",
+                            MAX_PREVIEW_CHARS
+                        ));
+                        break;
+                    }
+                    report.text_preview.push(run);
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// Text and embedded files gathered from one part of a page.
+#[derive(Default)]
+struct PageContents {
+    lines: Vec<String>,
+    attachments: Vec<Attachment>,
+    drawings: Vec<Drawing>,
+    /// Embedded files and ink that could not be recovered, with the reason
+    warnings: Vec<String>,
+    /// JCID (if readable) and reason for each object not turned into content
+    skipped: Vec<(Option<u32>, String)>,
+    unknown_properties: Vec<u32>,
+}
+
+/// Depth-first walk of an outline tree collecting one line per rich text
+/// node, the files behind image and embedded file nodes, and ink drawings.
+fn collect_contents(
+    store: &RevisionStore,
+    space: &ObjectSpace,
+    id: &ExtendedGuid,
+    contents: &mut PageContents,
+    visited: &mut HashSet<ExtendedGuid>,
+    depth: usize,
+) {
+    if depth > MAX_OUTLINE_DEPTH {
+        contents.skipped.push((None, format!("outline nested deeper than {} levels", MAX_OUTLINE_DEPTH)));
+        return;
+    }
+    if !visited.insert(*id) {
+        return;
+    }
+    let Some(object) = space.object(id) else {
+        contents.skipped.push((None, "referenced object is missing".to_string()));
+        return;
+    };
+    contents.unknown_properties.extend(
+        object
+            .properties
+            .properties
+            .iter()
+            .map(|property| property.id)
+            .filter(|id| !KNOWN_PROPERTIES.contains(id)),
+    );
+
+    match object.jcid {
+        JCID_RICH_TEXT_OE_NODE => {
+            if let Some(text) = object.properties.bytes(PROP_RICH_EDIT_TEXT_UNICODE) {
+                contents.lines.push(decode_utf16(text));
+            } else if let Some(text) = object.properties.bytes(PROP_TEXT_EXTENDED_ASCII) {
+                contents.lines.push(text.iter().map(|&b| b as char).collect::<String>().trim_end_matches('\0').to_string());
+            }
+        }
+        JCID_IMAGE_NODE => {
+            collect_file(store, space, object, PROP_PICTURE_CONTAINER, PROP_IMAGE_FILENAME, "Image", contents);
+        }
+        JCID_EMBEDDED_FILE_NODE => {
+            collect_file(store, space, object, PROP_EMBEDDED_FILE_CONTAINER, PROP_EMBEDDED_FILE_NAME, "Attachment", contents);
+        }
+        JCID_INK_CONTAINER => collect_ink(space, object, contents),
+        JCID_TITLE_NODE | JCID_OUTLINE_NODE | JCID_OUTLINE_ELEMENT_NODE | JCID_PAGE_MANIFEST_NODE => {}
+        // Children of unsupported nodes, such as table cells, are still read
+        _ => contents.skipped.push((Some(object.jcid), "unsupported object type".to_string())),
+    }
+
+    for property in CHILD_NODE_PROPERTIES {
+        for child in object.properties.object_ids(property) {
+            collect_contents(store, space, child, contents, visited, depth + 1);
+        }
+    }
+}
+
+/// Recover the file behind an image or embedded file node and leave a
+/// placeholder line where it appeared in the page.
+fn collect_file(
+    store: &RevisionStore,
+    space: &ObjectSpace,
+    node: &Object,
+    container_property: u32,
+    name_property: u32,
+    kind: &str,
+    contents: &mut PageContents,
+) {
+    let name = node
+        .properties
+        .bytes(name_property)
+        .map(decode_utf16)
+        .filter(|name| !name.trim().is_empty());
+    let file_data = node
+        .properties
+        .object_ids(container_property)
+        .first()
+        .and_then(|id| space.object(id))
+        .and_then(|container| container.file_data.as_ref());
+    let fallback_name = || {
+        let extension = file_data.map(|file_data| file_data.extension.as_str()).unwrap_or("");
+        format!("{}-{}{}", kind.to_lowercase(), contents.lines.len() + 1, extension)
+    };
+
+    let Some(file_data) = file_data else {
+        let name = name.unwrap_or_else(fallback_name);
+        contents.lines.push(format!("[Missing {}: {}]", kind.to_lowercase(), name));
+        contents.warnings.push(format!("{}: no file data object", name));
+        return;
+    };
+
+    match store.resolve_file_data(file_data) {
+        Ok(data) => {
+            let name = name.unwrap_or_else(fallback_name);
+            contents.lines.push(format!("[{}: {}]", kind, name));
+            contents.attachments.push(Attachment {
+                mime_type: mime_type_for(&name, data).to_string(),
+                name,
+                data: data.to_vec(),
+            });
+        }
+        Err(reason) => {
+            let name = name.unwrap_or_else(fallback_name);
+            contents.lines.push(format!("[Missing {}: {}]", kind.to_lowercase(), name));
+            contents.warnings.push(format!("{}: {}", name, reason));
+        }
+    }
+}
+
+/// Decode the strokes behind an ink container into a drawing and leave a
+/// placeholder line where it appeared in the page.
+fn collect_ink(space: &ObjectSpace, container: &Object, contents: &mut PageContents) {
+    let number = contents.drawings.len() + 1;
+    let Some(ink_data) = container
+        .properties
+        .object_ids(PROP_INK_DATA)
+        .first()
+        .and_then(|id| space.object(id))
+        .filter(|ink_data| ink_data.jcid == JCID_INK_DATA_NODE)
+    else {
+        contents.skipped.push((Some(container.jcid), "ink container has no ink data".to_string()));
+        return;
+    };
+    let scale = [PROP_INK_SCALING_X, PROP_INK_SCALING_Y].map(|id| {
+        positive_f32(&container.properties, id).unwrap_or(1.0) * PIXELS_PER_HIMETRIC
+    });
+
+    let mut strokes = Vec::new();
+    let mut unreadable = 0;
+    for id in ink_data.properties.object_ids(PROP_INK_STROKES) {
+        match space.object(id).and_then(|stroke| decode_stroke(space, stroke, scale)) {
+            Some(stroke) => strokes.push(stroke),
+            None => unreadable += 1,
+        }
+    }
+    if unreadable > 0 {
+        contents.warnings.push(format!("Drawing {}: skipped {} unreadable ink strokes", number, unreadable));
+    }
+
+    let drawing = Drawing::from_strokes(strokes);
+    if drawing.strokes.is_empty() {
+        contents.skipped.push((Some(container.jcid), "ink container has no readable strokes".to_string()));
+        return;
+    }
+    contents.lines.push(format!("[Drawing {}: {} strokes]", number, drawing.strokes.len()));
+    contents.drawings.push(drawing);
+}
+
+/// Turn an ink stroke node into a stroke in pixels. `scale` converts ink
+/// units to pixels along each axis.
+fn decode_stroke(space: &ObjectSpace, stroke: &Object, scale: [f32; 2]) -> Option<Stroke> {
+    if stroke.jcid != JCID_INK_STROKE_NODE {
+        return None;
+    }
+    // Every point holds a value per dimension; only X and Y are drawn
+    let dimensions = stroke
+        .properties
+        .bytes(PROP_INK_DIMENSIONS)
+        .map(|dimensions| dimensions.len() / INK_DIMENSION_SIZE)
+        .filter(|&count| count >= 2)
+        .unwrap_or(2);
+    let values = decode_ink_path(stroke.properties.bytes(PROP_INK_PATH)?)?;
+    if values.is_empty() || values.len() % dimensions != 0 {
+        return None;
+    }
+
+    // The first point is absolute and each later one is a delta from the last
+    let mut position = [0i64; 2];
+    let mut points = Vec::with_capacity(values.len() / dimensions);
+    for point in values.chunks_exact(dimensions) {
+        position = [position[0].checked_add(point[0])?, position[1].checked_add(point[1])?];
+        points.push([position[0] as f32 * scale[0], position[1] as f32 * scale[1]]);
+    }
+
+    let properties = stroke
+        .properties
+        .object_ids(PROP_INK_STROKE_PROPERTIES)
+        .first()
+        .and_then(|id| space.object(id))
+        .filter(|properties| properties.jcid == JCID_STROKE_PROPERTIES_NODE)
+        .map(|properties| &properties.properties);
+    let pen_size = match properties.map(|p| (positive_f32(p, PROP_INK_WIDTH), positive_f32(p, PROP_INK_HEIGHT))) {
+        Some((Some(width), Some(height))) => width.max(height),
+        Some((Some(size), None)) | Some((None, Some(size))) => size,
+        _ => DEFAULT_INK_PEN_SIZE,
+    };
+    // Colors are COLORREFs (0x00BBGGRR); transparency 0 is opaque
+    let [r, g, b, _] = properties
+        .and_then(|properties| properties.u32(PROP_INK_COLOR))
+        .unwrap_or(0)
+        .to_le_bytes();
+    let alpha = 255 - properties.and_then(|properties| properties.u8(PROP_INK_TRANSPARENCY)).unwrap_or(0);
+
+    Some(Stroke {
+        points,
+        width: pen_size * (scale[0] + scale[1]) / 2.0,
+        color: [r, g, b, alpha],
+    })
+}
+
+/// Decode an ink path: variable-length integers holding seven bits per byte,
+/// low bits first, with the high bit set on every byte but the last. The
+/// low bit of each value is its sign (zigzag encoding).
+fn decode_ink_path(bytes: &[u8]) -> Option<Vec<i64>> {
+    let mut values = Vec::new();
+    let mut value = 0u64;
+    let mut shift = 0;
+    for &byte in bytes {
+        if shift >= 64 {
+            return None;
+        }
+        value |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            values.push((value >> 1) as i64 ^ -((value & 1) as i64));
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+        }
+    }
+    // A path cut off in the middle of a value is corrupt
+    (shift == 0).then_some(values)
+}
+
+/// A float stored as a four-byte property, if it is a positive number.
+fn positive_f32(properties: &PropertySet, id: u32) -> Option<f32> {
+    properties
+        .u32(id)
+        .map(f32::from_bits)
+        .filter(|value| value.is_finite() && *value > 0.0)
+}
+
+/// Guess a MIME type from the file name, falling back to the file's magic bytes.
+fn mime_type_for(name: &str, data: &[u8]) -> &'static str {
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" => return "image/png",
+        "jpg" | "jpeg" => return "image/jpeg",
+        "gif" => return "image/gif",
+        "bmp" => return "image/bmp",
+        "tif" | "tiff" => return "image/tiff",
+        "emf" => return "image/emf",
+        "wmf" => return "image/wmf",
+        "svg" => return "image/svg+xml",
+        "pdf" => return "application/pdf",
+        "txt" => return "text/plain",
+        "doc" => return "application/msword",
+        "docx" => return "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xls" => return "application/vnd.ms-excel",
+        "xlsx" => return "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "ppt" => return "application/vnd.ms-powerpoint",
+        "pptx" => return "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "zip" => return "application/zip",
+        "mp3" => return "audio/mpeg",
+        "wav" => return "audio/wav",
+        "mp4" => return "video/mp4",
+        _ => {}
+    }
+
+    if data.starts_with(b"\x89PNG") {
+        "image/png"
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "image/jpeg"
+    } else if data.starts_with(b"GIF8") {
+        "image/gif"
+    } else if data.starts_with(b"BM") {
+        "image/bmp"
+    } else if data.starts_with(b"%PDF") {
+        "application/pdf"
+    } else if data.starts_with(b"PK\x03\x04") {
+        "application/zip"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+/// Read a whole input file, refusing files larger than `MAX_INPUT_FILE_SIZE`.
+fn read_input_file(path: &Path) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    fs::File::open(path)?
+        .take(MAX_INPUT_FILE_SIZE + 1)
+        .read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_INPUT_FILE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is larger than any OneNote file can be", path.display()),
+        ));
+    }
+    Ok(data)
+}
+
+/// Number pages in order without touching their timestamps.
+fn number_pages(pages: &mut [Page]) {
+    for (index, page) in pages.iter_mut().enumerate() {
+        page.number = Some(index as u32 + 1);
     }
 }
 
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+/// Find the .onetoc2 file inside a section group folder.
+fn find_toc_file(folder: &Path) -> io::Result<Option<PathBuf>> {
+    let mut candidates: Vec<PathBuf> = fs::read_dir(folder)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| has_extension(path, "onetoc2"))
+        .collect();
+    candidates.sort();
+    Ok(candidates.into_iter().next())
+}
+
+fn decode_utf16(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+        .trim_end_matches('\0')
+        .replace('\r', "\n")
+}
+
+/// Convert a Windows FILETIME (100ns intervals since 1601-01-01) to UTC.
+fn filetime_to_datetime(filetime: u64) -> Option<DateTime<Utc>> {
+    let seconds = (filetime / 10_000_000) as i64 - 11_644_473_600;
+    let nanos = (filetime % 10_000_000) as u32 * 100;
+    DateTime::from_timestamp(seconds, nanos)
+}
+
+/// Convert a OneNote Time32 (seconds since 1980-01-01) to UTC.
+fn time32_to_datetime(time: u32) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(time as i64 + 315_532_800, 0)
+}
+
 impl Default for OneNoteConverter {
     fn default() -> Self {
         Self::new()
@@ -139,22 +955,931 @@ impl Default for OneNoteConverter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::conversion_report::{AttachmentReport, UnknownProperty};
+    use crate::onestore::test_builder::*;
+    use crate::onestore::{Guid, GUID_FILE_TYPE_ONE, GUID_FILE_TYPE_ONETOC2};
     use std::io::Write;
+    use std::path::Path;
     use tempfile::NamedTempFile;
-    
+
+    const JCID_SECTION_NODE: u32 = 0x00060007;
+    const JCID_PAGE_METADATA: u32 = 0x00020030;
+    const JCID_TABLE_NODE: u32 = 0x00060022;
+    const PROP_PARAGRAPH_STYLE: u32 = 0x14001C10;
+
+    const TOC_GUID: Guid = Guid::new(0x70C00000, 0x0001, 0x4000, [0x80, 0, 0, 0, 0, 0, 0, 3]);
+    const SECTION_GUID: Guid = Guid::new(0x5EC71000, 0x0001, 0x4000, [0x80, 0, 0, 0, 0, 0, 0, 1]);
+
+    struct FixturePage<'a> {
+        title: &'a str,
+        paragraphs: &'a [&'a str],
+        created: u64,
+        modified: u32,
+        /// 1 for a page, 2 for a subpage and so on
+        level: u32,
+    }
+
+    fn fixture_path(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/onenote")
+            .join(name)
+    }
+
+    /// Build a section file laid out the way OneNote stores one: a section
+    /// node and page series in the root object space, and one object space
+    /// per page holding a page manifest, page node, title and outline.
+    fn build_section(pages: &[FixturePage]) -> Vec<u8> {
+        let mut builder = StoreBuilder::new(GUID_FILE_TYPE_ONE);
+        let page_guids: Vec<Guid> = (0..pages.len())
+            .map(|i| Guid::new(0x9A6E0000 + i as u32, 0x0001, 0x4000, [0x80, 0, 0, 0, 0, 0, 0, 2]))
+            .collect();
+
+        let mut space_references = Vec::new();
+        for (page, guid) in pages.iter().zip(&page_guids) {
+            let mut objects = vec![
+                TestObject {
+                    compact_id: 1,
+                    jcid: JCID_PAGE_MANIFEST_NODE,
+                    properties: PropSetBuilder::default()
+                        .object_ids(PROP_CONTENT_CHILD_NODES, &[2])
+                        .build(),
+                },
+                TestObject {
+                    compact_id: 2,
+                    jcid: JCID_PAGE_NODE,
+                    properties: PropSetBuilder::default()
+                        .object_ids(PROP_STRUCTURE_ELEMENT_CHILD_NODES, &[3, 6])
+                        .u32(PROP_LAST_MODIFIED_TIME, page.modified)
+                        .build(),
+                },
+                TestObject {
+                    compact_id: 3,
+                    jcid: JCID_TITLE_NODE,
+                    properties: PropSetBuilder::default()
+                        .object_ids(PROP_ELEMENT_CHILD_NODES, &[4])
+                        .build(),
+                },
+                TestObject {
+                    compact_id: 4,
+                    jcid: JCID_OUTLINE_ELEMENT_NODE,
+                    properties: PropSetBuilder::default()
+                        .object_ids(PROP_CONTENT_CHILD_NODES, &[5])
+                        .build(),
+                },
+                TestObject {
+                    compact_id: 5,
+                    jcid: JCID_RICH_TEXT_OE_NODE,
+                    properties: PropSetBuilder::default()
+                        .utf16(PROP_RICH_EDIT_TEXT_UNICODE, page.title)
+                        .build(),
+                },
+            ];
+
+            let elements: Vec<u32> = (0..page.paragraphs.len() as u32).map(|i| 10 + i * 2).collect();
+            objects.push(TestObject {
+                compact_id: 6,
+                jcid: JCID_OUTLINE_NODE,
+                properties: PropSetBuilder::default()
+                    .object_ids(PROP_ELEMENT_CHILD_NODES, &elements)
+                    .build(),
+            });
+            for (paragraph, element) in page.paragraphs.iter().zip(&elements) {
+                objects.push(TestObject {
+                    compact_id: *element,
+                    jcid: JCID_OUTLINE_ELEMENT_NODE,
+                    properties: PropSetBuilder::default()
+                        .object_ids(PROP_CONTENT_CHILD_NODES, &[element + 1])
+                        .build(),
+                });
+                objects.push(TestObject {
+                    compact_id: element + 1,
+                    jcid: JCID_RICH_TEXT_OE_NODE,
+                    properties: PropSetBuilder::default()
+                        .utf16(PROP_RICH_EDIT_TEXT_UNICODE, paragraph)
+                        .build(),
+                });
+            }
+            // Level 1 is left out, as older OneNote versions do
+            let mut metadata = PropSetBuilder::default()
+                .utf16(PROP_CACHED_TITLE_STRING, page.title)
+                .u64(PROP_TOPOLOGY_CREATION_TIME_STAMP, page.created);
+            if page.level > 1 {
+                metadata = metadata.u32(PROP_PAGE_LEVEL, page.level);
+            }
+            objects.push(TestObject {
+                compact_id: 7,
+                jcid: JCID_PAGE_METADATA,
+                properties: metadata.build(),
+            });
+
+            space_references.push(object_space(
+                &mut builder,
+                &ExtendedGuid::new(*guid, 1),
+                &[*guid],
+                &objects,
+                &[(ROOT_ROLE_DEFAULT_CONTENT, 1), (ROOT_ROLE_METADATA, 7)],
+            ));
+        }
+
+        // The section's global ID table lists its own GUID first, then every page space
+        let mut section_guids = vec![SECTION_GUID];
+        section_guids.extend(page_guids.iter().copied());
+        let page_space_ids: Vec<u32> = (1..=pages.len() as u32).map(|index| (index << 8) | 1).collect();
+        let section_space = ExtendedGuid::new(SECTION_GUID, 1);
+        let section_reference = object_space(
+            &mut builder,
+            &section_space,
+            &section_guids,
+            &[
+                TestObject {
+                    compact_id: 1,
+                    jcid: JCID_SECTION_NODE,
+                    properties: PropSetBuilder::default()
+                        .object_ids(PROP_ELEMENT_CHILD_NODES, &[2])
+                        .build(),
+                },
+                TestObject {
+                    compact_id: 2,
+                    jcid: JCID_PAGE_SERIES_NODE,
+                    properties: PropSetBuilder::default()
+                        .object_space_ids(PROP_CHILD_GRAPH_SPACE_ELEMENT_NODES, &page_space_ids)
+                        .build(),
+                },
+            ],
+            &[(ROOT_ROLE_DEFAULT_CONTENT, 1)],
+        );
+
+        // Page spaces are written before the section but listed after it
+        let mut root_nodes = vec![node(0x004, None, &extended_guid(&section_space)), section_reference];
+        root_nodes.extend(space_references.into_iter().rev());
+        let root = builder.list(&root_nodes);
+        builder.finish(root)
+    }
+
+    /// Build a .onetoc2 file listing `(name, ordering id)` entries in the
+    /// order given; OneNote sorts them by ordering id.
+    fn build_toc(entries: &[(&str, u32)]) -> Vec<u8> {
+        let mut builder = StoreBuilder::new(GUID_FILE_TYPE_ONETOC2);
+        let children: Vec<u32> = (2..entries.len() as u32 + 2).collect();
+        let mut objects = vec![TestObject {
+            compact_id: 1,
+            jcid: 0x00020001,
+            properties: PropSetBuilder::default()
+                .object_ids(PROP_TOC_CHILDREN, &children)
+                .build(),
+        }];
+        for ((name, order), compact_id) in entries.iter().zip(&children) {
+            objects.push(TestObject {
+                compact_id: *compact_id,
+                jcid: 0x00020001,
+                properties: PropSetBuilder::default()
+                    .utf16(PROP_FOLDER_CHILD_FILENAME, name)
+                    .u32(PROP_NOTEBOOK_ELEMENT_ORDERING_ID, *order)
+                    .build(),
+            });
+        }
+        let space = ExtendedGuid::new(TOC_GUID, 1);
+        let reference = toc_object_space(&mut builder, &space, &[TOC_GUID], &objects, &[(ROOT_ROLE_DEFAULT_CONTENT, 1)]);
+        let root = builder.list(&[node(0x004, None, &extended_guid(&space)), reference]);
+        builder.finish(root)
+    }
+
+    fn single_page_section(title: &str) -> Vec<u8> {
+        build_section(&[FixturePage {
+            title,
+            paragraphs: &["Body"],
+            created: 133_000_000_000_000_000,
+            modified: 1_400_000_000,
+            level: 1,
+        }])
+    }
+
+    fn two_page_section() -> Vec<u8> {
+        build_section(&[
+            FixturePage {
+                title: "Meeting Notes",
+                paragraphs: &["Agenda", "Budget review", "Größenänderung und 日本語のメモ"],
+                created: 133_000_000_000_000_000,
+                modified: 1_400_000_000,
+                level: 1,
+            },
+            FixturePage {
+                title: "Field Log",
+                paragraphs: &["Сегодня было солнечно"],
+                created: 133_100_000_000_000_000,
+                modified: 1_410_000_000,
+                level: 1,
+            },
+        ])
+    }
+
+    /// Build a one-page section whose outline holds an embedded picture, an
+    /// attached file stored outside the section, a paragraph of text, a
+    /// table the converter does not support and a dangling reference.
+    fn attachment_section(picture: &[u8]) -> Vec<u8> {
+        let page_guid = Guid::new(0x9A6E1000, 0x0001, 0x4000, [0x80, 0, 0, 0, 0, 0, 0, 2]);
+        let stored = Guid::new(0xF11E0001, 0x0001, 0x4000, [0x80, 0, 0, 0, 0, 0, 0, 9]);
+        let mut builder = StoreBuilder::new(GUID_FILE_TYPE_ONE);
+        let data_store = file_data_store(&mut builder, &[(stored, picture)]);
+
+        let element = |compact_id: u32, child: u32| TestObject {
+            compact_id,
+            jcid: JCID_OUTLINE_ELEMENT_NODE,
+            properties: PropSetBuilder::default()
+                .object_ids(PROP_CONTENT_CHILD_NODES, &[child])
+                .build(),
+        };
+        let objects = [
+            TestObject {
+                compact_id: 1,
+                jcid: JCID_PAGE_MANIFEST_NODE,
+                properties: PropSetBuilder::default()
+                    .object_ids(PROP_CONTENT_CHILD_NODES, &[2])
+                    .build(),
+            },
+            TestObject {
+                compact_id: 2,
+                jcid: JCID_PAGE_NODE,
+                properties: PropSetBuilder::default()
+                    .object_ids(PROP_STRUCTURE_ELEMENT_CHILD_NODES, &[3])
+                    .build(),
+            },
+            TestObject {
+                compact_id: 3,
+                jcid: JCID_OUTLINE_NODE,
+                properties: PropSetBuilder::default()
+                    .object_ids(PROP_ELEMENT_CHILD_NODES, &[4, 6, 8, 11, 13])
+                    .build(),
+            },
+            element(4, 5),
+            TestObject {
+                compact_id: 5,
+                jcid: JCID_IMAGE_NODE,
+                properties: PropSetBuilder::default()
+                    .object_id(PROP_PICTURE_CONTAINER, 20)
+                    .utf16(PROP_IMAGE_FILENAME, "whiteboard.png")
+                    .build(),
+            },
+            element(6, 7),
+            TestObject {
+                compact_id: 7,
+                jcid: JCID_EMBEDDED_FILE_NODE,
+                properties: PropSetBuilder::default()
+                    .object_id(PROP_EMBEDDED_FILE_CONTAINER, 21)
+                    .utf16(PROP_EMBEDDED_FILE_NAME, "budget.xlsx")
+                    .build(),
+            },
+            element(8, 9),
+            TestObject {
+                compact_id: 9,
+                jcid: JCID_RICH_TEXT_OE_NODE,
+                properties: PropSetBuilder::default()
+                    .utf16(PROP_RICH_EDIT_TEXT_UNICODE, "See attached")
+                    .u32(PROP_PARAGRAPH_STYLE, 3)
+                    .build(),
+            },
+            element(11, 12),
+            TestObject {
+                compact_id: 12,
+                jcid: JCID_TABLE_NODE,
+                properties: PropSetBuilder::default().build(),
+            },
+            element(13, 14),
+            TestObject {
+                compact_id: 10,
+                jcid: JCID_PAGE_METADATA,
+                properties: PropSetBuilder::default()
+                    .utf16(PROP_CACHED_TITLE_STRING, "Sketches")
+                    .build(),
+            },
+        ];
+        let page_space = ExtendedGuid::new(page_guid, 1);
+        let page_reference = object_space_with_files(
+            &mut builder,
+            &page_space,
+            &[page_guid],
+            &objects,
+            &[
+                (20, 0x00080039, &format!("<ifndf>{}", stored), ".png"),
+                (21, 0x00080039, "<file>{0B5E0000-0000-0000-0000-000000000000}.xlsx", ".xlsx"),
+            ],
+            &[(ROOT_ROLE_DEFAULT_CONTENT, 1), (ROOT_ROLE_METADATA, 10)],
+        );
+
+        let (section_space, section_reference) = single_page_section_space(&mut builder, page_guid);
+        let root = builder.list(&[
+            node(0x004, None, &extended_guid(&section_space)),
+            section_reference,
+            page_reference,
+            data_store,
+        ]);
+        builder.finish(root)
+    }
+
+    /// Write the root object space of a section holding the one page in
+    /// `page_guid`'s object space.
+    fn single_page_section_space(builder: &mut StoreBuilder, page_guid: Guid) -> (ExtendedGuid, Vec<u8>) {
+        let section_space = ExtendedGuid::new(SECTION_GUID, 1);
+        let section_reference = object_space(
+            builder,
+            &section_space,
+            &[SECTION_GUID, page_guid],
+            &[
+                TestObject {
+                    compact_id: 1,
+                    jcid: JCID_SECTION_NODE,
+                    properties: PropSetBuilder::default()
+                        .object_ids(PROP_ELEMENT_CHILD_NODES, &[2])
+                        .build(),
+                },
+                TestObject {
+                    compact_id: 2,
+                    jcid: JCID_PAGE_SERIES_NODE,
+                    properties: PropSetBuilder::default()
+                        .object_space_ids(PROP_CHILD_GRAPH_SPACE_ELEMENT_NODES, &[(1 << 8) | 1])
+                        .build(),
+                },
+            ],
+            &[(ROOT_ROLE_DEFAULT_CONTENT, 1)],
+        );
+        (section_space, section_reference)
+    }
+
+    /// Encode ink path values the way OneNote stores them.
+    fn ink_path(values: &[i64]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for &value in values {
+            let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+            while zigzag >= 0x80 {
+                bytes.push(zigzag as u8 | 0x80);
+                zigzag >>= 7;
+            }
+            bytes.push(zigzag as u8);
+        }
+        bytes
+    }
+
+    /// Build a one-page section with handwriting drawn on the page: a
+    /// translucent red stroke with pressure data, a dot drawn with the
+    /// default pen and a stroke whose path is cut off. The outline also
+    /// holds an ink container with no ink data.
+    fn ink_section() -> Vec<u8> {
+        let page_guid = Guid::new(0x9A6E2000, 0x0001, 0x4000, [0x80, 0, 0, 0, 0, 0, 0, 2]);
+        let mut builder = StoreBuilder::new(GUID_FILE_TYPE_ONE);
+        let objects = [
+            TestObject {
+                compact_id: 1,
+                jcid: JCID_PAGE_MANIFEST_NODE,
+                properties: PropSetBuilder::default()
+                    .object_ids(PROP_CONTENT_CHILD_NODES, &[2])
+                    .build(),
+            },
+            TestObject {
+                compact_id: 2,
+                jcid: JCID_PAGE_NODE,
+                properties: PropSetBuilder::default()
+                    .object_ids(PROP_STRUCTURE_ELEMENT_CHILD_NODES, &[3, 30])
+                    .build(),
+            },
+            TestObject {
+                compact_id: 3,
+                jcid: JCID_OUTLINE_NODE,
+                properties: PropSetBuilder::default()
+                    .object_ids(PROP_ELEMENT_CHILD_NODES, &[4])
+                    .build(),
+            },
+            TestObject {
+                compact_id: 4,
+                jcid: JCID_OUTLINE_ELEMENT_NODE,
+                properties: PropSetBuilder::default()
+                    .object_ids(PROP_CONTENT_CHILD_NODES, &[5])
+                    .build(),
+            },
+            TestObject {
+                compact_id: 5,
+                jcid: JCID_INK_CONTAINER,
+                properties: PropSetBuilder::default().build(),
+            },
+            TestObject {
+                compact_id: 10,
+                jcid: JCID_PAGE_METADATA,
+                properties: PropSetBuilder::default()
+                    .utf16(PROP_CACHED_TITLE_STRING, "Field Sketch")
+                    .build(),
+            },
+            TestObject {
+                compact_id: 30,
+                jcid: JCID_INK_CONTAINER,
+                properties: PropSetBuilder::default()
+                    .object_id(PROP_INK_DATA, 31)
+                    .u32(PROP_INK_SCALING_X, 2.0f32.to_bits())
+                    .build(),
+            },
+            TestObject {
+                compact_id: 31,
+                jcid: JCID_INK_DATA_NODE,
+                properties: PropSetBuilder::default()
+                    .object_ids(PROP_INK_STROKES, &[32, 33, 34])
+                    .build(),
+            },
+            TestObject {
+                compact_id: 32,
+                jcid: JCID_INK_STROKE_NODE,
+                properties: PropSetBuilder::default()
+                    .bytes(PROP_INK_DIMENSIONS, &[0; INK_DIMENSION_SIZE * 3])
+                    .bytes(PROP_INK_PATH, &ink_path(&[0, 0, 500, 2540, 0, 20, 0, 1270, -10]))
+                    .object_id(PROP_INK_STROKE_PROPERTIES, 35)
+                    .build(),
+            },
+            TestObject {
+                compact_id: 33,
+                jcid: JCID_INK_STROKE_NODE,
+                properties: PropSetBuilder::default()
+                    .bytes(PROP_INK_PATH, &ink_path(&[1270, 2540]))
+                    .build(),
+            },
+            TestObject {
+                compact_id: 34,
+                jcid: JCID_INK_STROKE_NODE,
+                properties: PropSetBuilder::default()
+                    .bytes(PROP_INK_PATH, &[0x80])
+                    .build(),
+            },
+            TestObject {
+                compact_id: 35,
+                jcid: JCID_STROKE_PROPERTIES_NODE,
+                properties: PropSetBuilder::default()
+                    .u32(PROP_INK_WIDTH, 254.0f32.to_bits())
+                    .u32(PROP_INK_HEIGHT, 127.0f32.to_bits())
+                    .u32(PROP_INK_COLOR, 0x000000FF)
+                    .u8(PROP_INK_TRANSPARENCY, 0x80)
+                    .build(),
+            },
+        ];
+        let page_space = ExtendedGuid::new(page_guid, 1);
+        let page_reference = object_space(
+            &mut builder,
+            &page_space,
+            &[page_guid],
+            &objects,
+            &[(ROOT_ROLE_DEFAULT_CONTENT, 1), (ROOT_ROLE_METADATA, 10)],
+        );
+        let (section_space, section_reference) = single_page_section_space(&mut builder, page_guid);
+        let root = builder.list(&[
+            node(0x004, None, &extended_guid(&section_space)),
+            section_reference,
+            page_reference,
+        ]);
+        builder.finish(root)
+    }
+
+    /// Rewrites the checked-in fixtures; run with `cargo test -- --ignored`
+    /// after changing the fixture layout.
     #[test]
-    fn test_convert_placeholder() {
+    #[ignore]
+    fn regenerate_fixtures() {
+        fs::create_dir_all(fixture_path("")).unwrap();
+        fs::write(fixture_path("two_pages.one"), two_page_section()).unwrap();
+    }
+
+    #[test]
+    fn test_fixture_is_up_to_date() {
+        assert_eq!(fs::read(fixture_path("two_pages.one")).unwrap(), two_page_section());
+    }
+
+    #[test]
+    fn test_convert_section_fixture() {
+        let converter = OneNoteConverter::new();
+        let notebook = converter
+            .convert_to_notebookx(fixture_path("two_pages.one"))
+            .unwrap();
+
+        assert_eq!(notebook.title, "Converted from two_pages");
+        assert_eq!(notebook.pages.len(), 2);
+
+        let first = &notebook.pages[0];
+        assert_eq!(first.title, "Meeting Notes");
+        assert_eq!(first.content, "Agenda\nBudget review\nGrößenänderung und 日本語のメモ");
+        assert_eq!(first.number, Some(1));
+        assert_eq!(first.created, filetime_to_datetime(133_000_000_000_000_000).unwrap());
+        assert_eq!(first.modified, time32_to_datetime(1_400_000_000).unwrap());
+
+        let second = &notebook.pages[1];
+        assert_eq!(second.title, "Field Log");
+        assert_eq!(second.content, "Сегодня было солнечно");
+        assert_eq!(second.number, Some(2));
+    }
+
+    #[test]
+    fn test_convert_section_extracts_attachments() {
+        let picture = b"\x89PNG\r\n\x1a\nimage data";
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(&attachment_section(picture)).unwrap();
+
+        let converter = OneNoteConverter::new();
+        let notebook = converter
+            .convert_to_notebookx(temp_file.path().to_path_buf())
+            .unwrap();
+        let page = &notebook.pages[0];
+        assert_eq!(page.title, "Sketches");
+        assert_eq!(
+            page.content,
+            "[Image: whiteboard.png]\n[Missing attachment: budget.xlsx]\nSee attached"
+        );
+        assert_eq!(
+            page.attachments,
+            vec![Attachment {
+                name: "whiteboard.png".to_string(),
+                mime_type: "image/png".to_string(),
+                data: picture.to_vec(),
+            }]
+        );
+
+    }
+
+    #[test]
+    fn test_conversion_report_lists_pages_and_skipped_objects() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(&attachment_section(b"\x89PNG\r\n\x1a\nimage data")).unwrap();
+
         let converter = OneNoteConverter::new();
-        
-        // Create a temporary "OneNote" file for testing
+        let report = converter
+            .create_conversion_report(temp_file.path().to_path_buf())
+            .unwrap();
+        assert_eq!(report.error, None);
+        assert_eq!(report.pages.len(), 1);
+        let page = &report.pages[0];
+        assert_eq!(page.title, "Sketches");
+        assert_eq!(page.paragraphs, 3);
+        assert_eq!(
+            page.attachments,
+            vec![AttachmentReport {
+                name: "whiteboard.png".to_string(),
+                mime_type: "image/png".to_string(),
+                size: 18,
+            }]
+        );
+        assert_eq!(page.warnings.len(), 1);
+        assert!(page.warnings[0].starts_with("budget.xlsx: stored outside the section file"));
+
+        assert_eq!(
+            report.skipped_objects,
+            vec![
+                SkippedObject {
+                    page: Some("Sketches".to_string()),
+                    jcid: Some(JCID_TABLE_NODE),
+                    reason: "unsupported object type".to_string(),
+                },
+                SkippedObject {
+                    page: Some("Sketches".to_string()),
+                    jcid: None,
+                    reason: "referenced object is missing".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            report.unknown_properties,
+            vec![UnknownProperty { id: PROP_PARAGRAPH_STYLE, occurrences: 1 }]
+        );
+
+        let rendered = report.to_page().content;
+        assert!(rendered.contains("1. Sketches (3 paragraphs, 1 attachments)"));
+        assert!(rendered.contains("Attachment: whiteboard.png (image/png, 18 bytes)"));
+        assert!(rendered.contains("Sketches: 0x00060022 (unsupported object type)"));
+    }
+
+    #[test]
+    fn test_conversion_report_records_failure() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let text: Vec<u8> = "Plain notes saved as UTF-16".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        temp_file.write_all(&text).unwrap();
+
+        let converter = OneNoteConverter::new();
+        let report = converter
+            .create_conversion_report(temp_file.path().to_path_buf())
+            .unwrap();
+        assert!(report.error.is_some());
+        assert!(report.pages.is_empty());
+        assert_eq!(report.text_preview, vec!["Plain notes saved as UTF-16"]);
+    }
+
+    #[test]
+    fn test_convert_section_decodes_ink_strokes() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&ink_section()).unwrap();
+        let converter = OneNoteConverter::new();
+        let mut report = ConversionReport::default();
+        let pages = converter.read_section(file.path(), None, &mut report).unwrap();
+
+        assert_eq!(pages.len(), 1);
+        let page = &pages[0];
+        assert_eq!(page.title, "Field Sketch");
+        assert_eq!(page.content, "[Drawing 1: 2 strokes]");
+        assert_eq!(page.drawings.len(), 1);
+        let drawing = &page.drawings[0];
+        let close = |a: f32, b: f32| (a - b).abs() < 0.01;
+
+        // X is scaled by 2 and HIMETRIC becomes 96 DPI pixels; the wider
+        // pen dimension is used and the drawing starts at its pen edge
+        let red = &drawing.strokes[0];
+        assert_eq!(red.color, [0xFF, 0, 0, 0x7F]);
+        assert!(close(red.width, 14.4));
+        let expected = [[7.2, 7.2], [199.2, 7.2], [199.2, 55.2]];
+        assert_eq!(red.points.len(), expected.len());
+        for (point, expected) in red.points.iter().zip(expected) {
+            assert!(close(point[0], expected[0]) && close(point[1], expected[1]), "{:?}", point);
+        }
+        let dot = &drawing.strokes[1];
+        assert_eq!(dot.color, [0, 0, 0, 0xFF]);
+        assert!(close(dot.width, 3.0));
+        assert_eq!(dot.points.len(), 1);
+        assert!(close(dot.points[0][0], 103.2) && close(dot.points[0][1], 103.2));
+        assert!(close(drawing.width, 206.4) && close(drawing.height, 104.7));
+
+        assert_eq!(report.pages[0].drawings, 1);
+        assert_eq!(report.pages[0].warnings, vec!["Drawing 1: skipped 1 unreadable ink strokes"]);
+        assert_eq!(
+            report.skipped_objects,
+            vec![SkippedObject {
+                page: Some("Field Sketch".to_string()),
+                jcid: Some(JCID_INK_CONTAINER),
+                reason: "ink container has no ink data".to_string(),
+            }]
+        );
+        assert!(report.unknown_properties.is_empty());
+    }
+
+    #[test]
+    fn test_decode_ink_path() {
+        assert_eq!(decode_ink_path(&ink_path(&[0, -1, 1, 63, -64, 64, 100_000])), Some(vec![0, -1, 1, 63, -64, 64, 100_000]));
+        assert_eq!(decode_ink_path(&[0x81]), None);
+        assert_eq!(decode_ink_path(&[0xFF; 11]), None);
+    }
+
+    #[test]
+    fn test_mime_type_falls_back_to_magic_bytes() {
+        assert_eq!(mime_type_for("Photo.JPG", b""), "image/jpeg");
+        assert_eq!(mime_type_for("scan", b"%PDF-1.7"), "application/pdf");
+        assert_eq!(mime_type_for("blob.bin", b"\0\0"), "application/octet-stream");
+    }
+
+    #[test]
+    fn test_convert_section_maps_page_levels_to_subpages() {
+        let pages: Vec<FixturePage> = [("Projects", 1), ("Garden", 2), ("Seeds", 3), ("Kitchen", 2), ("Journal", 1), ("Monday", 2)]
+            .iter()
+            .map(|&(title, level)| FixturePage {
+                title,
+                paragraphs: &[],
+                created: 133_000_000_000_000_000,
+                modified: 1_400_000_000,
+                level,
+            })
+            .collect();
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(&build_section(&pages)).unwrap();
+
+        let converter = OneNoteConverter::new();
+        let notebook = converter
+            .convert_to_notebookx(temp_file.path().to_path_buf())
+            .unwrap();
+        let parent_titles: Vec<(&str, Option<&str>)> = notebook
+            .pages
+            .iter()
+            .map(|page| {
+                let parent = page
+                    .parent_id
+                    .as_deref()
+                    .and_then(|id| notebook.get_page(id))
+                    .map(|parent| parent.title.as_str());
+                (page.title.as_str(), parent)
+            })
+            .collect();
+        assert_eq!(
+            parent_titles,
+            vec![
+                ("Projects", None),
+                ("Garden", Some("Projects")),
+                ("Seeds", Some("Garden")),
+                ("Kitchen", Some("Projects")),
+                ("Journal", None),
+                ("Monday", Some("Journal")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_basic_text_finds_every_script() {
+        let converter = OneNoteConverter::new();
+        let text: Vec<String> = converter
+            .extract_basic_text(fixture_path("two_pages.one"))
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            text,
+            vec![
+                "Meeting Notes",
+                "Agenda",
+                "Budget review",
+                "Größenänderung und 日本語のメモ",
+                "Meeting Notes",
+                "Field Log",
+                "Сегодня было солнечно",
+                "Field Log",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_convert_notebook_keeps_stored_section_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let notebook_dir = dir.path().join("Field Notes");
+        let group_dir = notebook_dir.join("Archive");
+        fs::create_dir_all(&group_dir).unwrap();
+
+        fs::write(
+            notebook_dir.join("Open Notebook.onetoc2"),
+            build_toc(&[("Zeta.one", 1), ("Alpha.one", 3), ("Archive", 2)]),
+        )
+        .unwrap();
+        fs::write(notebook_dir.join("Zeta.one"), single_page_section("First")).unwrap();
+        fs::write(notebook_dir.join("Alpha.one"), two_page_section()).unwrap();
+        fs::write(group_dir.join("Archive.onetoc2"), build_toc(&[("2019.one", 1)])).unwrap();
+        fs::write(group_dir.join("2019.one"), single_page_section("Old")).unwrap();
+
+        let converter = OneNoteConverter::new();
+        let notebook = converter
+            .convert_notebook(notebook_dir.join("Open Notebook.onetoc2"))
+            .unwrap();
+
+        assert_eq!(notebook.title, "Converted from Field Notes");
+        let pages: Vec<(&str, Option<&str>, Option<u32>)> = notebook
+            .pages
+            .iter()
+            .map(|page| (page.title.as_str(), page.section.as_deref(), page.number))
+            .collect();
+        assert_eq!(
+            pages,
+            vec![
+                ("First", Some("Zeta"), Some(1)),
+                ("Old", Some("Archive / 2019"), Some(2)),
+                ("Meeting Notes", Some("Alpha"), Some(3)),
+                ("Field Log", Some("Alpha"), Some(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_convert_notebook_reports_missing_section() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Notebook.onetoc2"), build_toc(&[("Missing.one", 1)])).unwrap();
+
+        let converter = OneNoteConverter::new();
+        let error = converter
+            .convert_notebook(dir.path().join("Notebook.onetoc2"))
+            .unwrap_err();
+        assert!(error.to_string().contains("Missing.one"));
+    }
+
+    #[test]
+    fn test_convert_package_unpacks_notebook() {
+        let toc = build_toc(&[("Beta.one", 1), ("Alpha.one", 2)]);
+        let beta = single_page_section("Packed");
+        let alpha = two_page_section();
+        let package = crate::cab::test_builder::build_cabinet(
+            &[
+                ("Open Notebook.onetoc2", &toc),
+                ("Alpha.one", &alpha),
+                ("Beta.one", &beta),
+                ("..\\escape.one", &beta),
+            ],
+            true,
+            4096,
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let package_path = dir.path().join("Team Notes.onepkg");
+        fs::write(&package_path, package).unwrap();
+
+        let converter = OneNoteConverter::new();
+        let notebook = converter.convert_package(package_path.clone()).unwrap();
+        assert_eq!(notebook.title, "Converted from Team Notes");
+        let titles: Vec<&str> = notebook.pages.iter().map(|page| page.title.as_str()).collect();
+        assert_eq!(titles, vec!["Packed", "Meeting Notes", "Field Log"]);
+        assert_eq!(notebook.pages[1].section.as_deref(), Some("Alpha"));
+
+        let report = converter.create_conversion_report(package_path).unwrap();
+        assert_eq!(report.packaged_files.len(), 4);
+        assert_eq!(report.packaged_files[1].name, "Alpha.one");
+        assert_eq!(report.packaged_files[1].compression, "MSZIP");
+        assert_eq!(report.pages.len(), 3);
+        assert_eq!(report.pages[1].section.as_deref(), Some("Alpha"));
+        assert_eq!(report.warnings, vec!["Skipped packaged file with unsafe path: ../escape.one"]);
+    }
+
+    #[test]
+    fn test_convert_package_without_toc_uses_sections() {
+        let section = single_page_section("Loose");
+        let package = crate::cab::test_builder::build_cabinet(&[("Loose.one", &section)], false, 4096);
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(&package).unwrap();
+
+        let converter = OneNoteConverter::new();
+        let notebook = converter.convert_package(temp_file.path().to_path_buf()).unwrap();
+        assert_eq!(notebook.pages.len(), 1);
+        assert_eq!(notebook.pages[0].section.as_deref(), Some("Loose"));
+    }
+
+    #[test]
+    fn test_convert_rejects_non_onenote_file() {
+        let converter = OneNoteConverter::new();
+
         let mut temp_file = NamedTempFile::new().unwrap();
         temp_file.write_all(b"Fake OneNote content").unwrap();
-        
+
         let result = converter.convert_to_notebookx(temp_file.path().to_path_buf());
-        assert!(result.is_ok());
-        
-        let notebook = result.unwrap();
-        assert_eq!(notebook.pages.len(), 1);
-        assert!(notebook.title.contains("Converted from"));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_timestamp_conversion() {
+        assert_eq!(
+            filetime_to_datetime(116_444_736_000_000_000).unwrap(),
+            DateTime::from_timestamp(0, 0).unwrap()
+        );
+        assert_eq!(
+            time32_to_datetime(0).unwrap().to_rfc3339(),
+            "1980-01-01T00:00:00+00:00"
+        );
     }
-}
\ No newline at end of file
+
+    /// Run untrusted bytes through every parser a user's file can reach.
+    /// Each must return, with an error if need be, rather than panic.
+    fn parse_untrusted(data: &[u8]) {
+        let converter = OneNoteConverter::new();
+        let _ = converter.parse_section(data, None, &mut ConversionReport::default());
+        let _ = converter.parse_toc_entries(data);
+        if let Ok(cabinet) = Cabinet::parse(data) {
+            for entry in &cabinet.entries {
+                cabinet.compression_name(entry);
+            }
+            let _ = cabinet.extract_all();
+        }
+        for run in Utf16Runs::new(data) {
+            if run.is_err() {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_regression_corpus() {
+        let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/corpus");
+        let mut inputs = 0;
+        for entry in fs::read_dir(corpus).unwrap() {
+            parse_untrusted(&fs::read(entry.unwrap().path()).unwrap());
+            inputs += 1;
+        }
+        assert!(inputs > 0);
+    }
+
+    #[test]
+    fn test_mutated_inputs_do_not_panic() {
+        let seeds = [
+            two_page_section(),
+            attachment_section(b"\x89PNG\r\n\x1a\n"),
+            ink_section(),
+            build_toc(&[("A.one", 2), ("B.one", 1)]),
+            crate::cab::test_builder::build_cabinet(&[("A.one", &single_page_section("A"))], true, 1024),
+        ];
+        // xorshift64 with a fixed seed, so every run tries the same inputs
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for _ in 0..1000 {
+            let mut data = seeds[next() as usize % seeds.len()].clone();
+            for _ in 0..1 + next() % 4 {
+                if data.is_empty() {
+                    break;
+                }
+                let pos = next() as usize % data.len();
+                match next() % 4 {
+                    0 => data[pos] ^= 1 << (next() % 8),
+                    1 => data[pos] = next() as u8,
+                    2 => data.truncate(pos),
+                    _ => {
+                        // Sizes and counts set to their maximum
+                        for (byte, value) in data[pos..].iter_mut().zip(u32::MAX.to_le_bytes()) {
+                            *byte = value;
+                        }
+                    }
+                }
+            }
+            parse_untrusted(&data);
+        }
+    }
+}
@@ -0,0 +1,1548 @@
+//! Reader for the MS-ONESTORE revision store format.
+//!
+//! OneNote section files (`.one`) and table-of-contents files (`.onetoc2`) are
+//! both revision stores: a fixed header pointing at a tree of FileNodeLists,
+//! which in turn declare object spaces, their revisions and the property sets
+//! of every object. This module only understands the storage layer; mapping
+//! objects onto pages is done by `OneNoteConverter`.
+
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+
+const HEADER_SIZE: usize = 1024;
+
+const FILE_NODE_LIST_HEADER_MAGIC: u64 = 0xA4567AB1F5F7F4C4;
+const FILE_NODE_LIST_FOOTER_MAGIC: u64 = 0x8BC215C38233BA4B;
+const TRANSACTION_SENTINEL: u32 = 0x00000001;
+
+/// Chunks may be referenced from any number of places and are decoded each
+/// time, so a small file could otherwise expand without bound. Decoding
+/// stops once this many times the file's size has been read.
+const MAX_DECODED_RATIO: usize = 4;
+
+pub const GUID_FILE_TYPE_ONE: Guid =
+    Guid::new(0x7B5C52E4, 0xD88C, 0x4DA7, [0xAE, 0xB1, 0x53, 0x78, 0xD0, 0x29, 0x96, 0xD3]);
+pub const GUID_FILE_TYPE_ONETOC2: Guid =
+    Guid::new(0x43FF2FA1, 0xEFD9, 0x4C76, [0x9E, 0xE2, 0x10, 0xEA, 0x57, 0x22, 0x76, 0x5F]);
+pub const GUID_FILE_FORMAT: Guid =
+    Guid::new(0x109ADD3F, 0x911B, 0x49F5, [0xA5, 0xD0, 0x17, 0x91, 0xED, 0xC8, 0xAE, 0xD8]);
+const GUID_FILE_DATA_STORE_OBJECT_HEADER: Guid =
+    Guid::new(0xBDE316E7, 0x2665, 0x4511, [0xA4, 0xC4, 0x8D, 0x4D, 0x0B, 0x7A, 0x9E, 0xAC]);
+const GUID_FILE_DATA_STORE_OBJECT_FOOTER: Guid =
+    Guid::new(0x71FBA722, 0x0F79, 0x4A0B, [0xBB, 0x13, 0x89, 0x92, 0x56, 0x42, 0x6B, 0x24]);
+
+// FileNode IDs (MS-ONESTORE 2.4.3)
+const OBJECT_SPACE_MANIFEST_ROOT: u32 = 0x004;
+const OBJECT_SPACE_MANIFEST_LIST_REFERENCE: u32 = 0x008;
+const REVISION_MANIFEST_LIST_REFERENCE: u32 = 0x010;
+const REVISION_MANIFEST_START4: u32 = 0x01B;
+const REVISION_MANIFEST_END: u32 = 0x01C;
+const REVISION_MANIFEST_START6: u32 = 0x01E;
+const REVISION_MANIFEST_START7: u32 = 0x01F;
+const GLOBAL_ID_TABLE_START: u32 = 0x021;
+const GLOBAL_ID_TABLE_START2: u32 = 0x022;
+const GLOBAL_ID_TABLE_ENTRY: u32 = 0x024;
+const GLOBAL_ID_TABLE_ENTRY2: u32 = 0x025;
+const GLOBAL_ID_TABLE_ENTRY3: u32 = 0x026;
+const GLOBAL_ID_TABLE_END: u32 = 0x028;
+const OBJECT_DECLARATION_WITH_REF_COUNT: u32 = 0x02D;
+const OBJECT_DECLARATION_WITH_REF_COUNT2: u32 = 0x02E;
+const OBJECT_REVISION_WITH_REF_COUNT: u32 = 0x041;
+const OBJECT_REVISION_WITH_REF_COUNT2: u32 = 0x042;
+const ROOT_OBJECT_REFERENCE2: u32 = 0x059;
+const ROOT_OBJECT_REFERENCE3: u32 = 0x05A;
+const OBJECT_DECLARATION_FILE_DATA3_REF_COUNT: u32 = 0x072;
+const OBJECT_DECLARATION_FILE_DATA3_LARGE_REF_COUNT: u32 = 0x073;
+const FILE_DATA_STORE_LIST_REFERENCE: u32 = 0x090;
+const FILE_DATA_STORE_OBJECT_REFERENCE: u32 = 0x094;
+const OBJECT_DECLARATION2_REF_COUNT: u32 = 0x0A4;
+const OBJECT_DECLARATION2_LARGE_REF_COUNT: u32 = 0x0A5;
+const OBJECT_GROUP_LIST_REFERENCE: u32 = 0x0B0;
+const READ_ONLY_OBJECT_DECLARATION2_REF_COUNT: u32 = 0x0C4;
+const READ_ONLY_OBJECT_DECLARATION2_LARGE_REF_COUNT: u32 = 0x0C5;
+const CHUNK_TERMINATOR: u32 = 0x0FF;
+
+// JCID flag bits (MS-ONESTORE 2.6.14)
+const JCID_IS_PROPERTY_SET: u32 = 0x0002_0000;
+
+// Property types (MS-ONESTORE 2.6.6)
+const PROPERTY_TYPE_NO_DATA: u32 = 0x1;
+const PROPERTY_TYPE_BOOL: u32 = 0x2;
+const PROPERTY_TYPE_ONE_BYTE: u32 = 0x3;
+const PROPERTY_TYPE_TWO_BYTES: u32 = 0x4;
+const PROPERTY_TYPE_FOUR_BYTES: u32 = 0x5;
+const PROPERTY_TYPE_EIGHT_BYTES: u32 = 0x6;
+const PROPERTY_TYPE_LENGTH_PREFIXED: u32 = 0x7;
+const PROPERTY_TYPE_OBJECT_ID: u32 = 0x8;
+const PROPERTY_TYPE_OBJECT_IDS: u32 = 0x9;
+const PROPERTY_TYPE_OBJECT_SPACE_ID: u32 = 0xA;
+const PROPERTY_TYPE_OBJECT_SPACE_IDS: u32 = 0xB;
+const PROPERTY_TYPE_CONTEXT_ID: u32 = 0xC;
+const PROPERTY_TYPE_CONTEXT_IDS: u32 = 0xD;
+const PROPERTY_TYPE_PROPERTY_VALUES: u32 = 0x10;
+const PROPERTY_TYPE_PROPERTY_SET: u32 = 0x11;
+
+/// Nesting limit for property sets embedded in property sets.
+const MAX_PROPERTY_SET_DEPTH: usize = 32;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Guid(pub [u8; 16]);
+
+impl Guid {
+    /// Builds a GUID from its textual field values, using the little-endian
+    /// byte layout in which GUIDs are stored on disk.
+    pub const fn new(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> Self {
+        let d1 = data1.to_le_bytes();
+        let d2 = data2.to_le_bytes();
+        let d3 = data3.to_le_bytes();
+        Self([
+            d1[0], d1[1], d1[2], d1[3], d2[0], d2[1], d3[0], d3[1],
+            data4[0], data4[1], data4[2], data4[3], data4[4], data4[5], data4[6], data4[7],
+        ])
+    }
+
+    pub fn is_nil(&self) -> bool {
+        self.0 == [0; 16]
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let b = &self.0;
+        write!(
+            f,
+            "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
+            u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            u16::from_le_bytes([b[4], b[5]]),
+            u16::from_le_bytes([b[6], b[7]]),
+            b[8], b[9], b[10], b[11], b[12], b[13], b[14], b[15]
+        )
+    }
+}
+
+impl fmt::Debug for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// A GUID plus a counter, used to identify objects, object spaces and revisions.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ExtendedGuid {
+    pub guid: Guid,
+    pub n: u32,
+}
+
+impl ExtendedGuid {
+    pub const fn new(guid: Guid, n: u32) -> Self {
+        Self { guid, n }
+    }
+
+    pub fn is_nil(&self) -> bool {
+        self.guid.is_nil() && self.n == 0
+    }
+}
+
+impl fmt::Debug for ExtendedGuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.guid, self.n)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileType {
+    Section,
+    TableOfContents,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    NoData,
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    Bytes(Vec<u8>),
+    ObjectIds(Vec<ExtendedGuid>),
+    ObjectSpaceIds(Vec<ExtendedGuid>),
+    ContextIds(Vec<ExtendedGuid>),
+    PropertySets(Vec<PropertySet>),
+    PropertySet(PropertySet),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Property {
+    /// PropertyID with the inline boolean bit masked off.
+    pub id: u32,
+    pub value: PropertyValue,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PropertySet {
+    pub properties: Vec<Property>,
+}
+
+impl PropertySet {
+    pub fn get(&self, id: u32) -> Option<&PropertyValue> {
+        self.properties
+            .iter()
+            .find(|p| p.id == id & 0x7FFF_FFFF)
+            .map(|p| &p.value)
+    }
+
+    pub fn bytes(&self, id: u32) -> Option<&[u8]> {
+        match self.get(id) {
+            Some(PropertyValue::Bytes(bytes)) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn u8(&self, id: u32) -> Option<u8> {
+        match self.get(id) {
+            Some(PropertyValue::U8(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn u32(&self, id: u32) -> Option<u32> {
+        match self.get(id) {
+            Some(PropertyValue::U32(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn u64(&self, id: u32) -> Option<u64> {
+        match self.get(id) {
+            Some(PropertyValue::U64(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn object_ids(&self, id: u32) -> &[ExtendedGuid] {
+        match self.get(id) {
+            Some(PropertyValue::ObjectIds(ids)) => ids,
+            _ => &[],
+        }
+    }
+
+    pub fn object_space_ids(&self, id: u32) -> &[ExtendedGuid] {
+        match self.get(id) {
+            Some(PropertyValue::ObjectSpaceIds(ids)) => ids,
+            _ => &[],
+        }
+    }
+}
+
+/// Where the contents of a file data object are stored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileDataReference {
+    /// `<ifndf>{GUID}` for data inside the file, `<file>name` for data in the
+    /// `onefiles` folder next to it, or `<invfdo>` if the data is gone.
+    pub reference: String,
+    /// File extension including the leading dot, e.g. `.png`
+    pub extension: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct Object {
+    pub jcid: u32,
+    pub properties: PropertySet,
+    pub file_data: Option<FileDataReference>,
+}
+
+/// The latest state of one object space: its objects and root objects by role.
+#[derive(Clone, Debug, Default)]
+pub struct ObjectSpace {
+    pub id: ExtendedGuid,
+    pub objects: HashMap<ExtendedGuid, Object>,
+    pub roots: HashMap<u32, ExtendedGuid>,
+}
+
+impl ObjectSpace {
+    pub fn object(&self, id: &ExtendedGuid) -> Option<&Object> {
+        self.objects.get(id)
+    }
+
+    pub fn root(&self, role: u32) -> Option<&Object> {
+        self.roots.get(&role).and_then(|id| self.objects.get(id))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RevisionStore {
+    pub file_type: FileType,
+    pub root_space: Option<ExtendedGuid>,
+    /// Object spaces in the order their manifests appear in the root list.
+    pub spaces: Vec<ObjectSpace>,
+    /// Embedded file contents keyed by the GUID file data objects refer to.
+    pub file_data: HashMap<Guid, Vec<u8>>,
+}
+
+impl RevisionStore {
+    pub fn parse(data: &[u8]) -> io::Result<Self> {
+        Parser::new(data)?.parse()
+    }
+
+    pub fn space(&self, id: &ExtendedGuid) -> Option<&ObjectSpace> {
+        self.spaces.iter().find(|space| space.id == *id)
+    }
+
+    /// Resolve a file data reference to its contents, or explain why the
+    /// contents are not available.
+    pub fn resolve_file_data(&self, reference: &FileDataReference) -> Result<&[u8], String> {
+        if let Some(guid) = reference.reference.strip_prefix("<ifndf>") {
+            let guid = parse_guid(guid).ok_or_else(|| format!("malformed file data reference {}", guid))?;
+            self.file_data
+                .get(&guid)
+                .map(|data| data.as_slice())
+                .ok_or_else(|| format!("file data {} is missing or corrupt", guid))
+        } else if let Some(name) = reference.reference.strip_prefix("<file>") {
+            Err(format!("stored outside the section file as {}", name))
+        } else if reference.reference.starts_with("<invfdo>") {
+            Err("marked as invalid by OneNote".to_string())
+        } else {
+            Err(format!("unknown file data reference {}", reference.reference))
+        }
+    }
+}
+
+/// Parses a GUID written as `{XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX}`.
+fn parse_guid(text: &str) -> Option<Guid> {
+    let hex = text.trim_start_matches('{').trim_end_matches('}');
+    let parts: Vec<&str> = hex.split('-').collect();
+    if parts.len() != 5 || parts.iter().map(|p| p.len()).ne([8, 4, 4, 4, 12]) {
+        return None;
+    }
+    let data1 = u32::from_str_radix(parts[0], 16).ok()?;
+    let data2 = u16::from_str_radix(parts[1], 16).ok()?;
+    let data3 = u16::from_str_radix(parts[2], 16).ok()?;
+    let tail = u64::from_str_radix(&format!("{}{}", parts[3], parts[4]), 16).ok()?;
+    Some(Guid::new(data1, data2, data3, tail.to_be_bytes()))
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if len > self.remaining() {
+            return Err(invalid(format!(
+                "Unexpected end of data: needed {} bytes at offset {}",
+                len, self.pos
+            )));
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        let b = self.bytes(8)?;
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(b);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    fn guid(&mut self) -> io::Result<Guid> {
+        let mut buf = [0u8; 16];
+        buf.copy_from_slice(self.bytes(16)?);
+        Ok(Guid(buf))
+    }
+
+    fn extended_guid(&mut self) -> io::Result<ExtendedGuid> {
+        let guid = self.guid()?;
+        let n = self.u32()?;
+        Ok(ExtendedGuid { guid, n })
+    }
+}
+
+/// A FileChunkReference of any width, normalized to byte offsets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct ChunkRef {
+    stp: u64,
+    cb: u64,
+    nil: bool,
+}
+
+impl ChunkRef {
+    fn read_64x32(reader: &mut Reader) -> io::Result<Self> {
+        let stp = reader.u64()?;
+        let cb = reader.u32()? as u64;
+        Ok(Self { stp, cb, nil: stp == u64::MAX && cb == 0 })
+    }
+
+    /// Reads a reference whose widths are given by a FileNode header.
+    fn read_formatted(reader: &mut Reader, stp_format: u32, cb_format: u32) -> io::Result<Self> {
+        let (stp, stp_nil) = match stp_format {
+            0 => {
+                let v = reader.u64()?;
+                (v, v == u64::MAX)
+            }
+            1 => {
+                let v = reader.u32()?;
+                (v as u64, v == u32::MAX)
+            }
+            2 => {
+                let v = reader.u16()?;
+                (v as u64 * 8, v == u16::MAX)
+            }
+            _ => {
+                let v = reader.u32()?;
+                (v as u64 * 8, v == u32::MAX)
+            }
+        };
+        let cb = match cb_format {
+            0 => reader.u32()? as u64,
+            1 => reader.u64()?,
+            2 => reader.u8()? as u64 * 8,
+            _ => reader.u16()? as u64 * 8,
+        };
+        Ok(Self { stp, cb, nil: stp_nil && cb == 0 })
+    }
+}
+
+/// One FileNode with its reference decoded and the remaining body left raw.
+struct FileNode<'a> {
+    id: u32,
+    reference: Option<ChunkRef>,
+    body: Reader<'a>,
+}
+
+/// Object declarations collected while walking a revision manifest.
+#[derive(Default)]
+struct RevisionState {
+    objects: HashMap<ExtendedGuid, Object>,
+    roots: HashMap<u32, ExtendedGuid>,
+    /// Revisions in a non-default context (e.g. version history) are skipped.
+    ignored: bool,
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    file_type: FileType,
+    root_list: ChunkRef,
+    committed_nodes: Option<HashMap<u32, u32>>,
+    /// Bytes of FileNodeLists, property sets and file data decoded so far
+    decoded: Cell<usize>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(data: &'a [u8]) -> io::Result<Self> {
+        if data.len() < HEADER_SIZE {
+            return Err(invalid("File is too small to be a OneNote revision store"));
+        }
+        let mut header = Reader::new(&data[..HEADER_SIZE]);
+        let file_type = match header.guid()? {
+            GUID_FILE_TYPE_ONE => FileType::Section,
+            GUID_FILE_TYPE_ONETOC2 => FileType::TableOfContents,
+            other => return Err(invalid(format!("Unknown OneNote file type {}", other))),
+        };
+        header.pos = 48;
+        if header.guid()? != GUID_FILE_FORMAT {
+            return Err(invalid("Not a OneNote revision store (bad file format GUID)"));
+        }
+        header.pos = 96;
+        let transaction_count = header.u32()?;
+        header.pos = 160;
+        let transaction_log = ChunkRef::read_64x32(&mut header)?;
+        let root_list = ChunkRef::read_64x32(&mut header)?;
+        if root_list.nil {
+            return Err(invalid("Revision store has no root file node list"));
+        }
+
+        let mut parser = Self {
+            data,
+            file_type,
+            root_list,
+            committed_nodes: None,
+            decoded: Cell::new(0),
+        };
+        if transaction_count > 0 && !transaction_log.nil {
+            parser.committed_nodes = Some(parser.read_transaction_log(transaction_log, transaction_count)?);
+        }
+        Ok(parser)
+    }
+
+    fn chunk(&self, reference: ChunkRef) -> io::Result<&'a [u8]> {
+        let end = reference
+            .stp
+            .checked_add(reference.cb)
+            .filter(|end| *end <= self.data.len() as u64)
+            .ok_or_else(|| {
+                invalid(format!(
+                    "Chunk at offset {} with size {} lies outside the file",
+                    reference.stp, reference.cb
+                ))
+            })?;
+        Ok(&self.data[reference.stp as usize..end as usize])
+    }
+
+    /// Count `len` bytes towards the decoding budget.
+    fn charge(&self, len: usize) -> io::Result<()> {
+        let decoded = self.decoded.get().saturating_add(len);
+        if decoded > self.data.len().saturating_mul(MAX_DECODED_RATIO) {
+            return Err(invalid("Revision store references its own data too many times"));
+        }
+        self.decoded.set(decoded);
+        Ok(())
+    }
+
+    /// Returns the number of committed FileNodes for every FileNodeList that
+    /// appears in the first `count` transactions.
+    fn read_transaction_log(&self, first: ChunkRef, count: u32) -> io::Result<HashMap<u32, u32>> {
+        let mut committed = HashMap::new();
+        let mut pending = HashMap::new();
+        let mut completed = 0;
+        let mut visited = HashSet::new();
+        let mut reference = first;
+
+        while completed < count && !reference.nil {
+            if !visited.insert(reference.stp) {
+                return Err(invalid("Transaction log fragments form a cycle"));
+            }
+            let fragment = self.chunk(reference)?;
+            if fragment.len() < 12 {
+                return Err(invalid("Transaction log fragment is truncated"));
+            }
+            let mut entries = Reader::new(&fragment[..fragment.len() - 12]);
+            while entries.remaining() >= 8 && completed < count {
+                let list_id = entries.u32()?;
+                let switch = entries.u32()?;
+                if list_id == TRANSACTION_SENTINEL {
+                    committed.extend(pending.drain());
+                    completed += 1;
+                } else if list_id != 0 {
+                    pending.insert(list_id, switch);
+                }
+            }
+            reference = ChunkRef::read_64x32(&mut Reader::new(&fragment[fragment.len() - 12..]))?;
+        }
+
+        Ok(committed)
+    }
+
+    fn read_file_node_list(&self, first: ChunkRef) -> io::Result<Vec<FileNode<'a>>> {
+        let mut nodes = Vec::new();
+        let mut visited = HashSet::new();
+        let mut reference = first;
+        let mut list_id = None;
+        let mut limit = None;
+        let mut sequence = 0;
+
+        while !reference.nil {
+            if !visited.insert(reference.stp) {
+                return Err(invalid("FileNodeList fragments form a cycle"));
+            }
+            let fragment = self.chunk(reference)?;
+            self.charge(fragment.len())?;
+            if fragment.len() < 36 {
+                return Err(invalid("FileNodeList fragment is truncated"));
+            }
+            let mut header = Reader::new(fragment);
+            if header.u64()? != FILE_NODE_LIST_HEADER_MAGIC {
+                return Err(invalid(format!(
+                    "Bad FileNodeList header magic at offset {}",
+                    reference.stp
+                )));
+            }
+            let id = header.u32()?;
+            let fragment_sequence = header.u32()?;
+            match list_id {
+                None => {
+                    list_id = Some(id);
+                    limit = self
+                        .committed_nodes
+                        .as_ref()
+                        .and_then(|committed| committed.get(&id).copied());
+                }
+                Some(expected) if expected != id => {
+                    return Err(invalid("FileNodeList fragment belongs to a different list"));
+                }
+                Some(_) => {}
+            }
+            if fragment_sequence != sequence {
+                return Err(invalid("FileNodeList fragments are out of sequence"));
+            }
+            sequence += 1;
+
+            let mut trailer = Reader::new(&fragment[fragment.len() - 20..]);
+            let next = ChunkRef::read_64x32(&mut trailer)?;
+            if trailer.u64()? != FILE_NODE_LIST_FOOTER_MAGIC {
+                return Err(invalid(format!(
+                    "Bad FileNodeList footer magic at offset {}",
+                    reference.stp
+                )));
+            }
+
+            let mut body = Reader::new(&fragment[16..fragment.len() - 20]);
+            while body.remaining() >= 4 && limit.is_none_or(|limit| (nodes.len() as u32) < limit) {
+                let header = Reader::new(&body.data[body.pos..]).u32()?;
+                if header == 0 {
+                    break;
+                }
+                let id = header & 0x3FF;
+                if id == CHUNK_TERMINATOR {
+                    break;
+                }
+                let size = ((header >> 10) & 0x1FFF) as usize;
+                if size < 4 {
+                    return Err(invalid(format!("FileNode 0x{:03X} has an invalid size", id)));
+                }
+                let mut node = Reader::new(body.bytes(size)?);
+                node.pos = 4;
+                let stp_format = (header >> 23) & 0x3;
+                let cb_format = (header >> 25) & 0x3;
+                let base_type = (header >> 27) & 0xF;
+                let reference = if base_type == 1 || base_type == 2 {
+                    Some(ChunkRef::read_formatted(&mut node, stp_format, cb_format)?)
+                } else {
+                    None
+                };
+                nodes.push(FileNode { id, reference, body: node });
+            }
+
+            if limit.is_some_and(|limit| nodes.len() as u32 >= limit) {
+                break;
+            }
+            reference = next;
+        }
+
+        Ok(nodes)
+    }
+
+    fn parse(self) -> io::Result<RevisionStore> {
+        let mut store = RevisionStore {
+            file_type: self.file_type,
+            root_space: None,
+            spaces: Vec::new(),
+            file_data: HashMap::new(),
+        };
+
+        for mut node in self.read_file_node_list(self.root_list)? {
+            match node.id {
+                OBJECT_SPACE_MANIFEST_ROOT => {
+                    store.root_space = Some(node.body.extended_guid()?);
+                }
+                OBJECT_SPACE_MANIFEST_LIST_REFERENCE => {
+                    let reference = list_reference(&node)?;
+                    let id = node.body.extended_guid()?;
+                    store.spaces.push(self.parse_object_space(id, reference)?);
+                }
+                FILE_DATA_STORE_LIST_REFERENCE => {
+                    let reference = list_reference(&node)?;
+                    self.parse_file_data_store(reference, &mut store.file_data)?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(store)
+    }
+
+    /// Collects the embedded files of a FileDataStoreList. Objects with a
+    /// damaged header or footer are left out, so references to them fail to
+    /// resolve instead of returning garbage.
+    fn parse_file_data_store(&self, list: ChunkRef, file_data: &mut HashMap<Guid, Vec<u8>>) -> io::Result<()> {
+        for mut node in self.read_file_node_list(list)? {
+            if node.id != FILE_DATA_STORE_OBJECT_REFERENCE {
+                continue;
+            }
+            let Some(reference) = node.reference.filter(|reference| !reference.nil) else {
+                continue;
+            };
+            let guid = node.body.guid()?;
+            let Ok(chunk) = self.chunk(reference) else {
+                continue;
+            };
+
+            let mut reader = Reader::new(chunk);
+            if reader.guid()? != GUID_FILE_DATA_STORE_OBJECT_HEADER {
+                continue;
+            }
+            let len = reader.u64()?;
+            reader.bytes(12)?;
+            let Some(len) = usize::try_from(len).ok().filter(|len| *len <= reader.remaining()) else {
+                continue;
+            };
+            let data = reader.bytes(len)?;
+            reader.bytes((8 - len % 8) % 8).ok();
+            if reader.guid().ok() != Some(GUID_FILE_DATA_STORE_OBJECT_FOOTER) {
+                continue;
+            }
+            self.charge(len)?;
+            file_data.insert(guid, data.to_vec());
+        }
+        Ok(())
+    }
+
+    fn parse_object_space(&self, id: ExtendedGuid, list: ChunkRef) -> io::Result<ObjectSpace> {
+        let mut space = ObjectSpace {
+            id,
+            ..Default::default()
+        };
+
+        // Only the last revision manifest list of an object space is current.
+        let revision_list = self
+            .read_file_node_list(list)?
+            .iter()
+            .filter(|node| node.id == REVISION_MANIFEST_LIST_REFERENCE)
+            .map(list_reference)
+            .next_back()
+            .transpose()?;
+
+        if let Some(reference) = revision_list {
+            self.parse_revision_manifest_list(reference, &mut space)?;
+        }
+        Ok(space)
+    }
+
+    fn parse_revision_manifest_list(&self, list: ChunkRef, space: &mut ObjectSpace) -> io::Result<()> {
+        let mut revision = None::<RevisionState>;
+        let mut table = GlobalIdTable::default();
+
+        for mut node in self.read_file_node_list(list)? {
+            match node.id {
+                REVISION_MANIFEST_START4 | REVISION_MANIFEST_START6 => {
+                    revision = Some(RevisionState::default());
+                }
+                REVISION_MANIFEST_START7 => {
+                    node.body.bytes(46)?;
+                    let context = node.body.extended_guid()?;
+                    revision = Some(RevisionState {
+                        ignored: !context.is_nil(),
+                        ..Default::default()
+                    });
+                }
+                REVISION_MANIFEST_END => {
+                    if let Some(state) = revision.take().filter(|state| !state.ignored) {
+                        space.objects.extend(state.objects);
+                        space.roots.extend(state.roots);
+                    }
+                }
+                OBJECT_GROUP_LIST_REFERENCE => {
+                    let reference = list_reference(&node)?;
+                    if let Some(state) = revision.as_mut() {
+                        self.parse_object_group_list(reference, state)?;
+                    }
+                }
+                ROOT_OBJECT_REFERENCE2 => {
+                    let id = table.resolve(node.body.u32()?)?;
+                    let role = node.body.u32()?;
+                    if let Some(state) = revision.as_mut() {
+                        state.roots.insert(role, id);
+                    }
+                }
+                ROOT_OBJECT_REFERENCE3 => {
+                    let id = node.body.extended_guid()?;
+                    let role = node.body.u32()?;
+                    if let Some(state) = revision.as_mut() {
+                        state.roots.insert(role, id);
+                    }
+                }
+                _ => {
+                    if !table.apply(&mut node)? {
+                        if let Some(state) = revision.as_mut() {
+                            self.apply_declaration(&mut node, &table, state)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_object_group_list(&self, list: ChunkRef, revision: &mut RevisionState) -> io::Result<()> {
+        let mut table = GlobalIdTable::default();
+        for mut node in self.read_file_node_list(list)? {
+            if !table.apply(&mut node)? {
+                self.apply_declaration(&mut node, &table, revision)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles object declaration and revision nodes; other nodes are ignored.
+    fn apply_declaration(
+        &self,
+        node: &mut FileNode<'a>,
+        table: &GlobalIdTable,
+        revision: &mut RevisionState,
+    ) -> io::Result<()> {
+        let (id, jcid) = match node.id {
+            OBJECT_DECLARATION2_REF_COUNT
+            | OBJECT_DECLARATION2_LARGE_REF_COUNT
+            | READ_ONLY_OBJECT_DECLARATION2_REF_COUNT
+            | READ_ONLY_OBJECT_DECLARATION2_LARGE_REF_COUNT => {
+                let id = table.resolve(node.body.u32()?)?;
+                (id, node.body.u32()?)
+            }
+            OBJECT_DECLARATION_WITH_REF_COUNT | OBJECT_DECLARATION_WITH_REF_COUNT2 => {
+                let id = table.resolve(node.body.u32()?)?;
+                let jci = node.body.u16()? as u32 & 0x3FF;
+                (id, JCID_IS_PROPERTY_SET | jci)
+            }
+            OBJECT_DECLARATION_FILE_DATA3_REF_COUNT | OBJECT_DECLARATION_FILE_DATA3_LARGE_REF_COUNT => {
+                let id = table.resolve(node.body.u32()?)?;
+                let jcid = node.body.u32()?;
+                if node.id == OBJECT_DECLARATION_FILE_DATA3_REF_COUNT {
+                    node.body.u8()?;
+                } else {
+                    node.body.u32()?;
+                }
+                let reference = read_storage_string(&mut node.body)?;
+                let extension = read_storage_string(&mut node.body)?;
+                revision.objects.insert(
+                    id,
+                    Object {
+                        jcid,
+                        properties: PropertySet::default(),
+                        file_data: Some(FileDataReference { reference, extension }),
+                    },
+                );
+                return Ok(());
+            }
+            OBJECT_REVISION_WITH_REF_COUNT | OBJECT_REVISION_WITH_REF_COUNT2 => {
+                let id = table.resolve(node.body.u32()?)?;
+                match revision.objects.get(&id) {
+                    Some(existing) => (id, existing.jcid),
+                    None => return Ok(()),
+                }
+            }
+            _ => return Ok(()),
+        };
+
+        let properties = match node.reference {
+            Some(reference) if !reference.nil && jcid & JCID_IS_PROPERTY_SET != 0 => {
+                let chunk = self.chunk(reference)?;
+                self.charge(chunk.len())?;
+                self.parse_object_property_set(chunk, table)?
+            }
+            _ => PropertySet::default(),
+        };
+        revision.objects.insert(
+            id,
+            Object {
+                jcid,
+                properties,
+                file_data: None,
+            },
+        );
+        Ok(())
+    }
+
+    fn parse_object_property_set(&self, data: &[u8], table: &GlobalIdTable) -> io::Result<PropertySet> {
+        let mut reader = Reader::new(data);
+        let (object_ids, osid_stream_absent, _) = read_id_stream(&mut reader, table)?;
+        let mut object_space_ids = Vec::new();
+        let mut context_ids = Vec::new();
+        if !osid_stream_absent {
+            let (ids, _, extended_streams) = read_id_stream(&mut reader, table)?;
+            object_space_ids = ids;
+            if extended_streams {
+                context_ids = read_id_stream(&mut reader, table)?.0;
+            }
+        }
+
+        let mut streams = IdStreams {
+            object_ids: object_ids.into_iter(),
+            object_space_ids: object_space_ids.into_iter(),
+            context_ids: context_ids.into_iter(),
+        };
+        read_property_set(&mut reader, &mut streams, 0)
+    }
+}
+
+/// Reads a StringInStorageBuffer: a character count followed by UTF-16 text.
+fn read_storage_string(reader: &mut Reader) -> io::Result<String> {
+    let len = reader.u32()? as usize;
+    let bytes = reader.bytes(len.checked_mul(2).ok_or_else(|| invalid("String length overflows"))?)?;
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    Ok(String::from_utf16_lossy(&units))
+}
+
+fn list_reference(node: &FileNode) -> io::Result<ChunkRef> {
+    node.reference
+        .filter(|reference| !reference.nil)
+        .ok_or_else(|| invalid(format!("FileNode 0x{:03X} is missing its list reference", node.id)))
+}
+
+/// Maps the GUID indices of CompactIDs to full GUIDs.
+#[derive(Default)]
+struct GlobalIdTable {
+    entries: HashMap<u32, Guid>,
+    previous: HashMap<u32, Guid>,
+}
+
+impl GlobalIdTable {
+    /// Applies a global ID table node, returning `false` for any other node.
+    fn apply(&mut self, node: &mut FileNode) -> io::Result<bool> {
+        match node.id {
+            GLOBAL_ID_TABLE_START | GLOBAL_ID_TABLE_START2 => {
+                self.previous = std::mem::take(&mut self.entries);
+            }
+            GLOBAL_ID_TABLE_ENTRY => {
+                let index = node.body.u32()?;
+                let guid = node.body.guid()?;
+                self.entries.insert(index, guid);
+            }
+            GLOBAL_ID_TABLE_ENTRY2 => {
+                let from = node.body.u32()?;
+                let to = node.body.u32()?;
+                if let Some(guid) = self.previous.get(&from) {
+                    self.entries.insert(to, *guid);
+                }
+            }
+            GLOBAL_ID_TABLE_ENTRY3 => {
+                let from = node.body.u32()?;
+                let count = node.body.u32()?;
+                let to = node.body.u32()?;
+                for offset in 0..count {
+                    let guid = from
+                        .checked_add(offset)
+                        .and_then(|index| self.previous.get(&index))
+                        .ok_or_else(|| invalid("Global ID table copies a missing entry"))?;
+                    let index = to
+                        .checked_add(offset)
+                        .ok_or_else(|| invalid("Global ID table index overflows"))?;
+                    self.entries.insert(index, *guid);
+                }
+            }
+            GLOBAL_ID_TABLE_END => {}
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn resolve(&self, compact_id: u32) -> io::Result<ExtendedGuid> {
+        if compact_id == 0 {
+            return Ok(ExtendedGuid::default());
+        }
+        let index = compact_id >> 8;
+        let guid = self
+            .entries
+            .get(&index)
+            .ok_or_else(|| invalid(format!("CompactID refers to unknown GUID index {}", index)))?;
+        Ok(ExtendedGuid::new(*guid, compact_id & 0xFF))
+    }
+}
+
+/// Reads an ObjectSpaceObjectStreamHeader and its CompactIDs, returning the
+/// resolved IDs plus the OsidStreamNotPresent and ExtendedStreamsPresent bits.
+fn read_id_stream(reader: &mut Reader, table: &GlobalIdTable) -> io::Result<(Vec<ExtendedGuid>, bool, bool)> {
+    let header = reader.u32()?;
+    let count = (header & 0x00FF_FFFF) as usize;
+    if count * 4 > reader.remaining() {
+        return Err(invalid("Object ID stream is longer than its property set"));
+    }
+    let ids = (0..count)
+        .map(|_| reader.u32().and_then(|id| table.resolve(id)))
+        .collect::<io::Result<Vec<_>>>()?;
+    Ok((ids, header & 0x8000_0000 != 0, header & 0x4000_0000 != 0))
+}
+
+struct IdStreams {
+    object_ids: std::vec::IntoIter<ExtendedGuid>,
+    object_space_ids: std::vec::IntoIter<ExtendedGuid>,
+    context_ids: std::vec::IntoIter<ExtendedGuid>,
+}
+
+fn take_ids(stream: &mut std::vec::IntoIter<ExtendedGuid>, count: usize) -> io::Result<Vec<ExtendedGuid>> {
+    if count > stream.len() {
+        return Err(invalid("Property references more IDs than its stream contains"));
+    }
+    Ok(stream.by_ref().take(count).collect())
+}
+
+fn read_property_set(reader: &mut Reader, streams: &mut IdStreams, depth: usize) -> io::Result<PropertySet> {
+    if depth > MAX_PROPERTY_SET_DEPTH {
+        return Err(invalid("Property sets are nested too deeply"));
+    }
+    let count = reader.u16()? as usize;
+    let ids = (0..count)
+        .map(|_| reader.u32())
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut properties = Vec::with_capacity(count);
+    for raw_id in ids {
+        let value = match (raw_id >> 26) & 0x1F {
+            PROPERTY_TYPE_NO_DATA => PropertyValue::NoData,
+            PROPERTY_TYPE_BOOL => PropertyValue::Bool(raw_id & 0x8000_0000 != 0),
+            PROPERTY_TYPE_ONE_BYTE => PropertyValue::U8(reader.u8()?),
+            PROPERTY_TYPE_TWO_BYTES => PropertyValue::U16(reader.u16()?),
+            PROPERTY_TYPE_FOUR_BYTES => PropertyValue::U32(reader.u32()?),
+            PROPERTY_TYPE_EIGHT_BYTES => PropertyValue::U64(reader.u64()?),
+            PROPERTY_TYPE_LENGTH_PREFIXED => {
+                let len = reader.u32()? as usize;
+                PropertyValue::Bytes(reader.bytes(len)?.to_vec())
+            }
+            PROPERTY_TYPE_OBJECT_ID => PropertyValue::ObjectIds(take_ids(&mut streams.object_ids, 1)?),
+            PROPERTY_TYPE_OBJECT_IDS => {
+                let count = reader.u32()? as usize;
+                PropertyValue::ObjectIds(take_ids(&mut streams.object_ids, count)?)
+            }
+            PROPERTY_TYPE_OBJECT_SPACE_ID => {
+                PropertyValue::ObjectSpaceIds(take_ids(&mut streams.object_space_ids, 1)?)
+            }
+            PROPERTY_TYPE_OBJECT_SPACE_IDS => {
+                let count = reader.u32()? as usize;
+                PropertyValue::ObjectSpaceIds(take_ids(&mut streams.object_space_ids, count)?)
+            }
+            PROPERTY_TYPE_CONTEXT_ID => PropertyValue::ContextIds(take_ids(&mut streams.context_ids, 1)?),
+            PROPERTY_TYPE_CONTEXT_IDS => {
+                let count = reader.u32()? as usize;
+                PropertyValue::ContextIds(take_ids(&mut streams.context_ids, count)?)
+            }
+            PROPERTY_TYPE_PROPERTY_VALUES => {
+                let count = reader.u32()? as usize;
+                let mut sets = Vec::new();
+                if count > 0 {
+                    // Every element shares one PropertyID naming the set type.
+                    reader.u32()?;
+                    for _ in 0..count {
+                        sets.push(read_property_set(reader, streams, depth + 1)?);
+                    }
+                }
+                PropertyValue::PropertySets(sets)
+            }
+            PROPERTY_TYPE_PROPERTY_SET => {
+                PropertyValue::PropertySet(read_property_set(reader, streams, depth + 1)?)
+            }
+            other => {
+                return Err(invalid(format!(
+                    "Property 0x{:08X} has unknown type 0x{:X}",
+                    raw_id, other
+                )))
+            }
+        };
+        properties.push(Property {
+            id: raw_id & 0x7FFF_FFFF,
+            value,
+        });
+    }
+
+    Ok(PropertySet { properties })
+}
+
+/// Writes minimal revision stores for tests and checked-in fixtures.
+#[cfg(test)]
+pub(crate) mod test_builder {
+    use super::*;
+
+    pub struct StoreBuilder {
+        buf: Vec<u8>,
+        file_type: Guid,
+        next_list_id: u32,
+        transactions: Vec<(u32, u32)>,
+    }
+
+    impl StoreBuilder {
+        pub fn new(file_type: Guid) -> Self {
+            Self {
+                buf: vec![0; HEADER_SIZE],
+                file_type,
+                next_list_id: 0x10,
+                transactions: Vec::new(),
+            }
+        }
+
+        /// Appends raw bytes and returns their (offset, size).
+        pub fn chunk(&mut self, bytes: &[u8]) -> (u64, u64) {
+            while !self.buf.len().is_multiple_of(8) {
+                self.buf.push(0);
+            }
+            let stp = self.buf.len() as u64;
+            self.buf.extend_from_slice(bytes);
+            (stp, bytes.len() as u64)
+        }
+
+        /// Writes a single-fragment FileNodeList and commits all of its nodes.
+        pub fn list(&mut self, nodes: &[Vec<u8>]) -> (u64, u64) {
+            self.list_with_committed(nodes, nodes.len())
+        }
+
+        /// Writes a FileNodeList in which only the first `committed` nodes
+        /// belong to a completed transaction.
+        pub fn list_with_committed(&mut self, nodes: &[Vec<u8>], committed: usize) -> (u64, u64) {
+            let id = self.next_list_id;
+            self.next_list_id += 1;
+            let mut fragment = Vec::new();
+            fragment.extend_from_slice(&FILE_NODE_LIST_HEADER_MAGIC.to_le_bytes());
+            fragment.extend_from_slice(&id.to_le_bytes());
+            fragment.extend_from_slice(&0u32.to_le_bytes());
+            for node in nodes {
+                fragment.extend_from_slice(node);
+            }
+            fragment.extend_from_slice(&u64::MAX.to_le_bytes());
+            fragment.extend_from_slice(&0u32.to_le_bytes());
+            fragment.extend_from_slice(&FILE_NODE_LIST_FOOTER_MAGIC.to_le_bytes());
+            self.transactions.push((id, committed as u32));
+            self.chunk(&fragment)
+        }
+
+        pub fn finish(mut self, root: (u64, u64)) -> Vec<u8> {
+            let mut log = Vec::new();
+            for (id, count) in &self.transactions {
+                log.extend_from_slice(&id.to_le_bytes());
+                log.extend_from_slice(&count.to_le_bytes());
+            }
+            log.extend_from_slice(&TRANSACTION_SENTINEL.to_le_bytes());
+            log.extend_from_slice(&0u32.to_le_bytes());
+            log.extend_from_slice(&u64::MAX.to_le_bytes());
+            log.extend_from_slice(&0u32.to_le_bytes());
+            let log_ref = self.chunk(&log);
+
+            let file_len = self.buf.len() as u64;
+            let header = &mut self.buf[..HEADER_SIZE];
+            header[0..16].copy_from_slice(&self.file_type.0);
+            header[48..64].copy_from_slice(&GUID_FILE_FORMAT.0);
+            for offset in [64, 68, 72, 76] {
+                header[offset..offset + 4].copy_from_slice(&0x2Au32.to_le_bytes());
+            }
+            header[96..100].copy_from_slice(&1u32.to_le_bytes());
+            header[160..168].copy_from_slice(&log_ref.0.to_le_bytes());
+            header[168..172].copy_from_slice(&(log_ref.1 as u32).to_le_bytes());
+            header[172..180].copy_from_slice(&root.0.to_le_bytes());
+            header[180..184].copy_from_slice(&(root.1 as u32).to_le_bytes());
+            header[184..192].copy_from_slice(&u64::MAX.to_le_bytes());
+            header[196..204].copy_from_slice(&file_len.to_le_bytes());
+            self.buf
+        }
+    }
+
+    /// Encodes a FileNode with an optional 8-byte offset / 4-byte size reference.
+    pub fn node(id: u32, reference: Option<((u64, u64), u32)>, body: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let base_type = match reference {
+            Some(((stp, cb), base_type)) => {
+                data.extend_from_slice(&stp.to_le_bytes());
+                data.extend_from_slice(&(cb as u32).to_le_bytes());
+                base_type
+            }
+            None => 0,
+        };
+        data.extend_from_slice(body);
+        let size = data.len() as u32 + 4;
+        let header = id | (size << 10) | (base_type << 27);
+        let mut bytes = header.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&data);
+        bytes
+    }
+
+    pub fn extended_guid(id: &ExtendedGuid) -> Vec<u8> {
+        let mut bytes = id.guid.0.to_vec();
+        bytes.extend_from_slice(&id.n.to_le_bytes());
+        bytes
+    }
+
+    /// Builds an ObjectSpaceObjectPropSet with object and object space IDs
+    /// given as CompactIDs.
+    #[derive(Default)]
+    pub struct PropSetBuilder {
+        ids: Vec<u32>,
+        data: Vec<u8>,
+        object_ids: Vec<u32>,
+        object_space_ids: Vec<u32>,
+    }
+
+    impl PropSetBuilder {
+        pub fn bytes(mut self, id: u32, value: &[u8]) -> Self {
+            self.ids.push(id);
+            self.data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            self.data.extend_from_slice(value);
+            self
+        }
+
+        pub fn utf16(self, id: u32, text: &str) -> Self {
+            let bytes: Vec<u8> = text.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect();
+            self.bytes(id, &bytes)
+        }
+
+        pub fn u8(mut self, id: u32, value: u8) -> Self {
+            self.ids.push(id);
+            self.data.push(value);
+            self
+        }
+
+        pub fn u32(mut self, id: u32, value: u32) -> Self {
+            self.ids.push(id);
+            self.data.extend_from_slice(&value.to_le_bytes());
+            self
+        }
+
+        pub fn u64(mut self, id: u32, value: u64) -> Self {
+            self.ids.push(id);
+            self.data.extend_from_slice(&value.to_le_bytes());
+            self
+        }
+
+        pub fn object_id(mut self, id: u32, compact_id: u32) -> Self {
+            self.ids.push(id);
+            self.object_ids.push(compact_id);
+            self
+        }
+
+        pub fn object_ids(mut self, id: u32, compact_ids: &[u32]) -> Self {
+            self.ids.push(id);
+            self.data.extend_from_slice(&(compact_ids.len() as u32).to_le_bytes());
+            self.object_ids.extend_from_slice(compact_ids);
+            self
+        }
+
+        pub fn object_space_ids(mut self, id: u32, compact_ids: &[u32]) -> Self {
+            self.ids.push(id);
+            self.data.extend_from_slice(&(compact_ids.len() as u32).to_le_bytes());
+            self.object_space_ids.extend_from_slice(compact_ids);
+            self
+        }
+
+        pub fn build(self) -> Vec<u8> {
+            let mut bytes = Vec::new();
+            let mut oid_header = self.object_ids.len() as u32;
+            if self.object_space_ids.is_empty() {
+                oid_header |= 0x8000_0000;
+            }
+            bytes.extend_from_slice(&oid_header.to_le_bytes());
+            for id in &self.object_ids {
+                bytes.extend_from_slice(&id.to_le_bytes());
+            }
+            if !self.object_space_ids.is_empty() {
+                bytes.extend_from_slice(&(self.object_space_ids.len() as u32).to_le_bytes());
+                for id in &self.object_space_ids {
+                    bytes.extend_from_slice(&id.to_le_bytes());
+                }
+            }
+            bytes.extend_from_slice(&(self.ids.len() as u16).to_le_bytes());
+            for id in &self.ids {
+                bytes.extend_from_slice(&id.to_le_bytes());
+            }
+            bytes.extend_from_slice(&self.data);
+            bytes
+        }
+    }
+
+    /// An object to declare inside an object space: (CompactID, JCID, property set).
+    pub struct TestObject {
+        pub compact_id: u32,
+        pub jcid: u32,
+        pub properties: Vec<u8>,
+    }
+
+    /// A file data object: (CompactID, JCID, file data reference, extension).
+    pub type TestFileObject<'a> = (u32, u32, &'a str, &'a str);
+
+    /// Writes one object space holding a single revision with one object group.
+    /// `guids` is the global ID table; `roots` maps root roles to CompactIDs.
+    pub fn object_space(
+        builder: &mut StoreBuilder,
+        id: &ExtendedGuid,
+        guids: &[Guid],
+        objects: &[TestObject],
+        roots: &[(u32, u32)],
+    ) -> Vec<u8> {
+        object_space_with_files(builder, id, guids, objects, &[], roots)
+    }
+
+    pub fn object_space_with_files(
+        builder: &mut StoreBuilder,
+        id: &ExtendedGuid,
+        guids: &[Guid],
+        objects: &[TestObject],
+        files: &[TestFileObject],
+        roots: &[(u32, u32)],
+    ) -> Vec<u8> {
+        let mut group = vec![node(0x0B4, None, &extended_guid(&ExtendedGuid::new(id.guid, 100)))];
+        group.push(node(GLOBAL_ID_TABLE_START2, None, &[]));
+        for (index, guid) in guids.iter().enumerate() {
+            let mut body = (index as u32).to_le_bytes().to_vec();
+            body.extend_from_slice(&guid.0);
+            group.push(node(GLOBAL_ID_TABLE_ENTRY, None, &body));
+        }
+        group.push(node(GLOBAL_ID_TABLE_END, None, &[]));
+        for object in objects {
+            let props = builder.chunk(&object.properties);
+            let mut body = object.compact_id.to_le_bytes().to_vec();
+            body.extend_from_slice(&object.jcid.to_le_bytes());
+            body.push(0);
+            body.push(1);
+            group.push(node(OBJECT_DECLARATION2_REF_COUNT, Some((props, 1)), &body));
+        }
+        for (compact_id, jcid, reference, extension) in files {
+            let mut body = compact_id.to_le_bytes().to_vec();
+            body.extend_from_slice(&jcid.to_le_bytes());
+            body.push(1);
+            for text in [reference, extension] {
+                body.extend_from_slice(&(text.encode_utf16().count() as u32).to_le_bytes());
+                body.extend(text.encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+            }
+            group.push(node(OBJECT_DECLARATION_FILE_DATA3_REF_COUNT, None, &body));
+        }
+        group.push(node(0x0B8, None, &[]));
+        let group_list = builder.list(&group);
+
+        let revision_id = ExtendedGuid::new(id.guid, 1);
+        let mut start = extended_guid(&revision_id);
+        start.extend_from_slice(&[0; 20]);
+        start.extend_from_slice(&1u32.to_le_bytes());
+        start.extend_from_slice(&0u16.to_le_bytes());
+        let mut revisions = vec![
+            node(0x014, None, &[extended_guid(id), 0u32.to_le_bytes().to_vec()].concat()),
+            node(REVISION_MANIFEST_START6, None, &start),
+            node(
+                OBJECT_GROUP_LIST_REFERENCE,
+                Some((group_list, 2)),
+                &extended_guid(&ExtendedGuid::new(id.guid, 100)),
+            ),
+        ];
+        for (role, compact_id) in roots {
+            let object = ExtendedGuid::new(guids[(*compact_id >> 8) as usize], compact_id & 0xFF);
+            let mut body = extended_guid(&object);
+            body.extend_from_slice(&role.to_le_bytes());
+            revisions.push(node(ROOT_OBJECT_REFERENCE3, None, &body));
+        }
+        revisions.push(node(REVISION_MANIFEST_END, None, &[]));
+        let revision_list = builder.list(&revisions);
+
+        let manifest_list = builder.list(&[
+            node(0x00C, None, &extended_guid(id)),
+            node(REVISION_MANIFEST_LIST_REFERENCE, Some((revision_list, 2)), &[]),
+        ]);
+        node(OBJECT_SPACE_MANIFEST_LIST_REFERENCE, Some((manifest_list, 2)), &extended_guid(id))
+    }
+
+    /// Writes a FileDataStoreList holding `files` and returns the root list
+    /// node that references it.
+    pub fn file_data_store(builder: &mut StoreBuilder, files: &[(Guid, &[u8])]) -> Vec<u8> {
+        let mut nodes = Vec::new();
+        for (guid, data) in files {
+            let mut object = GUID_FILE_DATA_STORE_OBJECT_HEADER.0.to_vec();
+            object.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            object.extend_from_slice(&[0; 12]);
+            object.extend_from_slice(data);
+            object.resize(object.len() + (8 - data.len() % 8) % 8, 0);
+            object.extend_from_slice(&GUID_FILE_DATA_STORE_OBJECT_FOOTER.0);
+            let chunk = builder.chunk(&object);
+            nodes.push(node(FILE_DATA_STORE_OBJECT_REFERENCE, Some((chunk, 1)), &guid.0));
+        }
+        let list = builder.list(&nodes);
+        node(FILE_DATA_STORE_LIST_REFERENCE, Some((list, 2)), &[])
+    }
+
+    /// Writes one object space the way .onetoc2 files do: a single revision
+    /// declaring its global ID table and objects directly, without object groups.
+    pub fn toc_object_space(
+        builder: &mut StoreBuilder,
+        id: &ExtendedGuid,
+        guids: &[Guid],
+        objects: &[TestObject],
+        roots: &[(u32, u32)],
+    ) -> Vec<u8> {
+        let mut start = extended_guid(&ExtendedGuid::new(id.guid, 1));
+        start.extend_from_slice(&[0; 28]);
+        start.extend_from_slice(&1u32.to_le_bytes());
+        start.extend_from_slice(&0u16.to_le_bytes());
+        let mut revisions = vec![
+            node(0x014, None, &[extended_guid(id), 0u32.to_le_bytes().to_vec()].concat()),
+            node(REVISION_MANIFEST_START4, None, &start),
+            node(GLOBAL_ID_TABLE_START, None, &[0]),
+        ];
+        for (index, guid) in guids.iter().enumerate() {
+            let mut body = (index as u32).to_le_bytes().to_vec();
+            body.extend_from_slice(&guid.0);
+            revisions.push(node(GLOBAL_ID_TABLE_ENTRY, None, &body));
+        }
+        revisions.push(node(GLOBAL_ID_TABLE_END, None, &[]));
+        for object in objects {
+            let props = builder.chunk(&object.properties);
+            let mut body = object.compact_id.to_le_bytes().to_vec();
+            let jci = (object.jcid & 0x3FF) as u64;
+            body.extend_from_slice(&(jci | 1 << 16).to_le_bytes()[..6]);
+            body.push(1);
+            revisions.push(node(OBJECT_DECLARATION_WITH_REF_COUNT, Some((props, 1)), &body));
+        }
+        for (role, compact_id) in roots {
+            let mut body = compact_id.to_le_bytes().to_vec();
+            body.extend_from_slice(&role.to_le_bytes());
+            revisions.push(node(ROOT_OBJECT_REFERENCE2, None, &body));
+        }
+        revisions.push(node(REVISION_MANIFEST_END, None, &[]));
+        let revision_list = builder.list(&revisions);
+
+        let manifest_list = builder.list(&[
+            node(0x00C, None, &extended_guid(id)),
+            node(REVISION_MANIFEST_LIST_REFERENCE, Some((revision_list, 2)), &[]),
+        ]);
+        node(OBJECT_SPACE_MANIFEST_LIST_REFERENCE, Some((manifest_list, 2)), &extended_guid(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_builder::*;
+    use super::*;
+
+    const SPACE_GUID: Guid = Guid::new(0x11111111, 0x2222, 0x3333, [4, 4, 5, 5, 6, 6, 7, 7]);
+
+    fn single_space_store(objects: &[TestObject], roots: &[(u32, u32)]) -> Vec<u8> {
+        let mut builder = StoreBuilder::new(GUID_FILE_TYPE_ONE);
+        let space = ExtendedGuid::new(SPACE_GUID, 1);
+        let reference = object_space(&mut builder, &space, &[SPACE_GUID], objects, roots);
+        let root = builder.list(&[node(OBJECT_SPACE_MANIFEST_ROOT, None, &extended_guid(&space)), reference]);
+        builder.finish(root)
+    }
+
+    #[test]
+    fn test_parse_object_space_with_properties() {
+        let properties = PropSetBuilder::default()
+            .utf16(0x1C001C22, "Hello")
+            .u32(0x14001D7A, 42)
+            .object_ids(0x24001C20, &[0x02])
+            .build();
+        let data = single_space_store(
+            &[
+                TestObject { compact_id: 0x01, jcid: 0x0006000B, properties },
+                TestObject { compact_id: 0x02, jcid: 0x0006000C, properties: PropSetBuilder::default().build() },
+            ],
+            &[(1, 0x01)],
+        );
+
+        let store = RevisionStore::parse(&data).unwrap();
+        assert_eq!(store.file_type, FileType::Section);
+        assert_eq!(store.root_space, Some(ExtendedGuid::new(SPACE_GUID, 1)));
+        assert_eq!(store.spaces.len(), 1);
+
+        let root = store.spaces[0].root(1).unwrap();
+        assert_eq!(root.jcid, 0x0006000B);
+        assert_eq!(root.properties.u32(0x14001D7A), Some(42));
+        assert_eq!(root.properties.bytes(0x1C001C22).unwrap().len(), 10);
+        let children = root.properties.object_ids(0x24001C20);
+        assert_eq!(children, &[ExtendedGuid::new(SPACE_GUID, 2)]);
+        assert_eq!(store.spaces[0].object(&children[0]).unwrap().jcid, 0x0006000C);
+    }
+
+    #[test]
+    fn test_uncommitted_nodes_are_ignored() {
+        let mut builder = StoreBuilder::new(GUID_FILE_TYPE_ONE);
+        let space = ExtendedGuid::new(SPACE_GUID, 1);
+        let reference = object_space(&mut builder, &space, &[SPACE_GUID], &[], &[]);
+        let root = builder.list_with_committed(
+            &[
+                node(OBJECT_SPACE_MANIFEST_ROOT, None, &extended_guid(&space)),
+                reference.clone(),
+                reference,
+            ],
+            2,
+        );
+        let store = RevisionStore::parse(&builder.finish(root)).unwrap();
+        assert_eq!(store.spaces.len(), 1);
+    }
+
+    #[test]
+    fn test_rejects_repeatedly_referenced_chunks() {
+        let properties = PropSetBuilder::default().bytes(0x1C001C22, &[0x41; 16 * 1024]).build();
+        let mut builder = StoreBuilder::new(GUID_FILE_TYPE_ONE);
+        let space = ExtendedGuid::new(SPACE_GUID, 1);
+        let reference = object_space(
+            &mut builder,
+            &space,
+            &[SPACE_GUID],
+            &[TestObject { compact_id: 0x01, jcid: 0x0006000B, properties }],
+            &[(1, 0x01)],
+        );
+        let mut nodes = vec![node(OBJECT_SPACE_MANIFEST_ROOT, None, &extended_guid(&space))];
+        nodes.extend(std::iter::repeat_n(reference, 8));
+        let root = builder.list(&nodes);
+
+        let error = RevisionStore::parse(&builder.finish(root)).err().unwrap();
+        assert!(error.to_string().contains("too many times"), "{}", error);
+    }
+
+    #[test]
+    fn test_rejects_non_onestore_data() {
+        assert!(RevisionStore::parse(b"Fake OneNote content").is_err());
+        assert!(RevisionStore::parse(&[0u8; 2048]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_out_of_bounds_references() {
+        let mut data = single_space_store(&[], &[]);
+        data[172..180].copy_from_slice(&(u64::MAX - 4).to_le_bytes());
+        assert!(RevisionStore::parse(&data).is_err());
+    }
+
+    #[test]
+    fn test_resolve_file_data() {
+        let stored = Guid::new(0xF11E0001, 0x0001, 0x4000, [0x80, 0, 0, 0, 0, 0, 0, 9]);
+        let mut builder = StoreBuilder::new(GUID_FILE_TYPE_ONE);
+        let space = ExtendedGuid::new(SPACE_GUID, 1);
+        let data_store = file_data_store(&mut builder, &[(stored, b"picture bytes")]);
+        let reference = object_space_with_files(
+            &mut builder,
+            &space,
+            &[SPACE_GUID],
+            &[],
+            &[(0x05, 0x00080039, &format!("<ifndf>{}", stored), ".png")],
+            &[],
+        );
+        let root = builder.list(&[node(OBJECT_SPACE_MANIFEST_ROOT, None, &extended_guid(&space)), reference, data_store]);
+        let store = RevisionStore::parse(&builder.finish(root)).unwrap();
+
+        let object = store.spaces[0].object(&ExtendedGuid::new(SPACE_GUID, 5)).unwrap();
+        let file_data = object.file_data.as_ref().unwrap();
+        assert_eq!(file_data.extension, ".png");
+        assert_eq!(store.resolve_file_data(file_data).unwrap(), b"picture bytes");
+
+        let external = FileDataReference {
+            reference: "<file>{ABC}.png".to_string(),
+            extension: ".png".to_string(),
+        };
+        assert!(store.resolve_file_data(&external).unwrap_err().contains("outside"));
+        let missing = FileDataReference {
+            reference: format!("<ifndf>{}", SPACE_GUID),
+            extension: String::new(),
+        };
+        assert!(store.resolve_file_data(&missing).unwrap_err().contains("missing"));
+    }
+
+    #[test]
+    fn test_guid_display() {
+        assert_eq!(
+            GUID_FILE_TYPE_ONE.to_string(),
+            "{7B5C52E4-D88C-4DA7-AEB1-5378D02996D3}"
+        );
+    }
+}
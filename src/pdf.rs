@@ -0,0 +1,386 @@
+use crate::drawing::Drawing;
+use crate::file_io;
+use crate::notebook::{Notebook, Page};
+use crate::{PAGE_MAX_CHARS_PER_LINE, PAGE_MAX_LINES};
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// US Letter in points, with 1" margins
+const SHEET_WIDTH: f32 = 612.0;
+const SHEET_HEIGHT: f32 = 792.0;
+const MARGIN: f32 = 72.0;
+const TEXT_WIDTH: f32 = SHEET_WIDTH - 2.0 * MARGIN;
+const TEXT_HEIGHT: f32 = SHEET_HEIGHT - 2.0 * MARGIN;
+
+// Courier characters are 0.6 em wide; the text area holds exactly the
+// lines and characters the editor allows on a page
+const FONT_SIZE: f32 = TEXT_WIDTH / (PAGE_MAX_CHARS_PER_LINE as f32 * 0.6);
+const LINE_HEIGHT: f32 = TEXT_HEIGHT / PAGE_MAX_LINES as f32;
+
+// Drawings are in pixels at 96 DPI
+const POINTS_PER_PIXEL: f32 = 0.75;
+
+const TAB_WIDTH: usize = 4;
+
+/// Something placed in the text area of a sheet, at a line index.
+enum Block<'a> {
+    Text(String),
+    Drawing(&'a Drawing, f32),
+}
+
+/// Write a notebook as a PDF, each page starting on a new US Letter sheet
+/// with 1" margins and continuing on more sheets if needed. Text is set in
+/// Courier, one of the fonts every PDF reader has, so nothing is embedded;
+/// characters outside Windows-1252 show as `?`. Each sheet has the page
+/// title at the top and the page number and modification time at the
+/// bottom. All pages must be loaded. Returns the path of the file.
+pub fn export_notebook(notebook: &Notebook, output: &Path) -> io::Result<PathBuf> {
+    file_io::require_loaded(notebook)?;
+    fs::write(output, notebook_pdf(notebook))?;
+    Ok(output.to_path_buf())
+}
+
+fn notebook_pdf(notebook: &Notebook) -> Vec<u8> {
+    let mut sheets = Vec::new();
+    for (index, page) in notebook.pages.iter().enumerate() {
+        sheets.extend(page_sheets(page, page.number.map_or(index + 1, |number| number as usize)));
+    }
+    // Readers expect at least one sheet
+    if sheets.is_empty() {
+        sheets.push(String::new());
+    }
+    write_pdf(&notebook.title, &sheets)
+}
+
+/// The content streams of the sheets one page is laid out on.
+fn page_sheets<'a>(page: &'a Page, number: usize) -> Vec<String> {
+    let mut sheets: Vec<Vec<(usize, Block<'a>)>> = vec![Vec::new()];
+    let mut line = 0;
+    let mut place = |block: Block<'a>, lines: usize| {
+        if line > 0 && line + lines > PAGE_MAX_LINES {
+            sheets.push(Vec::new());
+            line = 0;
+        }
+        sheets.last_mut().unwrap().push((line, block));
+        line += lines;
+    };
+
+    let attachments = page.attachments.iter().map(|attachment| format!("Attachment: {}", attachment.name));
+    for text in wrap_text(&page.content).into_iter().chain(attachments) {
+        place(Block::Text(text), 1);
+    }
+    for drawing in &page.drawings {
+        // Shrunk to fit a sheet's text area
+        let scale = POINTS_PER_PIXEL
+            .min(TEXT_WIDTH / drawing.width.max(1.0))
+            .min(TEXT_HEIGHT / drawing.height.max(1.0));
+        let lines = ((drawing.height * scale / LINE_HEIGHT).ceil() as usize).clamp(1, PAGE_MAX_LINES);
+        place(Block::Drawing(drawing, scale), lines);
+    }
+
+    let title = if page.title.is_empty() { "Untitled" } else { &page.title };
+    let count = sheets.len();
+    sheets
+        .iter()
+        .enumerate()
+        .map(|(index, blocks)| {
+            let mut stream = String::new();
+            // Header: the title above a rule
+            text_at(&mut stream, "F2", MARGIN, SHEET_HEIGHT - MARGIN + 24.0, &truncate(title, PAGE_MAX_CHARS_PER_LINE));
+            rule_at(&mut stream, SHEET_HEIGHT - MARGIN + 12.0);
+
+            for (line, block) in blocks {
+                let top = SHEET_HEIGHT - MARGIN - *line as f32 * LINE_HEIGHT;
+                match block {
+                    Block::Text(text) => text_at(&mut stream, "F1", MARGIN, top - FONT_SIZE, text),
+                    Block::Drawing(drawing, scale) => draw_at(&mut stream, drawing, *scale, top),
+                }
+            }
+
+            // Footer: the page number and modification time below a rule
+            rule_at(&mut stream, MARGIN - 12.0);
+            let mut footer = format!("Page {}", number);
+            if count > 1 {
+                footer.push_str(&format!(" ({} of {})", index + 1, count));
+            }
+            text_at(&mut stream, "F1", MARGIN, MARGIN - 30.0, &footer);
+            let modified = format!("Modified {}", page.modified.format("%Y-%m-%d %H:%M UTC"));
+            let width = modified.chars().count() as f32 * FONT_SIZE * 0.6;
+            text_at(&mut stream, "F1", SHEET_WIDTH - MARGIN - width, MARGIN - 30.0, &modified);
+            stream
+        })
+        .collect()
+}
+
+/// Break text into lines that fit the text area, at the last space that
+/// fits where there is one. Tabs become spaces.
+fn wrap_text(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let mut chars = Vec::new();
+        for c in line.chars() {
+            if c == '\t' {
+                chars.extend(std::iter::repeat_n(' ', TAB_WIDTH - chars.len() % TAB_WIDTH));
+            } else {
+                chars.push(c);
+            }
+        }
+        let mut rest = &chars[..];
+        while rest.len() > PAGE_MAX_CHARS_PER_LINE {
+            match rest[..=PAGE_MAX_CHARS_PER_LINE].iter().rposition(|&c| c == ' ').filter(|&space| space > 0) {
+                Some(space) => {
+                    lines.push(rest[..space].iter().collect());
+                    rest = &rest[space + 1..];
+                }
+                None => {
+                    lines.push(rest[..PAGE_MAX_CHARS_PER_LINE].iter().collect());
+                    rest = &rest[PAGE_MAX_CHARS_PER_LINE..];
+                }
+            }
+        }
+        lines.push(rest.iter().collect());
+    }
+    lines
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        text.chars().take(max_chars - 3).chain("...".chars()).collect()
+    }
+}
+
+fn text_at(stream: &mut String, font: &str, x: f32, y: f32, text: &str) {
+    stream.push_str(&format!(
+        "BT /{} {} Tf {} {} Td ({}) Tj ET\n",
+        font,
+        number(FONT_SIZE),
+        number(x),
+        number(y),
+        pdf_string(text)
+    ));
+}
+
+fn rule_at(stream: &mut String, y: f32) {
+    stream.push_str(&format!(
+        "0.5 w 0.8 G {} {} m {} {} l S\n",
+        number(MARGIN),
+        number(y),
+        number(SHEET_WIDTH - MARGIN),
+        number(y)
+    ));
+}
+
+/// Draw a drawing's strokes with its top-left corner at the left margin
+/// and `top`.
+fn draw_at(stream: &mut String, drawing: &Drawing, scale: f32, top: f32) {
+    stream.push_str("q 1 J 1 j\n");
+    for stroke in &drawing.strokes {
+        let Some((first, rest)) = stroke.points.split_first() else {
+            continue;
+        };
+        let [r, g, b, _] = stroke.color;
+        stream.push_str(&format!(
+            "{} {} {} RG {} w",
+            number(r as f32 / 255.0),
+            number(g as f32 / 255.0),
+            number(b as f32 / 255.0),
+            number(stroke.width * scale)
+        ));
+        let point = |[x, y]: [f32; 2]| format!("{} {}", number(MARGIN + x * scale), number(top - y * scale));
+        stream.push_str(&format!(" {} m", point(*first)));
+        // A single point is drawn as a dot by the round line cap
+        for next in if rest.is_empty() { std::slice::from_ref(first) } else { rest } {
+            stream.push_str(&format!(" {} l", point(*next)));
+        }
+        stream.push_str(" S\n");
+    }
+    stream.push_str("Q\n");
+}
+
+/// A number with at most two decimals and no trailing zeros.
+fn number(value: f32) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" { "0".to_string() } else { text.to_string() }
+}
+
+/// A literal string in the fonts' Windows-1252 encoding, with other
+/// characters replaced by `?`.
+fn pdf_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            _ => match windows_1252(c) {
+                Some(byte) => escaped.push_str(&format!("\\{:03o}", byte)),
+                None => escaped.push('?'),
+            },
+        }
+    }
+    escaped
+}
+
+fn windows_1252(c: char) -> Option<u8> {
+    const HIGH: [char; 32] = [
+        '€', '\0', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\0', 'Ž', '\0', '\0', '‘', '’', '“', '”', '•',
+        '–', '—', '˜', '™', 'š', '›', 'œ', '\0', 'ž', 'Ÿ',
+    ];
+    match c as u32 {
+        0xA0..=0xFF => Some(c as u8),
+        _ => HIGH.iter().position(|&high| high == c && c != '\0').map(|index| 0x80 + index as u8),
+    }
+}
+
+/// A text string outside content streams, such as the document title, in
+/// UTF-16 so any title is kept.
+fn pdf_text_string(text: &str) -> String {
+    let mut hex = String::from("<FEFF");
+    for unit in text.encode_utf16() {
+        hex.push_str(&format!("{:04X}", unit));
+    }
+    hex.push('>');
+    hex
+}
+
+/// Assemble the PDF file from the content stream of each sheet.
+fn write_pdf(title: &str, sheets: &[String]) -> Vec<u8> {
+    // Objects 1 to 5 are fixed; each sheet adds a page and its contents
+    let kids: Vec<String> = (0..sheets.len()).map(|index| format!("{} 0 R", 6 + 2 * index)).collect();
+    let font = |name: &str| format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>", name);
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), sheets.len()).into_bytes(),
+        font("Courier").into_bytes(),
+        font("Courier-Bold").into_bytes(),
+        format!("<< /Title {} /Producer (NotebookX) >>", pdf_text_string(title)).into_bytes(),
+    ];
+    for (index, sheet) in sheets.iter().enumerate() {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                number(SHEET_WIDTH),
+                number(SHEET_HEIGHT),
+                7 + 2 * index
+            )
+            .into_bytes(),
+        );
+        let data = compress_to_vec_zlib(sheet.as_bytes(), 6);
+        let mut contents = format!("<< /Length {} /Filter /FlateDecode >>\nstream\n", data.len()).into_bytes();
+        contents.extend_from_slice(&data);
+        contents.extend_from_slice(b"\nendstream");
+        objects.push(contents);
+    }
+
+    // The comment's high bytes mark the file as binary
+    let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::new();
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R /Info 5 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drawing::Stroke;
+    use miniz_oxide::inflate::decompress_to_vec_zlib;
+
+    /// The decompressed content stream of each sheet.
+    fn sheet_streams(pdf: &[u8]) -> Vec<String> {
+        let mut streams = Vec::new();
+        let mut rest = pdf;
+        while let Some(start) = rest.windows(8).position(|window| window == b"stream\n\x78") {
+            let data = &rest[start + 7..];
+            let end = data.windows(10).position(|window| window == b"\nendstream").unwrap();
+            streams.push(String::from_utf8(decompress_to_vec_zlib(&data[..end]).unwrap()).unwrap());
+            rest = &data[end..];
+        }
+        streams
+    }
+
+    #[test]
+    fn test_pages_are_laid_out_on_letter_sheets() {
+        let mut notebook = Notebook::new("Field (Notes)".to_string());
+        let long = (1..=50).map(|line| format!("Line {}", line)).collect::<Vec<_>>().join("\n");
+        notebook.add_page(Page::new("Plans".to_string(), long, None));
+        let mut sketch = Page::new("Café ☕".to_string(), String::new(), None);
+        sketch.drawings.push(Drawing::from_strokes(vec![Stroke {
+            points: vec![[0.0, 0.0], [100.0, 40.0]],
+            width: 4.0,
+            color: [255, 0, 0, 255],
+        }]));
+        notebook.add_page(sketch);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = export_notebook(&notebook, &dir.path().join("notes.pdf")).unwrap();
+        let pdf = fs::read(path).unwrap();
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+
+        // Every cross-reference entry points at its object
+        let start = pdf.windows(10).rposition(|window| window == b"startxref\n").unwrap();
+        let trailer = String::from_utf8_lossy(&pdf[start + 10..]);
+        let xref_offset: usize = trailer.lines().next().unwrap().parse().unwrap();
+        let xref = String::from_utf8_lossy(&pdf[xref_offset..]);
+        assert!(xref.starts_with("xref\n0 12\n"));
+        for (index, entry) in xref.lines().skip(3).take(11).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", index + 1).as_bytes()));
+        }
+
+        let streams = sheet_streams(&pdf);
+        assert_eq!(streams.len(), 3);
+        assert!(streams[0].contains("BT /F2 9.75 Tf 72 744 Td (Plans) Tj ET"));
+        assert!(streams[0].contains("(Line 1) Tj"));
+        assert!(streams[0].contains("(Line 46) Tj"));
+        assert!(!streams[0].contains("(Line 47) Tj"));
+        assert!(streams[0].contains("BT /F1 9.75 Tf 72 42 Td (Page 1 \\(1 of 2\\)) Tj ET"));
+        assert!(streams[1].contains("BT /F1 9.75 Tf 72 710.25 Td (Line 47) Tj ET"));
+        assert!(streams[2].contains("(Caf\\351 ?) Tj"));
+        assert!(streams[2].contains("(Page 2) Tj"));
+        assert!(streams[2].contains("1 0 0 RG 3 w 73.5 718.5 m 148.5 688.5 l S"));
+    }
+
+    #[test]
+    fn test_wrap_text_breaks_at_spaces() {
+        let words = "word ".repeat(20);
+        let lines = wrap_text(&format!("{}\n\ttab\n{}", words.trim_end(), "x".repeat(170)));
+        assert_eq!(lines[0], "word ".repeat(16).trim_end());
+        assert_eq!(lines[1], "word word word word");
+        assert_eq!(lines[2], "    tab");
+        assert_eq!(lines[3].len(), PAGE_MAX_CHARS_PER_LINE);
+        assert_eq!(lines[5].len(), 10);
+    }
+
+    #[test]
+    fn test_strings_use_windows_1252() {
+        assert_eq!(pdf_string("a(b)\\"), "a\\(b\\)\\\\");
+        assert_eq!(pdf_string("€ é 日"), "\\200 \\351 ?");
+        assert_eq!(pdf_text_string("Hi"), "<FEFF00480069>");
+    }
+}
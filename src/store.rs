@@ -0,0 +1,632 @@
+use crate::file_io::{self, Diagnostic, NotebookFileHandler};
+use crate::notebook::{Notebook, PageSource};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+/// Version of the JSON notebook files written by [`JsonStore`].
+pub const JSON_FORMAT_VERSION: u32 = 1;
+
+/// Version of the folder layout written by [`DirectoryStore`]. Page files
+/// are in the page format of the current text format version.
+pub const DIRECTORY_FORMAT_VERSION: u32 = 1;
+
+/// Extension of folder notebooks.
+pub const DIRECTORY_EXTENSION: &str = "nbx";
+
+/// Notebook title, timestamps and page order of a folder notebook.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+const PAGES_DIRECTORY: &str = "pages";
+
+/// A way of keeping notebooks on disk.
+pub trait NotebookStore {
+    /// Load a notebook together with the problems that were recovered from
+    /// while reading it. Strict stores fail on the first problem instead.
+    fn load_notebook(&self, path: PathBuf) -> io::Result<(Notebook, Vec<Diagnostic>)>;
+
+    /// Load a notebook's pages without their content, attachments and
+    /// drawings where the store can, to be read later with
+    /// [`NotebookFileHandler::load_page`].
+    fn load_notebook_index(&self, path: PathBuf) -> io::Result<(Notebook, Vec<Diagnostic>)> {
+        self.load_notebook(path)
+    }
+
+    /// Save a notebook, which may have pages that are not loaded yet.
+    fn save_notebook(&self, notebook: &mut Notebook, path: PathBuf) -> io::Result<()>;
+}
+
+/// The store for `path`, picked by extension: JSON for `.json` files, a
+/// folder notebook for `.nbx` folders and the manifest inside them, and the
+/// text format for anything else. Backups open with the store of the file
+/// they were taken of. `settings` supplies strictness and backup count.
+pub fn store_for_path(path: &Path, settings: &NotebookFileHandler) -> Box<dyn NotebookStore> {
+    let path = file_io::backed_up_file(path).unwrap_or_else(|| path.to_path_buf());
+    if DirectoryStore::folder(&path).is_some() {
+        Box::new(DirectoryStore { strict: settings.strict })
+    } else if has_extension(&path, "json") {
+        Box::new(JsonStore {
+            backups: settings.backups,
+            backup_minutes: settings.backup_minutes,
+        })
+    } else {
+        Box::new(settings.clone())
+    }
+}
+
+impl NotebookStore for NotebookFileHandler {
+    fn load_notebook(&self, path: PathBuf) -> io::Result<(Notebook, Vec<Diagnostic>)> {
+        NotebookFileHandler::load_notebook(self, path)
+    }
+
+    fn load_notebook_index(&self, path: PathBuf) -> io::Result<(Notebook, Vec<Diagnostic>)> {
+        NotebookFileHandler::load_notebook_index(self, path)
+    }
+
+    fn save_notebook(&self, notebook: &mut Notebook, path: PathBuf) -> io::Result<()> {
+        NotebookFileHandler::save_notebook(self, notebook, path)
+    }
+}
+
+/// Size and modification time of a notebook's files, compared to notice
+/// changes made by other programs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    files: usize,
+}
+
+impl FileStamp {
+    /// The stamp of the notebook at `path`. For folder notebooks it covers
+    /// the manifest and every page file.
+    pub fn of(path: &Path) -> io::Result<Self> {
+        let Some(folder) = DirectoryStore::folder(path) else {
+            return Ok(Self::from(fs::metadata(path)?));
+        };
+        let mut stamp = Self::from(fs::metadata(folder.join(MANIFEST_FILE))?);
+        for entry in fs::read_dir(folder.join(PAGES_DIRECTORY))? {
+            let page_stamp = Self::from(entry?.metadata()?);
+            stamp.modified = stamp.modified.max(page_stamp.modified);
+            stamp.len += page_stamp.len;
+            stamp.files += 1;
+        }
+        Ok(stamp)
+    }
+}
+
+impl From<fs::Metadata> for FileStamp {
+    fn from(metadata: fs::Metadata) -> Self {
+        Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            files: 1,
+        }
+    }
+}
+
+/// A notebook as a single JSON file.
+#[derive(Clone, Debug)]
+pub struct JsonStore {
+    /// Timestamped `.bak` copies of earlier saves to keep, as for text files
+    pub backups: usize,
+    /// Minutes between backups, as for text files
+    pub backup_minutes: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonNotebook<T> {
+    format_version: u32,
+    notebook: T,
+}
+
+/// Just the version of a JSON notebook or manifest, read before the rest so
+/// that files from newer versions are refused instead of misread.
+#[derive(Deserialize)]
+struct FormatVersion {
+    format_version: u32,
+}
+
+impl NotebookStore for JsonStore {
+    fn load_notebook(&self, path: PathBuf) -> io::Result<(Notebook, Vec<Diagnostic>)> {
+        let text = fs::read_to_string(&path)?;
+        let version: FormatVersion = serde_json::from_str(&text).map_err(|e| json_error(e, None))?;
+        check_version("JSON notebook", version.format_version, JSON_FORMAT_VERSION)?;
+
+        let file: JsonNotebook<Notebook> = serde_json::from_str(&text).map_err(|e| json_error(e, None))?;
+        let mut notebook = file.notebook;
+        notebook.repair_hierarchy();
+        Ok((notebook, Vec::new()))
+    }
+
+    fn save_notebook(&self, notebook: &mut Notebook, path: PathBuf) -> io::Result<()> {
+        NotebookFileHandler::new().load_all_pages(notebook)?;
+        let file = JsonNotebook {
+            format_version: JSON_FORMAT_VERSION,
+            notebook,
+        };
+        let mut text = serde_json::to_string_pretty(&file)?;
+        text.push('\n');
+        file_io::back_up(&path, self.backups, self.backup_minutes)?;
+        file_io::write_atomically(&path, text.as_bytes())
+    }
+}
+
+/// A notebook as a folder with a manifest and one text file per page, so
+/// that version control shows changes page by page:
+///
+/// ```text
+/// Notes.nbx/
+///   manifest.json
+///   pages/<page id>.txt
+/// ```
+#[derive(Clone, Debug)]
+pub struct DirectoryStore {
+    /// Refuse notebooks with any problem instead of loading what can be recovered
+    pub strict: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    format_version: u32,
+    id: String,
+    title: String,
+    created: DateTime<Utc>,
+    modified: DateTime<Utc>,
+    /// Page files in notebook order, relative to the pages folder
+    pages: Vec<String>,
+}
+
+impl DirectoryStore {
+    /// The notebook folder `path` refers to: the folder itself or its manifest.
+    pub fn folder(path: &Path) -> Option<PathBuf> {
+        if has_extension(path, DIRECTORY_EXTENSION) {
+            return Some(path.to_path_buf());
+        }
+        let folder = path.parent()?;
+        (path.file_name()? == MANIFEST_FILE && has_extension(folder, DIRECTORY_EXTENSION)).then(|| folder.to_path_buf())
+    }
+
+    fn report(&self, diagnostics: &mut Vec<Diagnostic>, file: PathBuf, message: String) -> io::Result<()> {
+        let diagnostic = Diagnostic {
+            file: Some(file),
+            line: 1,
+            column: 1,
+            message,
+        };
+        if self.strict {
+            return Err(diagnostic.into());
+        }
+        diagnostics.push(diagnostic);
+        Ok(())
+    }
+}
+
+impl DirectoryStore {
+    /// Read the manifest of a notebook folder and list its page files
+    /// relative to the folder, in notebook order.
+    fn read_listing(&self, folder: &Path) -> io::Result<(Notebook, Vec<PathBuf>, Vec<Diagnostic>)> {
+        let text = fs::read_to_string(folder.join(MANIFEST_FILE))?;
+        let manifest_error = |e| json_error(e, Some(PathBuf::from(MANIFEST_FILE)));
+        let version: FormatVersion = serde_json::from_str(&text).map_err(manifest_error)?;
+        check_version("notebook folder", version.format_version, DIRECTORY_FORMAT_VERSION)?;
+        let manifest: Manifest = serde_json::from_str(&text).map_err(manifest_error)?;
+
+        let mut diagnostics = Vec::new();
+        let mut files = Vec::new();
+        let mut listed = HashSet::new();
+        for name in &manifest.pages {
+            // Manifests come from clones and shared folders, so an entry
+            // must not lead out of the pages folder
+            if !is_page_file_name(name) {
+                self.report(
+                    &mut diagnostics,
+                    PathBuf::from(MANIFEST_FILE),
+                    format!("Page file {:?} is not a file name in the pages folder; skipped", name),
+                )?;
+                continue;
+            }
+            let relative = Path::new(PAGES_DIRECTORY).join(name);
+            listed.insert(name.to_lowercase());
+            match fs::metadata(folder.join(&relative)) {
+                Ok(_) => files.push(relative),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    self.report(&mut diagnostics, relative, "Page file listed in the manifest is missing".to_string())?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        // Pages added outside NotebookX, for example by a merge, go at the end
+        let mut unlisted = Vec::new();
+        for entry in fs::read_dir(folder.join(PAGES_DIRECTORY))? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type()?.is_file() && has_extension(Path::new(&name), "txt") && !listed.contains(&name.to_lowercase()) {
+                unlisted.push(name);
+            }
+        }
+        unlisted.sort();
+        for name in unlisted {
+            let relative = Path::new(PAGES_DIRECTORY).join(&name);
+            self.report(
+                &mut diagnostics,
+                relative.clone(),
+                "Page file is not listed in the manifest; added at the end".to_string(),
+            )?;
+            files.push(relative);
+        }
+
+        let notebook = Notebook {
+            id: manifest.id,
+            title: manifest.title,
+            pages: Vec::new(),
+            created: manifest.created,
+            modified: manifest.modified,
+        };
+        Ok((notebook, files, diagnostics))
+    }
+
+    fn handler(&self) -> NotebookFileHandler {
+        NotebookFileHandler {
+            strict: self.strict,
+            ..NotebookFileHandler::new()
+        }
+    }
+}
+
+impl NotebookStore for DirectoryStore {
+    fn load_notebook(&self, path: PathBuf) -> io::Result<(Notebook, Vec<Diagnostic>)> {
+        let folder = Self::folder(&path).unwrap_or(path);
+        let (mut notebook, files, mut diagnostics) = self.read_listing(&folder)?;
+        let files = files
+            .into_iter()
+            .map(|relative| Ok((relative.clone(), fs::read_to_string(folder.join(relative))?)))
+            .collect::<io::Result<Vec<_>>>()?;
+        diagnostics.extend(self.handler().parse_page_files(&mut notebook, &files)?);
+        Ok((notebook, diagnostics))
+    }
+
+    /// Reads only the metadata block of each page file. In strict mode the
+    /// whole notebook is loaded, so that every problem is found up front.
+    fn load_notebook_index(&self, path: PathBuf) -> io::Result<(Notebook, Vec<Diagnostic>)> {
+        if self.strict {
+            return self.load_notebook(path);
+        }
+        let folder = Self::folder(&path).unwrap_or(path);
+        let (mut notebook, files, mut diagnostics) = self.read_listing(&folder)?;
+        let files: Vec<(PathBuf, PathBuf)> = files
+            .into_iter()
+            .map(|relative| (relative.clone(), folder.join(relative)))
+            .collect();
+        diagnostics.extend(self.handler().index_page_files(&mut notebook, &files)?);
+        Ok((notebook, diagnostics))
+    }
+
+    /// Only page files whose text changed are written, so that unchanged
+    /// pages keep their timestamps and cost no more than a read.
+    fn save_notebook(&self, notebook: &mut Notebook, path: PathBuf) -> io::Result<()> {
+        let folder = Self::folder(&path).unwrap_or(path);
+        let pages_folder = folder.join(PAGES_DIRECTORY);
+        fs::create_dir_all(&pages_folder)?;
+
+        let handler = NotebookFileHandler::new();
+        let mut used = HashSet::new();
+        let mut names = Vec::new();
+        for page in &mut notebook.pages {
+            let name = page_file_name(&page.id, &mut used);
+            let page_path = pages_folder.join(&name);
+            let existing = fs::read_to_string(&page_path).ok();
+            let text = match (&page.source, &existing) {
+                // A page not loaded yet from this very file is read only once
+                (Some(PageSource::File(source)), Some(existing)) if *source == page_path => {
+                    handler.serialize_stored_page(page, existing)?
+                }
+                _ => handler.serialize_page(page)?,
+            };
+            if existing.as_deref() != Some(text.as_str()) {
+                file_io::write_atomically(&page_path, text.as_bytes())?;
+            }
+            if page.source.is_some() {
+                page.source = Some(PageSource::File(page_path));
+            }
+            names.push(name);
+        }
+
+        let manifest = Manifest {
+            format_version: DIRECTORY_FORMAT_VERSION,
+            id: notebook.id.clone(),
+            title: notebook.title.clone(),
+            created: notebook.created,
+            modified: notebook.modified,
+            pages: names,
+        };
+        let mut text = serde_json::to_string_pretty(&manifest)?;
+        text.push('\n');
+        file_io::write_atomically(&folder.join(MANIFEST_FILE), text.as_bytes())?;
+
+        // Page files of deleted pages go once the manifest no longer lists them
+        for entry in fs::read_dir(&pages_folder)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            if entry.file_type()?.is_file() && has_extension(Path::new(&name), "txt") && !used.contains(&name) {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether a manifest entry names a `.txt` file directly inside the pages
+/// folder, without any directory parts.
+fn is_page_file_name(name: &str) -> bool {
+    let path = Path::new(name);
+    let mut components = path.components();
+    matches!((components.next(), components.next()), (Some(Component::Normal(_)), None))
+        && !name.contains(['/', '\\'])
+        && has_extension(path, "txt")
+}
+
+/// A file name for a page, made from its ID and unique among `used`
+/// regardless of case, as file systems on Windows and macOS ignore it.
+fn page_file_name(id: &str, used: &mut HashSet<String>) -> String {
+    let stem: String = id
+        .chars()
+        .take(64)
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    let stem = if stem.is_empty() { "page".to_string() } else { stem };
+    let mut name = format!("{}.txt", stem);
+    let mut count = 1;
+    while !used.insert(name.to_lowercase()) {
+        count += 1;
+        name = format!("{}-{}.txt", stem, count);
+    }
+    name
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+fn check_version(kind: &str, version: u32, supported: u32) -> io::Result<()> {
+    if version == 0 || version > supported {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "This {} uses format version {}, but this version of NotebookX only reads up to version {}. Please update NotebookX to open it.",
+                kind, version, supported
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// A JSON syntax or data error as a [`Diagnostic`] with its position.
+fn json_error(error: serde_json::Error, file: Option<PathBuf>) -> io::Error {
+    if error.is_io() {
+        return error.into();
+    }
+    let message = error.to_string();
+    // serde_json appends the position, which the diagnostic already holds
+    let message = message.rsplit_once(" at line ").map_or(message.as_str(), |(message, _)| message);
+    Diagnostic {
+        file,
+        line: error.line(),
+        column: error.column(),
+        message: message.to_string(),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notebook::{Attachment, Page};
+
+    fn sample_notebook() -> Notebook {
+        let mut notebook = Notebook::new("Projects".to_string());
+        let mut parent = Page::new("Plans".to_string(), "--- PAGE BREAK ---\nstill plans".to_string(), Some(1));
+        parent.attachments.push(Attachment {
+            name: "notes.bin".to_string(),
+            mime_type: "application/octet-stream".to_string(),
+            data: vec![0, 1, 2, 255],
+        });
+        let mut child = Page::new("Details".to_string(), "Nested".to_string(), Some(2));
+        child.parent_id = Some(parent.id.clone());
+        notebook.pages = vec![parent, child];
+        notebook
+    }
+
+    fn as_json(notebook: &Notebook) -> serde_json::Value {
+        serde_json::to_value(notebook).unwrap()
+    }
+
+    #[test]
+    fn test_json_store_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Projects.json");
+        let mut notebook = sample_notebook();
+        let store = store_for_path(&path, &NotebookFileHandler::new());
+        store.save_notebook(&mut notebook, path.clone()).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.contains("\"format_version\": 1"));
+        assert!(text.contains("\"data\": \"AAEC/w==\""));
+        let (loaded, diagnostics) = store.load_notebook(path.clone()).unwrap();
+        assert!(diagnostics.is_empty());
+        assert_eq!(as_json(&loaded), as_json(&notebook));
+
+        fs::write(&path, text.replace("\"format_version\": 1", "\"format_version\": 2")).unwrap();
+        let error = store.load_notebook(path.clone()).unwrap_err();
+        assert!(error.to_string().contains("format version 2"));
+
+        fs::write(&path, "{\n  \"format_version\": 1,\n  \"notebook\": nope\n}").unwrap();
+        let error = store.load_notebook(path).unwrap_err();
+        let diagnostic = error.get_ref().and_then(|e| e.downcast_ref::<Diagnostic>()).unwrap();
+        assert_eq!((diagnostic.line, diagnostic.column), (3, 16));
+    }
+
+    #[test]
+    fn test_directory_store_writes_one_file_per_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("Projects.nbx");
+        let mut notebook = sample_notebook();
+        let store = store_for_path(&folder, &NotebookFileHandler::new());
+        store.save_notebook(&mut notebook, folder.clone()).unwrap();
+
+        let page_file = |page: &Page| folder.join(PAGES_DIRECTORY).join(format!("{}.txt", page.id));
+        assert!(folder.join(MANIFEST_FILE).is_file());
+        assert!(page_file(&notebook.pages[0]).is_file());
+        assert!(page_file(&notebook.pages[1]).is_file());
+
+        // Opening the manifest opens the folder
+        let (loaded, diagnostics) = store_for_path(&folder.join(MANIFEST_FILE), &NotebookFileHandler::new())
+            .load_notebook(folder.join(MANIFEST_FILE))
+            .unwrap();
+        assert!(diagnostics.is_empty());
+        assert_eq!(as_json(&loaded), as_json(&notebook));
+
+        let removed = notebook.pages.remove(1);
+        store.save_notebook(&mut notebook, folder.clone()).unwrap();
+        assert!(!page_file(&removed).exists());
+        assert!(page_file(&notebook.pages[0]).is_file());
+    }
+
+    #[test]
+    fn test_directory_store_recovers_from_changed_page_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("Projects.nbx");
+        let mut notebook = sample_notebook();
+        let store = DirectoryStore { strict: false };
+        store.save_notebook(&mut notebook, folder.clone()).unwrap();
+
+        let pages = folder.join(PAGES_DIRECTORY);
+        fs::remove_file(pages.join(format!("{}.txt", notebook.pages[0].id))).unwrap();
+        let extra = Page::new("Merged".to_string(), "From a branch".to_string(), None);
+        fs::write(pages.join("merged.txt"), NotebookFileHandler::new().serialize_page(&extra).unwrap()).unwrap();
+
+        let (loaded, diagnostics) = store.load_notebook(folder.clone()).unwrap();
+        let titles: Vec<&str> = loaded.pages.iter().map(|page| page.title.as_str()).collect();
+        assert_eq!(titles, vec!["Details", "Merged"]);
+        // The subpage lost its parent with the missing file
+        assert_eq!(loaded.pages[0].parent_id, None);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].ends_with("line 1, column 1: Page file listed in the manifest is missing"));
+        assert!(messages[1].contains("merged.txt"));
+        assert!(messages[2].contains("moved to the top level"));
+
+        assert!(DirectoryStore { strict: true }.load_notebook(folder).is_err());
+    }
+
+    #[test]
+    fn test_directory_store_reads_only_files_in_the_pages_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("Projects.nbx");
+        let mut notebook = sample_notebook();
+        let store = DirectoryStore { strict: false };
+        store.save_notebook(&mut notebook, folder.clone()).unwrap();
+
+        let outside = Page::new("Outside".to_string(), "Not part of the notebook".to_string(), None);
+        fs::write(dir.path().join("outside.txt"), NotebookFileHandler::new().serialize_page(&outside).unwrap()).unwrap();
+        let manifest_path = folder.join(MANIFEST_FILE);
+        let mut manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        let pages = manifest["pages"].as_array_mut().unwrap();
+        for name in ["../outside.txt", "/etc/passwd", "sub/page.txt", "..\\outside.txt"] {
+            pages.push(name.into());
+        }
+        fs::write(&manifest_path, manifest.to_string()).unwrap();
+
+        for (loaded, diagnostics) in [
+            store.load_notebook(folder.clone()).unwrap(),
+            store.load_notebook_index(folder.clone()).unwrap(),
+        ] {
+            assert_eq!(loaded.pages.len(), notebook.pages.len());
+            assert!(loaded.pages.iter().all(|page| page.title != "Outside"));
+            assert_eq!(diagnostics.len(), 4);
+            assert!(diagnostics[0].to_string().contains("\"../outside.txt\" is not a file name in the pages folder"));
+        }
+        assert!(DirectoryStore { strict: true }.load_notebook(folder).is_err());
+        assert!(is_page_file_name("0b8f.txt"));
+        assert!(!is_page_file_name("notes.md"));
+    }
+
+    #[test]
+    fn test_directory_store_only_rewrites_changed_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("Projects.nbx");
+        let store = DirectoryStore { strict: false };
+        store.save_notebook(&mut sample_notebook(), folder.clone()).unwrap();
+
+        let (mut index, diagnostics) = store.load_notebook_index(folder.clone()).unwrap();
+        assert!(diagnostics.is_empty());
+        assert!(index.pages.iter().all(|page| page.source.is_some() && page.content.is_empty()));
+        let page_files: Vec<PathBuf> = index
+            .pages
+            .iter()
+            .map(|page| folder.join(PAGES_DIRECTORY).join(format!("{}.txt", page.id)))
+            .collect();
+        // A marker shows which files get written again
+        for file in &page_files {
+            fs::write(file, fs::read_to_string(file).unwrap().replace("Nested", "Nested!")).unwrap();
+        }
+
+        index.pages[0].title = "Renamed".to_string();
+        store.save_notebook(&mut index, folder.clone()).unwrap();
+        assert!(fs::read_to_string(&page_files[0]).unwrap().contains("TITLE: Renamed"));
+        assert!(fs::read_to_string(&page_files[1]).unwrap().contains("Nested!"));
+
+        // Only the page edited behind the store's back fails its checksum
+        let (loaded, diagnostics) = store.load_notebook(folder).unwrap();
+        assert_eq!(loaded.pages[0].attachments, sample_notebook().pages[0].attachments);
+        assert_eq!(loaded.pages[1].content, "Nested!");
+        assert_eq!(diagnostics.len(), 1);
+        assert!(page_files[1].ends_with(diagnostics[0].file.as_ref().unwrap()));
+    }
+
+    #[test]
+    fn test_file_stamp_notices_changed_page_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("Projects.nbx");
+        let mut notebook = sample_notebook();
+        DirectoryStore { strict: false }.save_notebook(&mut notebook, folder.clone()).unwrap();
+
+        let stamp = FileStamp::of(&folder.join(MANIFEST_FILE)).unwrap();
+        assert_eq!(FileStamp::of(&folder).unwrap(), stamp);
+        let page_file = folder.join(PAGES_DIRECTORY).join(format!("{}.txt", notebook.pages[1].id));
+        fs::write(&page_file, fs::read_to_string(&page_file).unwrap() + "More\n").unwrap();
+        assert_ne!(FileStamp::of(&folder).unwrap(), stamp);
+    }
+
+    #[test]
+    fn test_page_file_names_are_unique_and_safe() {
+        let mut used = HashSet::new();
+        assert_eq!(page_file_name("../Secret Page", &mut used), "___Secret_Page.txt");
+        assert_eq!(page_file_name("../secret page", &mut used), "___secret_page-2.txt");
+        assert_eq!(page_file_name("", &mut used), "page.txt");
+    }
+
+    #[test]
+    fn test_backups_open_with_the_store_of_their_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Projects.json");
+        let settings = NotebookFileHandler::new();
+        store_for_path(&path, &settings).save_notebook(&mut sample_notebook(), path.clone()).unwrap();
+        store_for_path(&path, &settings).save_notebook(&mut sample_notebook(), path.clone()).unwrap();
+
+        let backup = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| has_extension(path, "bak"))
+            .unwrap();
+        let (loaded, _) = store_for_path(&backup, &settings).load_notebook(backup).unwrap();
+        assert_eq!(loaded.title, "Projects");
+    }
+}
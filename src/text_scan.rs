@@ -0,0 +1,336 @@
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+/// Runs shorter than this many characters are treated as noise.
+const MIN_RUN_CHARS: usize = 4;
+
+/// Score a run needs to be kept; see `classify` for how characters score.
+const MIN_RUN_SCORE: i32 = 12;
+
+/// Binary data right after a string decodes to a short tail stuck to it;
+/// tails up to this many characters are checked and cut off.
+const MAX_NOISE_TAIL_CHARS: usize = 8;
+
+/// Longer runs are emitted in pieces so one run cannot grow without bound.
+const MAX_RUN_CHARS: usize = 64 * 1024;
+
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Iterator over the UTF-16LE text runs found in a stream of arbitrary
+/// bytes, such as a OneNote file that could not be parsed. Runs are looked
+/// for at both even and odd offsets, decoded as full Unicode including
+/// surrogate pairs, and scored so runs that look like binary noise are
+/// dropped. The stream is read in chunks.
+pub struct Utf16Runs<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    /// The byte before the first unread one, paired with it to form a code unit
+    previous: Option<u8>,
+    /// Alignment of the next code unit: 0 for even offsets, 1 for odd
+    alignment: usize,
+    runs: [RunBuilder; 2],
+    ready: VecDeque<String>,
+    finished: bool,
+}
+
+impl<R: Read> Utf16Runs<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: vec![0; READ_CHUNK_SIZE],
+            previous: None,
+            alignment: 0,
+            runs: [RunBuilder::default(), RunBuilder::default()],
+            ready: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    fn scan(&mut self, bytes_read: usize) {
+        for index in 0..bytes_read {
+            let byte = self.buffer[index];
+            if let Some(previous) = self.previous {
+                let unit = u16::from_le_bytes([previous, byte]);
+                if let Some(run) = self.runs[self.alignment].push(unit) {
+                    self.ready.push_back(run);
+                }
+                self.alignment ^= 1;
+            }
+            self.previous = Some(byte);
+        }
+    }
+}
+
+impl<R: Read> Iterator for Utf16Runs<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(run) = self.ready.pop_front() {
+                return Some(Ok(run));
+            }
+            if self.finished {
+                return None;
+            }
+
+            match self.reader.read(&mut self.buffer) {
+                Ok(0) => {
+                    self.finished = true;
+                    for run in &mut self.runs {
+                        if let Some(text) = run.finish() {
+                            self.ready.push_back(text);
+                        }
+                    }
+                }
+                Ok(bytes_read) => self.scan(bytes_read),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// The run being decoded at one alignment.
+#[derive(Default)]
+struct RunBuilder {
+    text: String,
+    chars: usize,
+    pending_high_surrogate: Option<u16>,
+}
+
+impl RunBuilder {
+    /// Add one code unit, returning the run it ended if it was worth keeping.
+    fn push(&mut self, unit: u16) -> Option<String> {
+        if let Some(high) = self.pending_high_surrogate.take() {
+            if (0xDC00..=0xDFFF).contains(&unit) {
+                let code = 0x10000 + ((u32::from(high) - 0xD800) << 10) + (u32::from(unit) - 0xDC00);
+                return char::from_u32(code).and_then(|ch| self.push_char(ch));
+            }
+            // An unpaired high surrogate ends the run and `unit` starts the next
+            let ended = self.finish();
+            let next = self.push(unit);
+            return ended.or(next);
+        }
+        if (0xD800..=0xDBFF).contains(&unit) {
+            self.pending_high_surrogate = Some(unit);
+            return None;
+        }
+
+        match char::from_u32(u32::from(unit)) {
+            Some(ch) if is_text_char(ch) => self.push_char(ch),
+            _ => self.finish(),
+        }
+    }
+
+    fn push_char(&mut self, ch: char) -> Option<String> {
+        self.text.push(ch);
+        self.chars += 1;
+        if self.chars >= MAX_RUN_CHARS {
+            self.finish()
+        } else {
+            None
+        }
+    }
+
+    /// End the current run, returning its trimmed text if it looks like text.
+    fn finish(&mut self) -> Option<String> {
+        let text = std::mem::take(&mut self.text);
+        self.chars = 0;
+        self.pending_high_surrogate = None;
+
+        let text = trim_noise_tail(text.trim()).trim_end();
+        looks_like_text(text).then(|| text.to_string())
+    }
+}
+
+/// Cut off a short tail of Han, Hangul or stray characters stuck directly
+/// to a letter of another script, when the tail does not read as text by
+/// itself. Such tails are what the bytes following a string decode to.
+fn trim_noise_tail(text: &str) -> &str {
+    let tail: Vec<(usize, char)> = text.char_indices().rev().take(MAX_NOISE_TAIL_CHARS + 1).collect();
+    for pair in tail.windows(2).rev() {
+        let (index, ch) = pair[0];
+        let (_, before) = pair[1];
+        let (before_script, before_weight) = classify(before);
+        let strong_letter = before_weight >= 3
+            && !before.is_whitespace()
+            && !matches!(before_script, Some(Script::Cjk | Script::Hangul));
+        if strong_letter && !ch.is_whitespace() && classify(ch).1 <= 1 && !looks_like_text(&text[index..]) {
+            return &text[..index];
+        }
+    }
+    text
+}
+
+/// Characters that may appear inside a run: anything but control
+/// characters, private use characters and noncharacters.
+fn is_text_char(ch: char) -> bool {
+    if ch.is_control() {
+        return matches!(ch, '\t' | '\n' | '\r');
+    }
+    !matches!(ch, '\u{E000}'..='\u{F8FF}' | '\u{FDD0}'..='\u{FDEF}' | '\u{FFFE}' | '\u{FFFF}')
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Hebrew,
+    Arabic,
+    Indic,
+    Thai,
+    /// Han ideographs and Japanese kana
+    Cjk,
+    Hangul,
+}
+
+/// The script of a letter, if it is one, and how strongly the character
+/// suggests real text. Random 16-bit values rarely land in small Unicode
+/// blocks such as ASCII or kana, so those score high; Han and Hangul cover
+/// about half of all values and score low; anything outside the blocks
+/// notes are written in counts against the run.
+fn classify(ch: char) -> (Option<Script>, i32) {
+    match ch {
+        'a'..='z' | 'A'..='Z' => (Some(Script::Latin), 4),
+        ' '..='~' | '\t' | '\n' | '\r' => (None, 4),
+        '\u{A0}'..='\u{BF}' | '\u{D7}' | '\u{F7}' => (None, 3),
+        '\u{C0}'..='\u{24F}' | '\u{1E00}'..='\u{1EFF}' => (Some(Script::Latin), 3),
+        '\u{370}'..='\u{3FF}' | '\u{1F00}'..='\u{1FFF}' => (Some(Script::Greek), 3),
+        '\u{400}'..='\u{52F}' => (Some(Script::Cyrillic), 3),
+        '\u{590}'..='\u{5FF}' => (Some(Script::Hebrew), 3),
+        '\u{600}'..='\u{6FF}' => (Some(Script::Arabic), 3),
+        '\u{900}'..='\u{DFF}' => (Some(Script::Indic), 3),
+        '\u{E00}'..='\u{E7F}' => (Some(Script::Thai), 3),
+        '\u{2000}'..='\u{206F}' | '\u{20A0}'..='\u{20CF}' | '\u{2190}'..='\u{21FF}' => (None, 1),
+        '\u{3000}'..='\u{303F}' | '\u{FF01}'..='\u{FF5E}' => (None, 3),
+        '\u{3040}'..='\u{30FF}' => (Some(Script::Cjk), 4),
+        '\u{4E00}'..='\u{9FFF}' => (Some(Script::Cjk), 1),
+        '\u{AC00}'..='\u{D7A3}' => (Some(Script::Hangul), 1),
+        '\u{1F300}'..='\u{1FAFF}' => (None, 1),
+        _ => (None, -4),
+    }
+}
+
+/// Score a decoded run. Besides the per-character score, real text uses at
+/// most two scripts (never Han mixed with Hangul, which random data does
+/// constantly) and is not one character repeated, as fill patterns are.
+/// Text read one byte off its real alignment repeats one low byte, the
+/// high byte of its real script block, and is dropped too.
+fn looks_like_text(text: &str) -> bool {
+    let mut chars = 0;
+    let mut score = 0;
+    let mut scripts: Vec<Script> = Vec::new();
+    for ch in text.chars() {
+        chars += 1;
+        let (script, weight) = classify(ch);
+        score += weight;
+        if let Some(script) = script {
+            if !scripts.contains(&script) {
+                scripts.push(script);
+            }
+        }
+    }
+    if chars < MIN_RUN_CHARS || score < MIN_RUN_SCORE || scripts.len() > 2 {
+        return false;
+    }
+    if scripts.contains(&Script::Cjk) && scripts.contains(&Script::Hangul) {
+        return false;
+    }
+    let first = text.chars().next();
+    if text.chars().all(|ch| Some(ch) == first) {
+        return false;
+    }
+
+    let mut low_bytes = [0usize; 256];
+    let mut units = 0;
+    for unit in text.encode_utf16() {
+        low_bytes[usize::from(unit as u8)] += 1;
+        units += 1;
+    }
+    low_bytes.iter().all(|count| count * 2 <= units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn scan(data: &[u8]) -> Vec<String> {
+        Utf16Runs::new(data).collect::<io::Result<Vec<_>>>().unwrap()
+    }
+
+    #[test]
+    fn test_finds_runs_at_both_alignments() {
+        // Null-terminated strings behind length fields, one of them odd-aligned
+        let mut data = vec![0x1E, 0x00, 0x00, 0x00];
+        data.extend(utf16("Größenänderung\0"));
+        data.extend([0x2C, 0x00, 0x00, 0x00, 0x00]);
+        data.extend(utf16("Сегодня было солнечно\0"));
+        data.extend([0x0E, 0x00, 0x00, 0x00]);
+        data.extend(utf16("日本語のメモ\0"));
+
+        assert_eq!(scan(&data), vec!["Größenänderung", "Сегодня было солнечно", "日本語のメモ"]);
+    }
+
+    #[test]
+    fn test_cuts_noise_stuck_to_strings() {
+        let mut data = utf16("Meeting Notes");
+        data.extend([0x00, 0x80, 0xA0, 0x9B, 0x4B, 0x82, 0xD8, 0x01, 0x00, 0x00]);
+        data.extend(utf16("Excelファイル\0"));
+        assert_eq!(scan(&data), vec!["Meeting Notes", "Excelファイル"]);
+    }
+
+    #[test]
+    fn test_decodes_surrogate_pairs() {
+        let mut data = utf16("Notes 🎉 𠮷野家");
+        data.extend([0x00, 0xD8, 0x41, 0x00]);
+        assert_eq!(scan(&data), vec!["Notes 🎉 𠮷野家"]);
+    }
+
+    #[test]
+    fn test_drops_fill_patterns() {
+        assert!(scan(&[0x41; 4096]).is_empty());
+    }
+
+    #[test]
+    fn test_drops_binary_noise() {
+        // A simple xorshift generator standing in for compressed image data
+        let mut state = 0x2545_F491_4F6C_DD1D_u64;
+        let noise: Vec<u8> = (0..64 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        let runs = scan(&noise);
+        assert!(runs.len() < 8, "kept {} noise runs: {:?}", runs.len(), runs);
+    }
+
+    #[test]
+    fn test_has_no_cap_on_runs() {
+        let mut data = Vec::new();
+        for index in 0..200 {
+            data.extend(utf16(&format!("Paragraph number {}", index)));
+            data.extend([0x00, 0x00]);
+        }
+        let runs = scan(&data);
+        assert_eq!(runs.len(), 200);
+        assert_eq!(runs[199], "Paragraph number 199");
+    }
+
+    #[test]
+    fn test_reads_across_chunk_boundaries() {
+        let mut data = vec![0x00; READ_CHUNK_SIZE - 3];
+        data.extend(utf16("Spanning the boundary"));
+        assert_eq!(scan(&data), vec!["Spanning the boundary"]);
+    }
+}